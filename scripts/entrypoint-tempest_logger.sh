@@ -1,3 +1,3 @@
 #!/bin/bash
 
-/opt/vineiq/bin/tempest_logger --config /opt/vineiq/etc/tempest.yaml 
+/opt/vineiq/bin/vineiq tempest --config /opt/vineiq/etc/tempest.yaml 
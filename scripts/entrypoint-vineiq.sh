@@ -0,0 +1,3 @@
+#!/bin/bash
+
+/opt/vineiq/bin/vineiq run --config /opt/vineiq/etc/vineiq.yaml 
@@ -1,3 +1,3 @@
 #!/bin/bash
 
-/opt/vineiq/bin/yolink_logger --config /opt/vineiq/etc/yolink.yaml 
+/opt/vineiq/bin/vineiq yolink --config /opt/vineiq/etc/yolink.yaml 
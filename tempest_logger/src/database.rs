@@ -1,88 +1,234 @@
-extern crate serde_derive;
+use vineiq_core::intake::Intake;
+use vineiq_core::units::Units;
+use vineiq_core::{derived, DeadLetter, Observation, Recorder, Result, Sink};
 
-use questdb::{
-    ingress::{Buffer, Sender, TimestampMicros, TimestampNanos},
-    Result,
+use crate::model::{
+    AirObservation, EventReport, Message, Observations, PrecipitationEvent, RapidWindReport,
+    SkyObservation, StationObservation, StrikeEvent,
 };
-use serde_json::Value;
+
+const MICROS: i64 = 1000000;
 
 pub struct Appender {
-    db_appender: Sender,
+    intake: Intake,
 }
 
 impl Appender {
-    pub fn new(db_url: &String) -> Appender {
-        let db_appender = Sender::from_conf(format!("tcp::addr={db_url};"));
+    pub fn new(sink: Box<dyn Sink + Send>, dead_letter: DeadLetter) -> Appender {
         Appender {
-            db_appender: db_appender.expect("Error: failed to connecto to questdb"),
+            intake: Intake::new("tempest", sink, dead_letter),
         }
     }
 
-    pub fn event_lightning(&mut self, json_object: &Value) -> Result<()> {
-        println!("NOT IMPLEMENT => event_lightning: {}", json_object);
-        Ok(())
+    pub fn tick(&mut self) {
+        self.intake.tick();
     }
 
-    pub fn event_precipitation(&mut self, json_object: &Value) -> Result<()> {
-        println!("NOT IMPLEMENT => event_precipitation: {}", json_object);
-        Ok(())
+    pub fn shutdown(&mut self) {
+        self.intake.shutdown();
     }
 
-    pub fn observation_air(&mut self, json_object: &Value) -> Result<()> {
-        println!("NOT IMPLEMENT => observation_air: {}", json_object);
-        Ok(())
+    pub fn set_recorder(&mut self, recorder: Recorder) {
+        self.intake.set_recorder(recorder);
+    }
+
+    pub fn set_units(&mut self, units: Units) {
+        self.intake.set_units(units);
+    }
+
+    pub fn rejected(&self) -> u64 {
+        self.intake.rejected()
+    }
+
+    //
+    // a message that cannot be parsed or written goes to the dead-letter file
+    // instead of stopping the logger
+    //
+    pub fn log_record(&mut self, msg: &str) {
+        self.intake.record(msg);
+        if let Err(e) = self.process_record(msg) {
+            self.intake.reject(msg, &e);
+        }
+    }
+
+    fn process_record(&mut self, msg: &str) -> Result<()> {
+        match serde_json::from_str::<Message>(msg)? {
+            Message::Air(report) => self.observation_air(&report),
+            Message::Sky(report) => self.observation_sky(&report),
+            Message::Station(report) => self.observation_station(&report),
+            Message::RapidWind(report) => self.rapid_wind(&report),
+            Message::Strike(report) => self.event_lightning(&report),
+            Message::Precipitation(report) => self.event_precipitation(&report),
+            Message::Ack => {
+                println!("ack: {}", msg);
+                Ok(())
+            }
+            Message::Other => {
+                println!("status: {}\n", msg);
+                Ok(())
+            }
+        }
+    }
+
+    /*
+      {
+        "serial_number": "ST-00000512",
+        "type": "rapid_wind",
+        "hub_sn": "HB-00013030",
+        "ob": [1588948614, 0.27, 144]
+      }
+    */
+    pub fn rapid_wind(&mut self, report: &RapidWindReport) -> Result<()> {
+        let data = &report.ob;
+        let units = self.intake.units();
+
+        let mut observation = Observation::new("tempest_wind", data.time * MICROS);
+        observation
+            .symbol("device_id", &report.device.0)
+            .symbol("units", &units.label())
+            .column_opt_f64("wind_speed", data.wind_speed.map(|v| units.wind(v)))
+            .column_opt_f64("wind_dir", data.wind_direction);
+
+        self.intake.write(&observation)
+    }
+
+    /*
+      {
+        "device_id": 1110,
+        "evt": [1493322445, 27, 3848],
+        "type": "evt_strike"
+      }
+    */
+    pub fn event_lightning(&mut self, report: &EventReport<StrikeEvent>) -> Result<()> {
+        let data = &report.evt;
+        let units = self.intake.units();
+
+        println!("event_lightning: {:?}", data);
+        let mut observation = Observation::new("tempest_strike", data.time * MICROS);
+        observation
+            .symbol("device_id", &report.device.0)
+            .symbol("units", &units.label())
+            .column_opt_f64("distance", data.distance.map(|v| units.distance(v)))
+            .column_opt_f64("energy", data.energy);
+
+        self.intake.write(&observation)
+    }
+
+    /*
+      {
+        "device_id": 1110,
+        "evt": [1493322445],
+        "type": "evt_precip"
+      }
+    */
+    pub fn event_precipitation(&mut self, report: &EventReport<PrecipitationEvent>) -> Result<()> {
+        let data = &report.evt;
+
+        println!("event_precipitation: {:?}", data);
+        let mut observation = Observation::new("tempest_precip", data.time * MICROS);
+        observation
+            .symbol("device_id", &report.device.0)
+            .symbol("units", &self.intake.units().label());
+
+        self.intake.write(&observation)
     }
 
-    pub fn observation_sky(&mut self, json_object: &Value) -> Result<()> {
-        println!("NOT IMPLEMENT => observation_sky: {}", json_object);
+    pub fn observation_air(&mut self, report: &Observations<AirObservation>) -> Result<()> {
+        let units = self.intake.units();
+        for data in &report.obs {
+            println!("observation_air: {:?}", data);
+            let mut observation = Observation::new("tempest_air", data.time * MICROS);
+            observation
+                .symbol("device_id", &report.device.0)
+                .symbol("units", &units.label())
+                .column_opt_f64("pressure", data.pressure.map(|v| units.pressure(v)));
+            derived::append_climate(&mut observation, &units, data.temperature, data.humidity);
+            observation
+                .column_opt_f64("light_count", data.lightning_count)
+                .column_opt_f64(
+                    "light_dist",
+                    data.lightning_distance.map(|v| units.distance(v)),
+                )
+                .column_opt_f64("battery", data.battery)
+                .column_opt_f64("report_int", data.report_interval);
+
+            self.intake.write(&observation)?;
+        }
         Ok(())
     }
 
-    pub fn to_fahrenheit(&mut self, celcius: f64) -> Option<f64> {
-        Some((celcius * 1.8) + 32.0)
+    pub fn observation_sky(&mut self, report: &Observations<SkyObservation>) -> Result<()> {
+        let units = self.intake.units();
+        for data in &report.obs {
+            println!("observation_sky: {:?}", data);
+            let mut observation = Observation::new("tempest_sky", data.time * MICROS);
+            observation
+                .symbol("device_id", &report.device.0)
+                .symbol("units", &units.label())
+                .column_opt_f64("luminance", data.illuminance)
+                .column_opt_f64("uv", data.uv)
+                .column_opt_f64("rain_accum", data.rain_accumulated.map(|v| units.rain(v)))
+                .column_opt_f64("wind_lull", data.wind_lull.map(|v| units.wind(v)))
+                .column_opt_f64("wind_avg", data.wind_avg.map(|v| units.wind(v)))
+                .column_opt_f64("wind_gust", data.wind_gust.map(|v| units.wind(v)))
+                .column_opt_f64("wind_dir", data.wind_direction)
+                .column_opt_f64("battery", data.battery)
+                .column_opt_f64("report_int", data.report_interval)
+                .column_opt_f64("radiation", data.solar_radiation)
+                .column_opt_f64(
+                    "local_rain_accum",
+                    data.local_rain_accumulated.map(|v| units.rain(v)),
+                )
+                .column_opt_f64("precip_type", data.precipitation_type)
+                .column_opt_f64("wind_interval", data.wind_interval);
+
+            self.intake.write(&observation)?;
+        }
+        Ok(())
     }
 
-    pub fn observation_station(&mut self, json_object: &Value) -> Result<()> {
-        let device_id = &json_object["device_id"]
-            .as_i64()
-            .expect("Error missing device id");
-        let data = &json_object["obs"][0];
-        let time_ms = data[0].as_i64().unwrap() * 1000000;
-        let fahrenheit = self
-            .to_fahrenheit(data[7].as_f64().unwrap())
-            .unwrap();
-
-        println!("observation_station: {}", data);
-        let mut buffer = Buffer::new();
-        buffer
-            .table("tempest_station")?
-            .symbol("device_id", device_id.to_string())?
-            .column_f64("wind_lull", data[1].as_f64().unwrap())?
-            .column_f64("wind_avg", data[2].as_f64().unwrap())?
-            .column_f64("wind_gust", data[3].as_f64().unwrap())?
-            .column_f64("wind_dir", data[4].as_f64().unwrap())?
-            .column_f64("wind_interval", data[5].as_f64().unwrap())?
-            .column_f64("pressure", data[6].as_f64().unwrap())?
-            .column_f64("temperature", fahrenheit)?
-            .column_f64("humidity", data[8].as_f64().unwrap())?
-            .column_f64("luminance", data[9].as_f64().unwrap())?
-            .column_f64("uv", data[10].as_f64().unwrap())?
-            .column_f64("radiation", data[10].as_f64().unwrap())?
-            .column_f64("rain_accum", data[12].as_f64().unwrap())?
-            .column_f64("precip_type", data[13].as_f64().unwrap())?
-            .column_f64("light_dist", data[14].as_f64().unwrap())?
-            .column_f64("light_count", data[15].as_f64().unwrap())?
-            .column_f64("battery", data[16].as_f64().unwrap())?
-            .column_f64("report_int", data[17].as_f64().unwrap())?
-            .column_f64("local_rain_accum", data[18].as_f64().unwrap())?
-            //
-            // intentionally omitted array items 19,20,21
-            //
-            .column_ts("time", TimestampMicros::new(time_ms))?
-            .at(TimestampNanos::now())?;
-
-        self.db_appender.flush(&mut buffer)?;
+    pub fn observation_station(&mut self, report: &Observations<StationObservation>) -> Result<()> {
+        let units = self.intake.units();
+        for data in &report.obs {
+            println!("observation_station: {:?}", data);
+            let mut observation = Observation::new("tempest_station", data.time * MICROS);
+            observation
+                .symbol("device_id", &report.device.0)
+                .symbol("units", &units.label())
+                .column_opt_f64("wind_lull", data.wind_lull.map(|v| units.wind(v)))
+                .column_opt_f64("wind_avg", data.wind_avg.map(|v| units.wind(v)))
+                .column_opt_f64("wind_gust", data.wind_gust.map(|v| units.wind(v)))
+                .column_opt_f64("wind_dir", data.wind_direction)
+                .column_opt_f64("wind_interval", data.wind_interval)
+                .column_opt_f64("pressure", data.pressure.map(|v| units.pressure(v)));
+            derived::append_climate(&mut observation, &units, data.temperature, data.humidity);
+            derived::append_feels_like(
+                &mut observation,
+                &units,
+                data.temperature,
+                data.humidity,
+                data.wind_avg,
+            );
+            observation
+                .column_opt_f64("luminance", data.illuminance)
+                .column_opt_f64("uv", data.uv)
+                .column_opt_f64("radiation", data.solar_radiation)
+                .column_opt_f64("rain_accum", data.rain_accumulated.map(|v| units.rain(v)))
+                .column_opt_f64("precip_type", data.precipitation_type)
+                .column_opt_f64(
+                    "light_dist",
+                    data.lightning_distance.map(|v| units.distance(v)),
+                )
+                .column_opt_f64("light_count", data.lightning_count)
+                .column_opt_f64("battery", data.battery)
+                .column_opt_f64("report_int", data.report_interval)
+                .column_opt_f64(
+                    "local_rain_accum",
+                    data.local_rain_accumulated.map(|v| units.rain(v)),
+                );
 
+            self.intake.write(&observation)?;
+        }
         Ok(())
     }
 }
@@ -0,0 +1,44 @@
+//!
+//! WeatherFlow Tempest logger
+//!
+
+pub mod database;
+pub mod model;
+pub mod tempest;
+
+use std::sync::atomic::AtomicBool;
+use vineiq_core::units::Units;
+use vineiq_core::{Recorder, Sink};
+
+//
+// log the station until shutdown is set, from the cloud websocket or the
+// hub's local UDP broadcast
+//
+pub async fn run(
+    conf: &mut tempest::Conf,
+    sink: Box<dyn Sink + Send>,
+    recorder: Option<Recorder>,
+    shutdown: &AtomicBool,
+) {
+    let dead_letter = conf.get_dead_letter().open("tempest");
+    let mut db_appender = database::Appender::new(sink, dead_letter);
+    db_appender.set_units(Units::from(&conf.get_units()));
+    if let Some(recorder) = recorder {
+        db_appender.set_recorder(recorder);
+    }
+
+    match conf.get_source().as_str() {
+        "udp" => {
+            let mut data_logger = tempest::UdpDatabaseLogger::new(&conf.get_udp_address());
+            data_logger.udp_listen(&mut db_appender, shutdown).await;
+        }
+        _ => {
+            let mut data_logger = tempest::WebsocketDatabaseLogger::new(
+                &conf.get_websocket_url(),
+                &conf.get_access_token(),
+                &conf.get_device_id(),
+            );
+            data_logger.ws_connect(&mut db_appender, shutdown).await;
+        }
+    }
+}
@@ -0,0 +1,317 @@
+//!
+//! Typed Tempest messages, as sent by the websocket and the hub's UDP broadcast
+//!
+//! The observations themselves are positional arrays. They are read by index
+//! in one place here so that a shorter UDP layout, a null reading or a firmware
+//! adding items at the end does not break parsing.
+//!
+
+use serde_derive::Deserialize;
+
+#[derive(Deserialize, Debug, PartialEq)]
+#[serde(tag = "type")]
+pub enum Message {
+    #[serde(rename = "obs_st")]
+    Station(Observations<StationObservation>),
+    #[serde(rename = "obs_air")]
+    Air(Observations<AirObservation>),
+    #[serde(rename = "obs_sky")]
+    Sky(Observations<SkyObservation>),
+    #[serde(rename = "rapid_wind")]
+    RapidWind(RapidWindReport),
+    #[serde(rename = "evt_strike")]
+    Strike(EventReport<StrikeEvent>),
+    #[serde(rename = "evt_precip")]
+    Precipitation(EventReport<PrecipitationEvent>),
+    #[serde(rename = "ack")]
+    Ack,
+    #[serde(other)]
+    Other,
+}
+
+//
+// the websocket identifies a device by its numeric device_id, while the
+// local UDP broadcast only carries the device serial_number
+//
+#[derive(Deserialize)]
+struct DeviceFields {
+    device_id: Option<i64>,
+    serial_number: Option<String>,
+}
+
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+#[serde(try_from = "DeviceFields")]
+pub struct DeviceId(pub String);
+
+impl TryFrom<DeviceFields> for DeviceId {
+    type Error = String;
+
+    fn try_from(fields: DeviceFields) -> Result<Self, Self::Error> {
+        match (fields.device_id, fields.serial_number) {
+            (Some(device_id), _) => Ok(DeviceId(device_id.to_string())),
+            (None, Some(serial_number)) => Ok(DeviceId(serial_number)),
+            (None, None) => Err("missing device_id or serial_number".to_string()),
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+pub struct Observations<T> {
+    #[serde(flatten)]
+    pub device: DeviceId,
+    pub obs: Vec<T>,
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+pub struct RapidWindReport {
+    #[serde(flatten)]
+    pub device: DeviceId,
+    pub ob: RapidWind,
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+pub struct EventReport<T> {
+    #[serde(flatten)]
+    pub device: DeviceId,
+    pub evt: T,
+}
+
+fn at(values: &[Option<f64>], index: usize) -> Option<f64> {
+    values.get(index).copied().flatten()
+}
+
+fn epoch(values: &[Option<f64>]) -> Result<i64, String> {
+    at(values, 0)
+        .map(|time| time as i64)
+        .ok_or_else(|| "missing timestamp".to_string())
+}
+
+/*
+  obs_st, 18 items over UDP and 22 over the websocket:
+    0 time epoch (s), 1 wind lull (m/s), 2 wind avg (m/s), 3 wind gust (m/s),
+    4 wind direction (deg), 5 wind sample interval (s), 6 station pressure (mb),
+    7 air temperature (C), 8 relative humidity (%), 9 illuminance (lux),
+    10 UV index, 11 solar radiation (W/m^2), 12 rain accumulated (mm),
+    13 precipitation type, 14 lightning strike avg distance (km),
+    15 lightning strike count, 16 battery (V), 17 report interval (min),
+    18 local day rain accumulation (mm), 19 rain accumulated final,
+    20 local day rain accumulation final, 21 precipitation analysis type
+*/
+#[derive(Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(try_from = "Vec<Option<f64>>")]
+pub struct StationObservation {
+    pub time: i64,
+    pub wind_lull: Option<f64>,
+    pub wind_avg: Option<f64>,
+    pub wind_gust: Option<f64>,
+    pub wind_direction: Option<f64>,
+    pub wind_interval: Option<f64>,
+    pub pressure: Option<f64>,
+    pub temperature: Option<f64>,
+    pub humidity: Option<f64>,
+    pub illuminance: Option<f64>,
+    pub uv: Option<f64>,
+    pub solar_radiation: Option<f64>,
+    pub rain_accumulated: Option<f64>,
+    pub precipitation_type: Option<f64>,
+    pub lightning_distance: Option<f64>,
+    pub lightning_count: Option<f64>,
+    pub battery: Option<f64>,
+    pub report_interval: Option<f64>,
+    pub local_rain_accumulated: Option<f64>,
+    pub rain_accumulated_final: Option<f64>,
+    pub local_rain_accumulated_final: Option<f64>,
+    pub precipitation_analysis_type: Option<f64>,
+}
+
+impl TryFrom<Vec<Option<f64>>> for StationObservation {
+    type Error = String;
+
+    fn try_from(values: Vec<Option<f64>>) -> Result<Self, Self::Error> {
+        Ok(Self {
+            time: epoch(&values)?,
+            wind_lull: at(&values, 1),
+            wind_avg: at(&values, 2),
+            wind_gust: at(&values, 3),
+            wind_direction: at(&values, 4),
+            wind_interval: at(&values, 5),
+            pressure: at(&values, 6),
+            temperature: at(&values, 7),
+            humidity: at(&values, 8),
+            illuminance: at(&values, 9),
+            uv: at(&values, 10),
+            solar_radiation: at(&values, 11),
+            rain_accumulated: at(&values, 12),
+            precipitation_type: at(&values, 13),
+            lightning_distance: at(&values, 14),
+            lightning_count: at(&values, 15),
+            battery: at(&values, 16),
+            report_interval: at(&values, 17),
+            local_rain_accumulated: at(&values, 18),
+            rain_accumulated_final: at(&values, 19),
+            local_rain_accumulated_final: at(&values, 20),
+            precipitation_analysis_type: at(&values, 21),
+        })
+    }
+}
+
+/*
+  obs_air:
+    0 time epoch (s), 1 station pressure (mb), 2 air temperature (C),
+    3 relative humidity (%), 4 lightning strike count,
+    5 lightning strike avg distance (km), 6 battery (V),
+    7 report interval (min)
+*/
+#[derive(Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(try_from = "Vec<Option<f64>>")]
+pub struct AirObservation {
+    pub time: i64,
+    pub pressure: Option<f64>,
+    pub temperature: Option<f64>,
+    pub humidity: Option<f64>,
+    pub lightning_count: Option<f64>,
+    pub lightning_distance: Option<f64>,
+    pub battery: Option<f64>,
+    pub report_interval: Option<f64>,
+}
+
+impl TryFrom<Vec<Option<f64>>> for AirObservation {
+    type Error = String;
+
+    fn try_from(values: Vec<Option<f64>>) -> Result<Self, Self::Error> {
+        Ok(Self {
+            time: epoch(&values)?,
+            pressure: at(&values, 1),
+            temperature: at(&values, 2),
+            humidity: at(&values, 3),
+            lightning_count: at(&values, 4),
+            lightning_distance: at(&values, 5),
+            battery: at(&values, 6),
+            report_interval: at(&values, 7),
+        })
+    }
+}
+
+/*
+  obs_sky:
+    0 time epoch (s), 1 illuminance (lux), 2 UV index,
+    3 rain accumulated (mm), 4 wind lull (m/s), 5 wind avg (m/s),
+    6 wind gust (m/s), 7 wind direction (deg), 8 battery (V),
+    9 report interval (min), 10 solar radiation (W/m^2),
+    11 local day rain accumulation (mm, null until the hub has synced),
+    12 precipitation type, 13 wind sample interval (s),
+    14 rain accumulated final, 15 local day rain accumulation final,
+    16 precipitation analysis type
+*/
+#[derive(Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(try_from = "Vec<Option<f64>>")]
+pub struct SkyObservation {
+    pub time: i64,
+    pub illuminance: Option<f64>,
+    pub uv: Option<f64>,
+    pub rain_accumulated: Option<f64>,
+    pub wind_lull: Option<f64>,
+    pub wind_avg: Option<f64>,
+    pub wind_gust: Option<f64>,
+    pub wind_direction: Option<f64>,
+    pub battery: Option<f64>,
+    pub report_interval: Option<f64>,
+    pub solar_radiation: Option<f64>,
+    pub local_rain_accumulated: Option<f64>,
+    pub precipitation_type: Option<f64>,
+    pub wind_interval: Option<f64>,
+    pub rain_accumulated_final: Option<f64>,
+    pub local_rain_accumulated_final: Option<f64>,
+    pub precipitation_analysis_type: Option<f64>,
+}
+
+impl TryFrom<Vec<Option<f64>>> for SkyObservation {
+    type Error = String;
+
+    fn try_from(values: Vec<Option<f64>>) -> Result<Self, Self::Error> {
+        Ok(Self {
+            time: epoch(&values)?,
+            illuminance: at(&values, 1),
+            uv: at(&values, 2),
+            rain_accumulated: at(&values, 3),
+            wind_lull: at(&values, 4),
+            wind_avg: at(&values, 5),
+            wind_gust: at(&values, 6),
+            wind_direction: at(&values, 7),
+            battery: at(&values, 8),
+            report_interval: at(&values, 9),
+            solar_radiation: at(&values, 10),
+            local_rain_accumulated: at(&values, 11),
+            precipitation_type: at(&values, 12),
+            wind_interval: at(&values, 13),
+            rain_accumulated_final: at(&values, 14),
+            local_rain_accumulated_final: at(&values, 15),
+            precipitation_analysis_type: at(&values, 16),
+        })
+    }
+}
+
+/*
+  rapid_wind ob: 0 time epoch (s), 1 wind speed (m/s), 2 wind direction (deg)
+*/
+#[derive(Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(try_from = "Vec<Option<f64>>")]
+pub struct RapidWind {
+    pub time: i64,
+    pub wind_speed: Option<f64>,
+    pub wind_direction: Option<f64>,
+}
+
+impl TryFrom<Vec<Option<f64>>> for RapidWind {
+    type Error = String;
+
+    fn try_from(values: Vec<Option<f64>>) -> Result<Self, Self::Error> {
+        Ok(Self {
+            time: epoch(&values)?,
+            wind_speed: at(&values, 1),
+            wind_direction: at(&values, 2),
+        })
+    }
+}
+
+/*
+  evt_strike evt: 0 time epoch (s), 1 distance (km), 2 energy
+*/
+#[derive(Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(try_from = "Vec<Option<f64>>")]
+pub struct StrikeEvent {
+    pub time: i64,
+    pub distance: Option<f64>,
+    pub energy: Option<f64>,
+}
+
+impl TryFrom<Vec<Option<f64>>> for StrikeEvent {
+    type Error = String;
+
+    fn try_from(values: Vec<Option<f64>>) -> Result<Self, Self::Error> {
+        Ok(Self {
+            time: epoch(&values)?,
+            distance: at(&values, 1),
+            energy: at(&values, 2),
+        })
+    }
+}
+
+/*
+  evt_precip evt: 0 time epoch (s)
+*/
+#[derive(Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(try_from = "Vec<Option<f64>>")]
+pub struct PrecipitationEvent {
+    pub time: i64,
+}
+
+impl TryFrom<Vec<Option<f64>>> for PrecipitationEvent {
+    type Error = String;
+
+    fn try_from(values: Vec<Option<f64>>) -> Result<Self, Self::Error> {
+        Ok(Self {
+            time: epoch(&values)?,
+        })
+    }
+}
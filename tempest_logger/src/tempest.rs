@@ -1,12 +1,29 @@
 //!
-//!
-//!
+//! WeatherFlow Tempest websocket and local UDP clients
 //!
 
 use crate::database::Appender;
+use futures_util::{SinkExt, StreamExt};
+use serde::de::DeserializeOwned;
 use serde_json::Value;
+use std::io::ErrorKind;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+use tokio::net::UdpSocket;
+use tokio_tungstenite::tungstenite::{self, Message};
 use url::Url;
+use vineiq_core::config::{BatchConfig, DeadLetterConfig, SpoolConfig};
+use vineiq_core::shutdown;
+use vineiq_core::units::UnitsConfig;
+
+const DEFAULT_UDP_ADDRESS: &str = "0.0.0.0:50222";
+const MIN_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(300);
+const STABLE_SESSION: Duration = Duration::from_secs(60);
+const READ_TIMEOUT: Duration = Duration::from_secs(300);
+const TICK_INTERVAL: Duration = Duration::from_secs(1);
 
+#[derive(Clone)]
 pub struct Conf {
     value: Value,
 }
@@ -15,7 +32,16 @@ impl Conf {
     pub fn new(config_file: &str) -> Self {
         let content = std::fs::read_to_string(config_file).unwrap();
         let value = serde_yaml::from_str::<Value>(&content).unwrap();
-        Self { value: value }
+        Self { value }
+    }
+    //
+    // the tempest section of the combined vineiq configuration
+    //
+    pub fn from_value(value: Value) -> Self {
+        Self { value }
+    }
+    pub fn into_value(self) -> Value {
+        self.value
     }
     pub fn get_access_token(&mut self) -> String {
         self.value["access_token"]
@@ -35,6 +61,46 @@ impl Conf {
             .expect("missing api url")
             .to_string()
     }
+    //
+    // "websocket" (default) or "udp"
+    //
+    pub fn get_source(&mut self) -> String {
+        self.value["source"]
+            .as_str()
+            .unwrap_or("websocket")
+            .to_string()
+    }
+    pub fn get_udp_address(&mut self) -> String {
+        self.value["udp_address"]
+            .as_str()
+            .unwrap_or(DEFAULT_UDP_ADDRESS)
+            .to_string()
+    }
+    pub fn get_spool(&mut self) -> SpoolConfig {
+        self.section("spool")
+    }
+    pub fn get_batch(&mut self) -> BatchConfig {
+        self.section("batch")
+    }
+    pub fn get_dead_letter(&mut self) -> DeadLetterConfig {
+        self.section("dead_letter")
+    }
+    pub fn get_units(&mut self) -> UnitsConfig {
+        self.section("units")
+    }
+    //
+    // the combined configuration sets the units once for every source
+    //
+    pub fn set_units(&mut self, units: &UnitsConfig) {
+        self.value["units"] = serde_json::to_value(units).expect("units serialize");
+    }
+    fn section<T: DeserializeOwned + Default>(&self, name: &str) -> T {
+        match &self.value[name] {
+            Value::Null => T::default(),
+            section => serde_json::from_value(section.clone())
+                .unwrap_or_else(|e| panic!("invalid {} section: {}", name, e)),
+        }
+    }
     pub fn get_questdb_url(&mut self) -> String {
         self.value["questdb"]
             .as_str()
@@ -50,58 +116,154 @@ pub struct WebsocketDatabaseLogger {
 }
 
 impl WebsocketDatabaseLogger {
-    pub fn new(websocket_url: &String, access_token: &String, device_id: &String) -> Self {
+    pub fn new(websocket_url: &str, access_token: &str, device_id: &str) -> Self {
         Self {
-            websocket_url: websocket_url.clone(),
-            access_token: access_token.clone(),
-            device_id: device_id.clone(),
+            websocket_url: websocket_url.to_string(),
+            access_token: access_token.to_string(),
+            device_id: device_id.to_string(),
         }
     }
 
-    pub fn ws_connect(&mut self, db_appender: &mut Appender) {
+    //
+    // supervise the websocket session, reconnecting with exponential backoff
+    // whenever the connection fails or the server closes it
+    //
+    pub async fn ws_connect(&mut self, db_appender: &mut Appender, shutdown: &AtomicBool) {
+        let mut backoff = MIN_BACKOFF;
+        loop {
+            let started = Instant::now();
+            let reason = match self.ws_session(db_appender, shutdown).await {
+                Ok(reason) => reason,
+                Err(e) => e.to_string(),
+            };
+            if shutdown.load(Ordering::Relaxed) {
+                return;
+            }
+            //
+            // a session that stayed up for a while starts over at the minimum delay
+            //
+            if started.elapsed() > STABLE_SESSION {
+                backoff = MIN_BACKOFF;
+            }
+            println!(
+                "websocket disconnected: {} -- reconnecting in {}s",
+                reason,
+                backoff.as_secs()
+            );
+            if !shutdown::pause(backoff, shutdown, || db_appender.tick()).await {
+                return;
+            }
+            backoff = (backoff * 2).min(MAX_BACKOFF);
+        }
+    }
+
+    async fn ws_session(
+        &mut self,
+        db_appender: &mut Appender,
+        shutdown: &AtomicBool,
+    ) -> Result<String, Box<tungstenite::Error>> {
         let ws_url =
             Url::parse(format!("{}?token={}", self.websocket_url, self.access_token).as_str())
                 .unwrap();
 
         println!("ws_url: {}", ws_url);
-        let (mut socket, response) = tungstenite::connect(ws_url).expect("Error connecting");
+        let (mut socket, response) = tokio_tungstenite::connect_async(ws_url.as_str()).await?;
         println!("Response HTTP code: {}", response.status());
 
         let listen_command = format!(
             "{{\"type\":\"listen_start\",\"device_id\": {},\"id\":\"vineiq-{}\"}}",
             self.device_id, self.device_id
         );
-        socket.send(listen_command.into()).expect("Error sending listen command");
+        socket.send(listen_command.into()).await?;
 
+        //
+        // wake up regularly to flush partial batches and notice a shutdown
+        //
+        let mut ticker = tokio::time::interval(TICK_INTERVAL);
+        let mut received_at = Instant::now();
         loop {
-            let msg: tungstenite::Message = socket.read().expect("Error reading message");
-            let msg = match msg {
-                tungstenite::Message::Text(s) => s,
-                _ => {
-                    panic!()
+            let msg = tokio::select! {
+                msg = socket.next() => msg,
+                _ = ticker.tick() => {
+                    if shutdown.load(Ordering::Relaxed) {
+                        let _ = socket.close(None).await;
+                        return Ok("shutting down".to_string());
+                    }
+                    db_appender.tick();
+                    //
+                    // the station reports every minute, so a silent socket is a dead one
+                    //
+                    if received_at.elapsed() > READ_TIMEOUT {
+                        return Ok(format!("no data for {}s", READ_TIMEOUT.as_secs()));
+                    }
+                    continue;
                 }
             };
-            let parsed: Value = serde_json::from_str(&msg).expect("Error parsing JSON");
-            let record_type = parsed["type"].to_string();
-            match record_type.as_str() {
-                "\"obs_air\"" => db_appender
-                    .observation_air(&parsed)
-                    .expect("Failed to insert record"),
-                "\"obs_sky\"" => db_appender
-                    .observation_sky(&parsed)
-                    .expect("Failed to insert record"),
-                "\"obs_st\"" => db_appender
-                    .observation_station(&parsed)
-                    .expect("Failed to insert record"),
-                "\"evt_strike\"" => db_appender
-                    .event_lightning(&parsed)
-                    .expect("Failed to insert record"),
-                "\"evt_precip\"" => db_appender
-                    .event_precipitation(&parsed)
-                    .expect("Failed to insert record"),
-                "\"ack\"" => println!("ack: {}", parsed),
-                _ => println!("status: {}\n", parsed),
+            let msg = match msg {
+                Some(msg) => msg?,
+                None => return Ok("connection closed".to_string()),
+            };
+            received_at = Instant::now();
+
+            match msg {
+                Message::Text(msg) => db_appender.log_record(&msg),
+                //
+                // tungstenite queues the pong reply itself and sends it on the next read
+                //
+                Message::Ping(_) | Message::Pong(_) => {}
+                Message::Close(frame) => {
+                    return Ok(match frame {
+                        Some(frame) => format!("server closed ({}: {})", frame.code, frame.reason),
+                        None => "server closed".to_string(),
+                    })
+                }
+                Message::Binary(_) | Message::Frame(_) => {
+                    println!("ignoring non-text websocket message");
+                }
             }
         }
     }
 }
+
+pub struct UdpDatabaseLogger {
+    address: String,
+}
+
+impl UdpDatabaseLogger {
+    pub fn new(address: &str) -> Self {
+        Self {
+            address: address.to_string(),
+        }
+    }
+
+    pub async fn udp_listen(&mut self, db_appender: &mut Appender, shutdown: &AtomicBool) {
+        println!("udp_address: {}", self.address);
+        let socket = UdpSocket::bind(&self.address)
+            .await
+            .expect("Error binding UDP socket");
+
+        let mut ticker = tokio::time::interval(TICK_INTERVAL);
+        let mut buf = [0u8; 4096];
+        while !shutdown.load(Ordering::Relaxed) {
+            let len = tokio::select! {
+                received = socket.recv_from(&mut buf) => match received {
+                    Ok((len, _)) => len,
+                    Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                    //
+                    // e.g. an ICMP unreachable or a full buffer; the next
+                    // broadcast is read as usual
+                    //
+                    Err(e) => {
+                        println!("Error reading datagram: {}", e);
+                        continue;
+                    }
+                },
+                _ = ticker.tick() => {
+                    db_appender.tick();
+                    continue;
+                }
+            };
+            db_appender.log_record(&String::from_utf8_lossy(&buf[..len]));
+        }
+    }
+}
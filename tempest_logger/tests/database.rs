@@ -0,0 +1,179 @@
+use std::time::Duration;
+
+use tempest_logger::database::Appender;
+use tempest_logger::model::Message;
+use vineiq_core::testing::{IlpReceiver, Line, Value};
+use vineiq_core::units::{self, Units};
+use vineiq_core::{derived, DeadLetter};
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+fn parse(payload: &str) -> Message {
+    serde_json::from_str(payload).expect("payload should parse")
+}
+
+fn appender(receiver: &IlpReceiver, name: &str) -> Appender {
+    Appender::new(Box::new(receiver.sink(name)), DeadLetter::new(name, None))
+}
+
+fn only_line(receiver: &IlpReceiver) -> Line {
+    let lines = receiver.wait_for(1, TIMEOUT);
+    assert_eq!(lines.len(), 1, "expected one row, got {:?}", lines);
+    lines.into_iter().next().unwrap()
+}
+
+#[test]
+fn station_observation_over_ilp() {
+    let receiver = IlpReceiver::start();
+    let mut appender = appender(&receiver, "tempest-station");
+    let Message::Station(report) = parse(include_str!("payloads/obs_st_websocket.json")) else {
+        panic!("expected obs_st");
+    };
+    appender.observation_station(&report).unwrap();
+
+    let line = only_line(&receiver);
+    assert_eq!(line.table, "tempest_station");
+    assert_eq!(
+        line.symbols,
+        vec![
+            ("device_id".to_string(), "1110".to_string()),
+            ("units".to_string(), "F,m/s,mb,mm,km".to_string()),
+        ]
+    );
+    let expected = [
+        ("wind_lull", Value::Float(0.18)),
+        ("wind_avg", Value::Float(0.22)),
+        ("wind_gust", Value::Float(0.27)),
+        ("wind_dir", Value::Float(144.0)),
+        ("wind_interval", Value::Float(6.0)),
+        ("pressure", Value::Float(1017.57)),
+        ("temperature", Value::Float(units::to_fahrenheit(22.37))),
+        ("humidity", Value::Float(50.26)),
+        ("vpd", Value::Float(derived::vpd(22.37, 50.26))),
+        (
+            "dew_point",
+            Value::Float(units::to_fahrenheit(
+                derived::dew_point(22.37, 50.26).unwrap(),
+            )),
+        ),
+        (
+            "heat_index",
+            Value::Float(units::to_fahrenheit(derived::heat_index(22.37, 50.26))),
+        ),
+        (
+            "wet_bulb",
+            Value::Float(units::to_fahrenheit(derived::wet_bulb(22.37, 50.26))),
+        ),
+        //
+        // too warm for a wind chill, too cool for the heat index
+        //
+        ("feels_like", Value::Float(units::to_fahrenheit(22.37))),
+        ("luminance", Value::Float(328.0)),
+        ("uv", Value::Float(0.03)),
+        ("radiation", Value::Float(3.0)),
+        ("rain_accum", Value::Float(0.0)),
+        ("precip_type", Value::Float(0.0)),
+        ("light_dist", Value::Float(0.0)),
+        ("light_count", Value::Float(0.0)),
+        ("battery", Value::Float(2.41)),
+        ("report_int", Value::Float(1.0)),
+        ("local_rain_accum", Value::Float(0.0)),
+        ("time", Value::Timestamp(1588948614000000)),
+    ];
+    let expected: Vec<(String, Value)> = expected
+        .into_iter()
+        .map(|(name, value)| (name.to_string(), value))
+        .collect();
+    assert_eq!(line.columns, expected);
+}
+
+#[test]
+fn udp_station_observation_leaves_out_missing_items() {
+    let receiver = IlpReceiver::start();
+    let mut appender = appender(&receiver, "tempest-udp");
+    let Message::Station(report) = parse(include_str!("payloads/obs_st_udp.json")) else {
+        panic!("expected obs_st");
+    };
+    appender.observation_station(&report).unwrap();
+
+    let line = only_line(&receiver);
+    assert_eq!(line.symbol("device_id"), Some("ST-00000512"));
+    assert_eq!(line.column("report_int"), Some(&Value::Float(1.0)));
+    assert_eq!(line.column("local_rain_accum"), None);
+    assert_eq!(line.column_names().last(), Some(&"time"));
+}
+
+#[test]
+fn lightning_strike_over_ilp() {
+    let receiver = IlpReceiver::start();
+    let mut appender = appender(&receiver, "tempest-strike");
+    let Message::Strike(report) = parse(include_str!("payloads/evt_strike.json")) else {
+        panic!("expected evt_strike");
+    };
+    appender.event_lightning(&report).unwrap();
+
+    let line = only_line(&receiver);
+    assert_eq!(line.table, "tempest_strike");
+    assert_eq!(line.symbol("device_id"), Some("1110"));
+    assert_eq!(line.column_names(), vec!["distance", "energy", "time"]);
+    assert_eq!(line.column("distance"), Some(&Value::Float(27.0)));
+    assert_eq!(line.column("energy"), Some(&Value::Float(3848.0)));
+    assert_eq!(
+        line.column("time"),
+        Some(&Value::Timestamp(1493322445000000))
+    );
+}
+
+#[test]
+fn station_observation_in_metric() {
+    let receiver = IlpReceiver::start();
+    let mut appender = appender(&receiver, "tempest-metric");
+    appender.set_units(Units::metric());
+    let Message::Station(report) = parse(include_str!("payloads/obs_st_websocket.json")) else {
+        panic!("expected obs_st");
+    };
+    appender.observation_station(&report).unwrap();
+
+    let line = only_line(&receiver);
+    assert_eq!(line.symbol("units"), Some("metric"));
+    assert_eq!(line.column("temperature"), Some(&Value::Float(22.37)));
+    assert_eq!(line.column("wind_avg"), Some(&Value::Float(0.22)));
+    assert_eq!(line.column("pressure"), Some(&Value::Float(1017.57)));
+}
+
+#[test]
+fn station_observation_in_imperial() {
+    let receiver = IlpReceiver::start();
+    let mut appender = appender(&receiver, "tempest-imperial");
+    let units = Units::imperial();
+    appender.set_units(units);
+    let Message::Station(report) = parse(include_str!("payloads/obs_st_websocket.json")) else {
+        panic!("expected obs_st");
+    };
+    appender.observation_station(&report).unwrap();
+
+    let line = only_line(&receiver);
+    assert_eq!(line.symbol("units"), Some("imperial"));
+    assert_eq!(
+        line.column("temperature"),
+        Some(&Value::Float(units.temperature(22.37)))
+    );
+    assert_eq!(
+        line.column("wind_gust"),
+        Some(&Value::Float(units.wind(0.27)))
+    );
+    assert_eq!(
+        line.column("pressure"),
+        Some(&Value::Float(units.pressure(1017.57)))
+    );
+    assert_eq!(line.column("rain_accum"), Some(&Value::Float(0.0)));
+    //
+    // humidity, the vapor pressure deficit and the wind direction keep their units
+    //
+    assert_eq!(line.column("humidity"), Some(&Value::Float(50.26)));
+    assert_eq!(
+        line.column("vpd"),
+        Some(&Value::Float(derived::vpd(22.37, 50.26)))
+    );
+    assert_eq!(line.column("wind_dir"), Some(&Value::Float(144.0)));
+}
@@ -0,0 +1,130 @@
+use tempest_logger::database::Appender;
+use tempest_logger::model::{DeviceId, Message};
+use vineiq_core::testing::CaptureSink;
+use vineiq_core::DeadLetter;
+
+fn parse(payload: &str) -> Message {
+    serde_json::from_str(payload).expect("payload should parse")
+}
+
+#[test]
+fn obs_st_websocket() {
+    let Message::Station(report) = parse(include_str!("payloads/obs_st_websocket.json")) else {
+        panic!("expected obs_st");
+    };
+    assert_eq!(report.device, DeviceId("1110".to_string()));
+    let obs = &report.obs[0];
+    assert_eq!(obs.time, 1588948614);
+    assert_eq!(obs.temperature, Some(22.37));
+    assert_eq!(obs.humidity, Some(50.26));
+    assert_eq!(obs.uv, Some(0.03));
+    assert_eq!(obs.solar_radiation, Some(3.0));
+    assert_eq!(obs.battery, Some(2.41));
+    assert_eq!(obs.local_rain_accumulated, Some(0.0));
+    assert_eq!(obs.rain_accumulated_final, None);
+    assert_eq!(obs.precipitation_analysis_type, Some(0.0));
+}
+
+#[test]
+fn obs_st_udp_is_shorter() {
+    let Message::Station(report) = parse(include_str!("payloads/obs_st_udp.json")) else {
+        panic!("expected obs_st");
+    };
+    assert_eq!(report.device, DeviceId("ST-00000512".to_string()));
+    let obs = &report.obs[0];
+    assert_eq!(obs.report_interval, Some(1.0));
+    assert_eq!(obs.local_rain_accumulated, None);
+}
+
+#[test]
+fn obs_air() {
+    let Message::Air(report) = parse(include_str!("payloads/obs_air.json")) else {
+        panic!("expected obs_air");
+    };
+    let obs = &report.obs[0];
+    assert_eq!(obs.time, 1493164835);
+    assert_eq!(obs.pressure, Some(835.0));
+    assert_eq!(obs.temperature, Some(10.0));
+    assert_eq!(obs.humidity, Some(45.0));
+    assert_eq!(obs.battery, Some(3.46));
+}
+
+#[test]
+fn obs_sky_with_null_local_rain() {
+    let Message::Sky(report) = parse(include_str!("payloads/obs_sky.json")) else {
+        panic!("expected obs_sky");
+    };
+    let obs = &report.obs[0];
+    assert_eq!(obs.illuminance, Some(9000.0));
+    assert_eq!(obs.solar_radiation, Some(130.0));
+    assert_eq!(obs.local_rain_accumulated, None);
+    assert_eq!(obs.wind_interval, Some(3.0));
+}
+
+#[test]
+fn events() {
+    let Message::RapidWind(report) = parse(include_str!("payloads/rapid_wind.json")) else {
+        panic!("expected rapid_wind");
+    };
+    assert_eq!(report.ob.wind_speed, Some(2.3));
+    assert_eq!(report.ob.wind_direction, Some(128.0));
+
+    let Message::Strike(report) = parse(include_str!("payloads/evt_strike.json")) else {
+        panic!("expected evt_strike");
+    };
+    assert_eq!(report.evt.distance, Some(27.0));
+    assert_eq!(report.evt.energy, Some(3848.0));
+
+    let Message::Precipitation(report) = parse(include_str!("payloads/evt_precip.json")) else {
+        panic!("expected evt_precip");
+    };
+    assert_eq!(report.evt.time, 1493322445);
+}
+
+#[test]
+fn status_messages() {
+    assert_eq!(parse(include_str!("payloads/ack.json")), Message::Ack);
+    assert_eq!(
+        parse(include_str!("payloads/connection_opened.json")),
+        Message::Other
+    );
+}
+
+#[test]
+fn missing_timestamp_is_rejected() {
+    let payload = r#"{"device_id":1110,"type":"evt_precip","evt":[null]}"#;
+    assert!(serde_json::from_str::<Message>(payload).is_err());
+}
+
+#[test]
+fn station_row_reads_radiation_not_uv() {
+    let capture = CaptureSink::default();
+    let mut appender = Appender::new(Box::new(capture.clone()), DeadLetter::new("tempest", None));
+    let Message::Station(report) = parse(include_str!("payloads/obs_st_websocket.json")) else {
+        panic!("expected obs_st");
+    };
+    appender.observation_station(&report).unwrap();
+
+    let rows = capture.rows();
+    let row = &rows[0];
+    assert_eq!(row.table, "tempest_station");
+    assert_eq!(row.time, 1588948614000000);
+    assert_eq!(row.get_symbol("device_id"), Some("1110"));
+    assert_eq!(row.get_f64("uv"), Some(0.03));
+    assert_eq!(row.get_f64("radiation"), Some(3.0));
+    assert_eq!(row.get_f64("humidity"), Some(50.26));
+    assert!((row.get_f64("temperature").unwrap() - 72.266).abs() < 1e-9);
+}
+
+#[test]
+fn bad_messages_are_rejected_not_fatal() {
+    let capture = CaptureSink::default();
+    let mut appender = Appender::new(Box::new(capture.clone()), DeadLetter::new("tempest", None));
+    appender.log_record("{\"type\":\"obs_st\"");
+    appender.log_record(r#"{"device_id":1110,"type":"obs_st","obs":[[1588948614,0.18]]}"#);
+    appender.log_record(r#"{"device_id":1110,"type":"evt_precip","evt":[null]}"#);
+    appender.log_record(include_str!("payloads/obs_air.json"));
+
+    assert_eq!(appender.rejected(), 2);
+    assert_eq!(capture.rows().len(), 2);
+}
@@ -0,0 +1,272 @@
+//!
+//! Alert rules and the notifiers they are sent to
+//!
+//!   alerts:
+//!     timezone: "America/Los_Angeles"   # of the quiet hours, UTC by default
+//!     rules:
+//!       - name: block-frost
+//!         table: yolink               # optional, any table by default
+//!         metric: temperature
+//!         match: { block: "*" }       # symbols the row must have, * and ? globs
+//!         below: 0.5                  # or above
+//!         temperature: celsius        # unit of the threshold, if a temperature
+//!         for_secs: 600               # how long before it fires
+//!         hysteresis: 0.5             # how far back it must go to resolve
+//!         quiet_hours: { from: "22:00", to: "06:00" }
+//!         severity: critical
+//!         notify: [crew]              # every notifier when left out
+//!     notifiers:
+//!       crew: { type: log }
+//!
+//! Notifiers are `log`, `webhook`, `mqtt` or `smtp`; see `crate::notify` for
+//! their settings.
+//!
+//! Thresholds are in the units the table is written in, unless a temperature
+//! unit is given for the rule.
+//!
+
+use chrono::NaiveTime;
+use chrono_tz::Tz;
+use serde_derive::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use vineiq_core::units::Temperature;
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct AlertsConfig {
+    pub timezone: String,
+    pub rules: Vec<Rule>,
+    pub notifiers: BTreeMap<String, NotifierConfig>,
+}
+
+impl Default for AlertsConfig {
+    fn default() -> Self {
+        Self {
+            timezone: "UTC".to_string(),
+            rules: Vec::new(),
+            notifiers: BTreeMap::new(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Rule {
+    pub name: String,
+    pub table: Option<String>,
+    pub metric: String,
+    #[serde(default, rename = "match")]
+    pub selector: BTreeMap<String, String>,
+    pub below: Option<f64>,
+    pub above: Option<f64>,
+    pub temperature: Option<Temperature>,
+    #[serde(default)]
+    pub for_secs: u64,
+    #[serde(default)]
+    pub hysteresis: f64,
+    pub quiet_hours: Option<QuietHours>,
+    #[serde(default = "default_severity")]
+    pub severity: String,
+    pub notify: Option<Vec<String>>,
+}
+
+fn default_severity() -> String {
+    "warning".to_string()
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Comparison {
+    Below,
+    Above,
+}
+
+impl Rule {
+    pub fn comparison(&self) -> Result<(Comparison, f64), String> {
+        match (self.below, self.above) {
+            (Some(threshold), None) => Ok((Comparison::Below, threshold)),
+            (None, Some(threshold)) => Ok((Comparison::Above, threshold)),
+            _ => Err(format!(
+                "alert rule {} needs one of below or above",
+                self.name
+            )),
+        }
+    }
+}
+
+//
+// in the timezone of the alerts section; the span may run past midnight
+//
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct QuietHours {
+    pub from: String,
+    pub to: String,
+}
+
+impl QuietHours {
+    pub fn window(&self) -> Result<(NaiveTime, NaiveTime), String> {
+        Ok((parse_time(&self.from)?, parse_time(&self.to)?))
+    }
+}
+
+fn parse_time(text: &str) -> Result<NaiveTime, String> {
+    NaiveTime::parse_from_str(text, "%H:%M")
+        .map_err(|e| format!("invalid quiet hours time {:?}: {}", text, e))
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct NotifierConfig {
+    #[serde(flatten)]
+    pub channel: ChannelConfig,
+    #[serde(default)]
+    pub retry: RetryConfig,
+    pub rate_limit: Option<RateLimit>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum ChannelConfig {
+    //
+    // printed with the rest of the log
+    //
+    Log,
+    Webhook(WebhookConfig),
+    Mqtt(MqttConfig),
+    Smtp(SmtpConfig),
+}
+
+//
+// a failed delivery is tried again after `backoff_secs`, doubling each time,
+// until it has been tried `attempts` times
+//
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct RetryConfig {
+    pub attempts: u32,
+    pub backoff_secs: f64,
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        Self {
+            attempts: 3,
+            backoff_secs: 2.0,
+        }
+    }
+}
+
+//
+// at most `count` alerts every `per_secs`; the rest are dropped
+//
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct RateLimit {
+    pub count: usize,
+    pub per_secs: u64,
+}
+
+//
+// the body is a JSON template; without one the alert is sent as JSON
+//
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct WebhookConfig {
+    pub url: String,
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    pub body: Option<Value>,
+    #[serde(default = "default_timeout_secs")]
+    pub timeout_secs: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct MqttConfig {
+    pub broker: String,
+    #[serde(default = "default_mqtt_port")]
+    pub port: u16,
+    pub topic: String,
+    #[serde(default = "default_client_id")]
+    pub client_id: String,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    #[serde(default)]
+    pub retain: bool,
+    pub body: Option<Value>,
+    #[serde(default = "default_timeout_secs")]
+    pub timeout_secs: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum SmtpTls {
+    None,
+    #[default]
+    Starttls,
+    Tls,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SmtpConfig {
+    pub host: String,
+    pub port: Option<u16>,
+    #[serde(default)]
+    pub tls: SmtpTls,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub from: String,
+    pub to: Vec<String>,
+    #[serde(default = "default_subject")]
+    pub subject: String,
+    #[serde(default = "default_text")]
+    pub text: String,
+    #[serde(default = "default_timeout_secs")]
+    pub timeout_secs: u64,
+}
+
+fn default_timeout_secs() -> u64 {
+    10
+}
+
+fn default_mqtt_port() -> u16 {
+    1883
+}
+
+fn default_client_id() -> String {
+    "vineiq-alerts".to_string()
+}
+
+fn default_subject() -> String {
+    "[{severity}] {rule} {state}".to_string()
+}
+
+fn default_text() -> String {
+    "{message}".to_string()
+}
+
+impl AlertsConfig {
+    //
+    // a mistake in the rules should stop the process at startup rather than
+    // go unnoticed until the night it matters
+    //
+    pub fn validate(&self) -> Result<(), String> {
+        self.get_timezone()?;
+        for rule in &self.rules {
+            rule.comparison()?;
+            if let Some(quiet) = &rule.quiet_hours {
+                quiet.window()?;
+            }
+            for name in rule.notify.iter().flatten() {
+                if !self.notifiers.contains_key(name) {
+                    return Err(format!(
+                        "alert rule {} notifies unknown {}",
+                        rule.name, name
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn get_timezone(&self) -> Result<Tz, String> {
+        self.timezone
+            .parse()
+            .map_err(|e| format!("invalid alerts timezone {:?}: {}", self.timezone, e))
+    }
+}
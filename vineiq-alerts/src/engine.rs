@@ -0,0 +1,376 @@
+//!
+//! Rule evaluation over the rows as they are written
+//!
+//! Every row is checked against every rule it matches. A sensor past the
+//! threshold for the rule's duration fires; it resolves once it is back
+//! past the threshold by the hysteresis. Time is the time of the readings,
+//! so a replay raises the same alerts as the live run did.
+//!
+//! A rule firing during its quiet hours is held, and sent with the first
+//! reading after they end if it is still firing; a firing that was never
+//! sent resolves without a notification.
+//!
+
+use chrono::{DateTime, NaiveTime};
+use chrono_tz::Tz;
+use std::collections::HashMap;
+use vineiq_core::units::Temperature;
+use vineiq_core::{Field, Observation};
+
+use crate::config::{AlertsConfig, Comparison, Rule};
+
+const MICROS: i64 = 1_000_000;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum AlertState {
+    Firing,
+    Resolved,
+}
+
+impl AlertState {
+    pub fn label(&self) -> &'static str {
+        match self {
+            AlertState::Firing => "firing",
+            AlertState::Resolved => "resolved",
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Alert {
+    pub rule: String,
+    pub severity: String,
+    pub state: AlertState,
+    pub table: String,
+    //
+    // the symbols that identify the sensor, e.g. sensorName and block
+    //
+    pub symbols: Vec<(String, String)>,
+    pub metric: String,
+    pub value: f64,
+    pub threshold: f64,
+    pub time: i64,
+    //
+    // how long the sensor has been past the threshold
+    //
+    pub duration_secs: i64,
+    //
+    // held for the rule's quiet hours, so recorded but not sent
+    //
+    pub quiet: bool,
+    pub notify: Option<Vec<String>>,
+}
+
+//
+// the symbols that name a sensor; the others, such as a YoLink device's
+// state, can change from one reading to the next
+//
+const SENSOR_SYMBOLS: [&str; 5] = ["device_id", "deviceId", "sensorName", "sensor", "block"];
+
+impl Alert {
+    pub fn sensor(&self) -> String {
+        self.symbols
+            .iter()
+            .map(|(name, value)| format!("{}={}", name, value))
+            .collect::<Vec<String>>()
+            .join(",")
+    }
+
+    pub fn message(&self) -> String {
+        let verb = match self.state {
+            AlertState::Firing => "fired",
+            AlertState::Resolved => "resolved",
+        };
+        format!(
+            "{} {}: {} {} is {:.1} (threshold {:.1}) on {}",
+            self.rule,
+            verb,
+            self.table,
+            self.metric,
+            self.value,
+            self.threshold,
+            self.sensor()
+        )
+    }
+
+    pub fn observation(&self) -> Observation {
+        let mut observation = Observation::new("alert", self.time);
+        observation
+            .symbol("rule", &self.rule)
+            .symbol("state", self.state.label())
+            .symbol("severity", &self.severity)
+            .symbol("source", &self.table);
+        for (name, value) in &self.symbols {
+            observation.symbol(name, value);
+        }
+        observation
+            .column_str("metric", &self.metric)
+            .column_f64("value", self.value)
+            .column_f64("threshold", self.threshold)
+            .column_i64("duration_secs", self.duration_secs)
+            .column_bool("notified", !self.quiet);
+        observation
+    }
+}
+
+//
+// a sensor past the threshold: since when, whether it has fired yet and
+// whether that was sent or held for quiet hours
+//
+struct Breach {
+    since: i64,
+    firing: bool,
+    notified: bool,
+}
+
+//
+// a rule with its threshold and quiet hours read once, up front
+//
+struct Checked {
+    rule: Rule,
+    comparison: Comparison,
+    threshold: f64,
+    quiet_hours: Option<(NaiveTime, NaiveTime)>,
+}
+
+pub struct Engine {
+    rules: Vec<Checked>,
+    timezone: Tz,
+    breaches: HashMap<(usize, Vec<(String, String)>), Breach>,
+}
+
+impl Engine {
+    pub fn new(config: &AlertsConfig) -> Result<Self, String> {
+        config.validate()?;
+        let mut rules = Vec::new();
+        for rule in &config.rules {
+            let (comparison, threshold) = rule.comparison()?;
+            let quiet_hours = match &rule.quiet_hours {
+                Some(quiet) => Some(quiet.window()?),
+                None => None,
+            };
+            rules.push(Checked {
+                rule: rule.clone(),
+                comparison,
+                threshold,
+                quiet_hours,
+            });
+        }
+        Ok(Self {
+            rules,
+            timezone: config.get_timezone()?,
+            breaches: HashMap::new(),
+        })
+    }
+
+    //
+    // the alerts the row raises or resolves, if any
+    //
+    pub fn evaluate(&mut self, observation: &Observation) -> Vec<Alert> {
+        let mut alerts = Vec::new();
+        for (index, checked) in self.rules.iter().enumerate() {
+            let rule = &checked.rule;
+            if !matches(rule, observation) {
+                continue;
+            }
+            let Some(value) = metric(observation, &rule.metric) else {
+                continue;
+            };
+            let (comparison, threshold, hysteresis) = threshold(checked, observation);
+            let past = match comparison {
+                Comparison::Below => value < threshold,
+                Comparison::Above => value > threshold,
+            };
+            let cleared = match comparison {
+                Comparison::Below => value >= threshold + hysteresis,
+                Comparison::Above => value <= threshold - hysteresis,
+            };
+
+            let key = (index, sensor_symbols(observation));
+            let time = observation.time;
+            let quiet = quiet(checked, self.timezone, time);
+            let transition = match self.breaches.get_mut(&key) {
+                Some(breach) if breach.firing && cleared => {
+                    let (since, held) = (breach.since, !breach.notified);
+                    self.breaches.remove(&key);
+                    Some((AlertState::Resolved, since, quiet || held))
+                }
+                Some(breach) if breach.firing && !breach.notified && !quiet => {
+                    breach.notified = true;
+                    Some((AlertState::Firing, breach.since, false))
+                }
+                Some(breach) if breach.firing => None,
+                Some(breach) if past => fire(rule, breach, time, quiet).then_some((
+                    AlertState::Firing,
+                    breach.since,
+                    quiet,
+                )),
+                Some(_) => {
+                    self.breaches.remove(&key);
+                    None
+                }
+                None if past => {
+                    let mut breach = Breach {
+                        since: time,
+                        firing: false,
+                        notified: false,
+                    };
+                    let fired = fire(rule, &mut breach, time, quiet);
+                    self.breaches.insert(key, breach);
+                    fired.then_some((AlertState::Firing, time, quiet))
+                }
+                None => None,
+            };
+            if let Some((state, since, quiet)) = transition {
+                alerts.push(alert(
+                    rule,
+                    state,
+                    observation,
+                    value,
+                    threshold,
+                    since,
+                    quiet,
+                ));
+            }
+        }
+        alerts
+    }
+}
+
+//
+// mark the breach as firing once it has lasted long enough; true when it
+// has just started to
+//
+fn fire(rule: &Rule, breach: &mut Breach, time: i64, quiet: bool) -> bool {
+    if time - breach.since < rule.for_secs as i64 * MICROS {
+        return false;
+    }
+    breach.firing = true;
+    breach.notified = !quiet;
+    true
+}
+
+//
+// whether the reading falls in the rule's quiet hours
+//
+fn quiet(checked: &Checked, timezone: Tz, time: i64) -> bool {
+    checked.quiet_hours.is_some_and(|window| {
+        DateTime::from_timestamp_micros(time)
+            .is_some_and(|time| within(window, time.with_timezone(&timezone).time()))
+    })
+}
+
+fn alert(
+    rule: &Rule,
+    state: AlertState,
+    observation: &Observation,
+    value: f64,
+    threshold: f64,
+    since: i64,
+    quiet: bool,
+) -> Alert {
+    Alert {
+        rule: rule.name.clone(),
+        severity: rule.severity.clone(),
+        state,
+        table: observation.table.clone(),
+        symbols: sensor_symbols(observation),
+        metric: rule.metric.clone(),
+        value,
+        threshold,
+        time: observation.time,
+        duration_secs: (observation.time - since) / MICROS,
+        quiet,
+        notify: rule.notify.clone(),
+    }
+}
+
+//
+// the span may run past midnight
+//
+fn within((from, to): (NaiveTime, NaiveTime), time: NaiveTime) -> bool {
+    if from <= to {
+        time >= from && time < to
+    } else {
+        time >= from || time < to
+    }
+}
+
+fn matches(rule: &Rule, observation: &Observation) -> bool {
+    if rule
+        .table
+        .as_ref()
+        .is_some_and(|table| *table != observation.table)
+    {
+        return false;
+    }
+    rule.selector.iter().all(|(name, pattern)| {
+        observation
+            .get_symbol(name)
+            .is_some_and(|value| glob(pattern, value))
+    })
+}
+
+fn metric(observation: &Observation, name: &str) -> Option<f64> {
+    observation.columns.iter().find_map(|(n, v)| match v {
+        Field::F64(v) if n == name => Some(*v),
+        Field::I64(v) if n == name => Some(*v as f64),
+        _ => None,
+    })
+}
+
+//
+// the rule's threshold and hysteresis in the temperature unit of the row
+//
+fn threshold(checked: &Checked, observation: &Observation) -> (Comparison, f64, f64) {
+    let (rule, comparison, threshold) = (&checked.rule, checked.comparison, checked.threshold);
+    let Some(unit) = rule.temperature else {
+        return (comparison, threshold, rule.hysteresis);
+    };
+    let row = Temperature::from_label(observation.get_symbol("units"));
+    let celsius = unit.to_celsius(threshold);
+    match (unit, row) {
+        (Temperature::Celsius, Temperature::Fahrenheit) => {
+            (comparison, celsius * 1.8 + 32.0, rule.hysteresis * 1.8)
+        }
+        (Temperature::Fahrenheit, Temperature::Celsius) => {
+            (comparison, celsius, rule.hysteresis / 1.8)
+        }
+        _ => (comparison, threshold, rule.hysteresis),
+    }
+}
+
+fn sensor_symbols(observation: &Observation) -> Vec<(String, String)> {
+    observation
+        .symbols
+        .iter()
+        .filter(|(name, _)| SENSOR_SYMBOLS.contains(&name.as_str()))
+        .cloned()
+        .collect()
+}
+
+//
+// `*` matches any run of characters and `?` any one character
+//
+pub fn glob(pattern: &str, value: &str) -> bool {
+    let (pattern, value): (Vec<char>, Vec<char>) =
+        (pattern.chars().collect(), value.chars().collect());
+    let (mut p, mut v) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+    while v < value.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == value[v]) {
+            p += 1;
+            v += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            star = Some((p, v));
+            p += 1;
+        } else if let Some((star_p, star_v)) = star {
+            p = star_p + 1;
+            v = star_v + 1;
+            star = Some((star_p, star_v + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
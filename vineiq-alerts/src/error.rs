@@ -0,0 +1,72 @@
+//!
+//! Errors surfaced while delivering an alert
+//!
+
+use std::fmt;
+
+#[derive(Debug)]
+pub enum Error {
+    Http(reqwest::Error),
+    Mqtt(String),
+    Smtp(lettre::transport::smtp::Error),
+    //
+    // an address or message the mail library would not take
+    //
+    Email(String),
+    //
+    // the channel refused or could not take the alert
+    //
+    Delivery(String),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Http(e) => write!(f, "http error: {}", e),
+            Error::Mqtt(e) => write!(f, "mqtt error: {}", e),
+            Error::Smtp(e) => write!(f, "smtp error: {}", e),
+            Error::Email(e) => write!(f, "email error: {}", e),
+            Error::Delivery(e) => write!(f, "delivery error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<reqwest::Error> for Error {
+    fn from(e: reqwest::Error) -> Self {
+        Error::Http(e)
+    }
+}
+
+impl From<rumqttc::ClientError> for Error {
+    fn from(e: rumqttc::ClientError) -> Self {
+        Error::Mqtt(e.to_string())
+    }
+}
+
+impl From<rumqttc::ConnectionError> for Error {
+    fn from(e: rumqttc::ConnectionError) -> Self {
+        Error::Mqtt(e.to_string())
+    }
+}
+
+impl From<lettre::transport::smtp::Error> for Error {
+    fn from(e: lettre::transport::smtp::Error) -> Self {
+        Error::Smtp(e)
+    }
+}
+
+impl From<lettre::error::Error> for Error {
+    fn from(e: lettre::error::Error) -> Self {
+        Error::Email(e.to_string())
+    }
+}
+
+impl From<lettre::address::AddressError> for Error {
+    fn from(e: lettre::address::AddressError) -> Self {
+        Error::Email(e.to_string())
+    }
+}
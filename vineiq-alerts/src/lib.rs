@@ -0,0 +1,24 @@
+//!
+//! Threshold alerts on the readings as the loggers write them
+//!
+//! Rules are evaluated by a sink placed in front of the database sink; the
+//! firing and resolved transitions are written to the `alert` table and
+//! handed to the configured notifiers.
+//!
+
+pub mod config;
+pub mod engine;
+pub mod error;
+pub mod mqtt;
+pub mod notify;
+pub mod sink;
+pub mod smtp;
+pub mod template;
+#[cfg(feature = "test-util")]
+pub mod testing;
+pub mod webhook;
+
+pub use config::AlertsConfig;
+pub use engine::{Alert, AlertState, Engine};
+pub use error::{Error, Result};
+pub use sink::AlertSink;
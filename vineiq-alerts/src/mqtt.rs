@@ -0,0 +1,76 @@
+//!
+//! MQTT notifier
+//!
+//! Publishes the rendered JSON body to the topic template with QoS 1. Alerts
+//! are rare, so each one gets its own connection: nothing is left to keep
+//! alive between them.
+//!
+
+use rumqttc::{Client, Event, MqttOptions, Outgoing, Packet, QoS, RecvTimeoutError};
+use std::time::{Duration, Instant};
+
+use crate::config::MqttConfig;
+use crate::engine::Alert;
+use crate::error::{Error, Result};
+use crate::notify::Notifier;
+use crate::template;
+
+pub struct MqttNotifier {
+    config: MqttConfig,
+}
+
+impl MqttNotifier {
+    pub fn new(config: MqttConfig) -> Self {
+        Self { config }
+    }
+}
+
+impl Notifier for MqttNotifier {
+    fn notify(&mut self, alert: &Alert) -> Result<()> {
+        let config = &self.config;
+        let mut options = MqttOptions::new(&config.client_id, &config.broker, config.port);
+        options.set_keep_alive(Duration::from_secs(20));
+        if let Some(username) = &config.username {
+            options.set_credentials(username, config.password.as_deref().unwrap_or_default());
+        }
+
+        let body = match &config.body {
+            Some(body) => template::render_json(body, alert),
+            None => template::render_json(&template::default_json(), alert),
+        };
+        let topic = template::render(&config.topic, alert);
+
+        let (client, mut connection) = Client::new(options, 10);
+        client.publish(topic, QoS::AtLeastOnce, config.retain, body.to_string())?;
+
+        //
+        // drive the connection until the broker acknowledges the publish
+        //
+        let deadline = Instant::now() + Duration::from_secs(config.timeout_secs);
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            match connection.recv_timeout(remaining) {
+                Ok(Ok(Event::Incoming(Packet::PubAck(_)))) => break,
+                Ok(Ok(_)) => {}
+                Ok(Err(e)) => return Err(e.into()),
+                Err(RecvTimeoutError::Timeout) => {
+                    return Err(Error::Mqtt(format!(
+                        "no acknowledgement from {} within {}s",
+                        config.broker, config.timeout_secs
+                    )))
+                }
+                Err(RecvTimeoutError::Disconnected) => {
+                    return Err(Error::Mqtt("connection closed".to_string()))
+                }
+            }
+        }
+
+        client.disconnect()?;
+        while let Ok(Ok(event)) = connection.recv_timeout(Duration::from_secs(1)) {
+            if event == Event::Outgoing(Outgoing::Disconnect) {
+                break;
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,292 @@
+//!
+//! Delivery of alerts to the configured notifiers
+//!
+//! The rules are evaluated on the write path of the loggers, so each
+//! notifier gets a thread and a queue of its own: a slow or failing channel
+//! never holds up a reading, nor the other channels.
+//!
+//!   notifiers:
+//!     phones:
+//!       type: webhook
+//!       url: "https://ntfy.sh"
+//!       body: { topic: "vineiq-frost", title: "{rule}", message: "{message}" }
+//!       rate_limit: { count: 10, per_secs: 3600 }
+//!     dashboard:
+//!       type: mqtt
+//!       broker: 127.0.0.1
+//!       topic: "vineiq/alerts/{rule}"
+//!     office:
+//!       type: smtp
+//!       host: smtp.example.com
+//!       username: alerts@example.com
+//!       password: "..."
+//!       from: "VineIQ <alerts@example.com>"
+//!       to: ["crew@example.com"]
+//!       retry: { attempts: 5, backoff_secs: 10 }
+//!
+
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use crate::config::{ChannelConfig, NotifierConfig, RateLimit, RetryConfig};
+use crate::engine::Alert;
+use crate::error::Result;
+use crate::mqtt::MqttNotifier;
+use crate::smtp::SmtpNotifier;
+use crate::webhook::WebhookNotifier;
+
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(15);
+
+pub trait Notifier {
+    fn notify(&mut self, alert: &Alert) -> Result<()>;
+}
+
+pub struct LogNotifier {
+    name: String,
+}
+
+impl LogNotifier {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+        }
+    }
+}
+
+impl Notifier for LogNotifier {
+    fn notify(&mut self, alert: &Alert) -> Result<()> {
+        println!(
+            "alert [{}] {}: {}",
+            self.name,
+            alert.severity,
+            alert.message()
+        );
+        Ok(())
+    }
+}
+
+pub fn notifier(name: &str, config: &ChannelConfig) -> Box<dyn Notifier + Send> {
+    match config {
+        ChannelConfig::Log => Box::new(LogNotifier::new(name)),
+        ChannelConfig::Webhook(config) => Box::new(WebhookNotifier::new(config.clone())),
+        ChannelConfig::Mqtt(config) => Box::new(MqttNotifier::new(config.clone())),
+        ChannelConfig::Smtp(config) => Box::new(SmtpNotifier::new(config.clone())),
+    }
+}
+
+//
+// a sliding window over the alerts sent
+//
+pub struct RateLimiter {
+    limit: RateLimit,
+    sent: VecDeque<Instant>,
+}
+
+impl RateLimiter {
+    pub fn new(limit: RateLimit) -> Self {
+        Self {
+            limit,
+            sent: VecDeque::new(),
+        }
+    }
+
+    pub fn allow(&mut self, now: Instant) -> bool {
+        let window = Duration::from_secs(self.limit.per_secs);
+        while self
+            .sent
+            .front()
+            .is_some_and(|sent| now.duration_since(*sent) >= window)
+        {
+            self.sent.pop_front();
+        }
+        if self.sent.len() >= self.limit.count {
+            return false;
+        }
+        self.sent.push_back(now);
+        true
+    }
+}
+
+//
+// raised when the dispatcher shuts down; wakes the workers waiting out a
+// retry backoff
+//
+#[derive(Default)]
+struct Stop {
+    stopped: Mutex<bool>,
+    wake: Condvar,
+}
+
+impl Stop {
+    fn raise(&self) {
+        *self.stopped.lock().unwrap() = true;
+        self.wake.notify_all();
+    }
+
+    //
+    // false, at once, when the dispatcher is shutting down
+    //
+    fn wait(&self, duration: Duration) -> bool {
+        let stopped = self.stopped.lock().unwrap();
+        let (stopped, _) = self
+            .wake
+            .wait_timeout_while(stopped, duration, |stopped| !*stopped)
+            .unwrap();
+        !*stopped
+    }
+}
+
+pub struct Channel {
+    name: String,
+    notifier: Box<dyn Notifier + Send>,
+    retry: RetryConfig,
+    limiter: Option<RateLimiter>,
+}
+
+impl Channel {
+    pub fn new(
+        name: &str,
+        notifier: Box<dyn Notifier + Send>,
+        retry: RetryConfig,
+        rate_limit: Option<RateLimit>,
+    ) -> Self {
+        Self {
+            name: name.to_string(),
+            notifier,
+            retry,
+            limiter: rate_limit.map(RateLimiter::new),
+        }
+    }
+
+    pub fn from_config(name: &str, config: &NotifierConfig) -> Self {
+        Self::new(
+            name,
+            notifier(name, &config.channel),
+            config.retry.clone(),
+            config.rate_limit.clone(),
+        )
+    }
+
+    //
+    // a failed attempt is retried after the backoff, unless the dispatcher
+    // is shutting down by then
+    //
+    fn deliver(&mut self, alert: &Alert, stop: &Stop) {
+        if let Some(limiter) = self.limiter.as_mut() {
+            if !limiter.allow(Instant::now()) {
+                println!("alert: {} rate limited, dropping {}", self.name, alert.rule);
+                return;
+            }
+        }
+
+        let attempts = self.retry.attempts.max(1);
+        let mut backoff = Duration::from_secs_f64(self.retry.backoff_secs.max(0.0));
+        for attempt in 1..=attempts {
+            match self.notifier.notify(alert) {
+                Ok(()) => return,
+                Err(e) if attempt < attempts => {
+                    println!(
+                        "alert: {} failed: {} -- retrying in {:.1}s",
+                        self.name,
+                        e,
+                        backoff.as_secs_f64()
+                    );
+                    if !stop.wait(backoff) {
+                        println!(
+                            "alert: {} shutting down -- giving up on {}",
+                            self.name, alert.rule
+                        );
+                        return;
+                    }
+                    backoff *= 2;
+                }
+                Err(e) => println!(
+                    "alert: {} failed: {} -- giving up on {}",
+                    self.name, e, alert.rule
+                ),
+            }
+        }
+    }
+}
+
+pub struct Dispatcher {
+    senders: Vec<(String, Sender<Alert>)>,
+    workers: Vec<JoinHandle<()>>,
+    stop: Arc<Stop>,
+}
+
+impl Dispatcher {
+    pub fn new(channels: Vec<Channel>) -> Self {
+        let mut senders = Vec::new();
+        let mut workers = Vec::new();
+        let stop = Arc::new(Stop::default());
+        for mut channel in channels {
+            let (sender, receiver) = mpsc::channel::<Alert>();
+            senders.push((channel.name.clone(), sender));
+            let stop = stop.clone();
+            workers.push(thread::spawn(move || {
+                for alert in receiver {
+                    channel.deliver(&alert, &stop);
+                }
+            }));
+        }
+        Self {
+            senders,
+            workers,
+            stop,
+        }
+    }
+
+    pub fn from_config(config: &BTreeMap<String, NotifierConfig>) -> Self {
+        Self::new(
+            config
+                .iter()
+                .map(|(name, config)| Channel::from_config(name, config))
+                .collect(),
+        )
+    }
+
+    //
+    // to the notifiers the rule names, or to all of them
+    //
+    pub fn send(&self, alert: Alert) {
+        for (name, sender) in &self.senders {
+            let wanted = alert
+                .notify
+                .as_ref()
+                .is_none_or(|names| names.contains(name));
+            if wanted {
+                let _ = sender.send(alert.clone());
+            }
+        }
+    }
+
+    //
+    // deliver what is queued, without retries, then stop; a notifier
+    // still busy after SHUTDOWN_TIMEOUT is left behind
+    //
+    pub fn shutdown(&mut self) {
+        self.senders.clear();
+        self.stop.raise();
+        let deadline = Instant::now() + SHUTDOWN_TIMEOUT;
+        for worker in self.workers.drain(..) {
+            while !worker.is_finished() && Instant::now() < deadline {
+                thread::sleep(Duration::from_millis(10));
+            }
+            if worker.is_finished() {
+                let _ = worker.join();
+            } else {
+                println!("alert: a notifier is still busy -- not waiting for it");
+            }
+        }
+    }
+}
+
+impl Drop for Dispatcher {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
@@ -0,0 +1,64 @@
+//!
+//! A sink that checks every row against the alert rules on its way through
+//!
+
+use vineiq_core::{Observation, Result, Sink};
+
+use crate::config::AlertsConfig;
+use crate::engine::Engine;
+use crate::notify::Dispatcher;
+
+pub struct AlertSink {
+    inner: Box<dyn Sink + Send>,
+    engine: Engine,
+    dispatcher: Dispatcher,
+}
+
+impl AlertSink {
+    pub fn new(inner: Box<dyn Sink + Send>, engine: Engine, dispatcher: Dispatcher) -> Self {
+        Self {
+            inner,
+            engine,
+            dispatcher,
+        }
+    }
+
+    pub fn from_config(
+        inner: Box<dyn Sink + Send>,
+        config: &AlertsConfig,
+    ) -> std::result::Result<Self, String> {
+        Ok(Self::new(
+            inner,
+            Engine::new(config)?,
+            Dispatcher::from_config(&config.notifiers),
+        ))
+    }
+}
+
+impl Sink for AlertSink {
+    //
+    // the row is written whatever the rules make of it; the alert rows go
+    // straight to the inner sink so they are never evaluated themselves
+    //
+    fn write(&mut self, observation: &Observation) -> Result<()> {
+        let result = self.inner.write(observation);
+        for alert in self.engine.evaluate(observation) {
+            if let Err(e) = self.inner.write(&alert.observation()) {
+                println!("alert: could not record {}: {}", alert.rule, e);
+            }
+            if !alert.quiet {
+                self.dispatcher.send(alert);
+            }
+        }
+        result
+    }
+
+    fn tick(&mut self) {
+        self.inner.tick();
+    }
+
+    fn shutdown(&mut self) {
+        self.inner.shutdown();
+        self.dispatcher.shutdown();
+    }
+}
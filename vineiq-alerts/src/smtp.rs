@@ -0,0 +1,82 @@
+//!
+//! SMTP email notifier
+//!
+//! Sends a plain text message from the subject and text templates, over
+//! STARTTLS by default, implicit TLS, or in the clear for a local relay.
+//!
+
+use lettre::message::Mailbox;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::transport::smtp::client::{Tls, TlsParameters};
+use lettre::{Message, SmtpTransport, Transport};
+use std::time::Duration;
+
+use crate::config::{SmtpConfig, SmtpTls};
+use crate::engine::Alert;
+use crate::error::{Error, Result};
+use crate::notify::Notifier;
+use crate::template;
+
+pub struct SmtpNotifier {
+    config: SmtpConfig,
+    transport: Option<SmtpTransport>,
+}
+
+impl SmtpNotifier {
+    pub fn new(config: SmtpConfig) -> Self {
+        Self {
+            config,
+            transport: None,
+        }
+    }
+
+    fn transport(&mut self) -> Result<&SmtpTransport> {
+        if self.transport.is_none() {
+            let config = &self.config;
+            let tls = match config.tls {
+                SmtpTls::None => Tls::None,
+                SmtpTls::Starttls => Tls::Required(TlsParameters::new(config.host.clone())?),
+                SmtpTls::Tls => Tls::Wrapper(TlsParameters::new(config.host.clone())?),
+            };
+            let port = config.port.unwrap_or(match config.tls {
+                SmtpTls::None => 25,
+                SmtpTls::Starttls => 587,
+                SmtpTls::Tls => 465,
+            });
+            let mut builder = SmtpTransport::builder_dangerous(&config.host)
+                .port(port)
+                .tls(tls)
+                .timeout(Some(Duration::from_secs(config.timeout_secs)));
+            if let Some(username) = &config.username {
+                builder = builder.credentials(Credentials::new(
+                    username.clone(),
+                    config.password.clone().unwrap_or_default(),
+                ));
+            }
+            self.transport = Some(builder.build());
+        }
+        Ok(self.transport.as_ref().expect("transport was just built"))
+    }
+}
+
+impl Notifier for SmtpNotifier {
+    fn notify(&mut self, alert: &Alert) -> Result<()> {
+        let mut message = Message::builder()
+            .from(self.config.from.parse::<Mailbox>()?)
+            .subject(template::render(&self.config.subject, alert));
+        for to in &self.config.to {
+            message = message.to(to.parse::<Mailbox>()?);
+        }
+        let message = message.body(template::render(&self.config.text, alert))?;
+
+        let response = self.transport()?.send(&message)?;
+        if !response.is_positive() {
+            return Err(Error::Delivery(format!(
+                "{} answered {}",
+                self.config.host,
+                response.code()
+            )));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,69 @@
+//!
+//! Alert templates for the notifier bodies, topics and subjects
+//!
+//! `{rule}`, `{state}`, `{severity}`, `{source}`, `{sensor}`, `{metric}`,
+//! `{value}`, `{threshold}`, `{duration_secs}`, `{time}` and `{message}` are
+//! replaced with the alert's own. In a JSON template a string that is only
+//! `{value}`, `{threshold}` or `{duration_secs}` becomes a number.
+//!
+
+use chrono::{DateTime, SecondsFormat};
+use serde_json::{json, Value};
+
+use crate::engine::Alert;
+
+pub fn render(template: &str, alert: &Alert) -> String {
+    let time = DateTime::from_timestamp_micros(alert.time)
+        .map(|t| t.to_rfc3339_opts(SecondsFormat::Secs, true))
+        .unwrap_or_default();
+    template
+        .replace("{rule}", &alert.rule)
+        .replace("{state}", alert.state.label())
+        .replace("{severity}", &alert.severity)
+        .replace("{source}", &alert.table)
+        .replace("{sensor}", &alert.sensor())
+        .replace("{metric}", &alert.metric)
+        .replace("{value}", &format!("{:.1}", alert.value))
+        .replace("{threshold}", &format!("{:.1}", alert.threshold))
+        .replace("{duration_secs}", &alert.duration_secs.to_string())
+        .replace("{time}", &time)
+        .replace("{message}", &alert.message())
+}
+
+pub fn render_json(template: &Value, alert: &Alert) -> Value {
+    match template {
+        Value::String(text) => match text.as_str() {
+            "{value}" => json!(alert.value),
+            "{threshold}" => json!(alert.threshold),
+            "{duration_secs}" => json!(alert.duration_secs),
+            _ => Value::String(render(text, alert)),
+        },
+        Value::Array(items) => Value::Array(items.iter().map(|v| render_json(v, alert)).collect()),
+        Value::Object(fields) => Value::Object(
+            fields
+                .iter()
+                .map(|(k, v)| (k.clone(), render_json(v, alert)))
+                .collect(),
+        ),
+        other => other.clone(),
+    }
+}
+
+//
+// the body sent when none is configured
+//
+pub fn default_json() -> Value {
+    json!({
+        "rule": "{rule}",
+        "state": "{state}",
+        "severity": "{severity}",
+        "source": "{source}",
+        "sensor": "{sensor}",
+        "metric": "{metric}",
+        "value": "{value}",
+        "threshold": "{threshold}",
+        "duration_secs": "{duration_secs}",
+        "time": "{time}",
+        "message": "{message}",
+    })
+}
@@ -0,0 +1,424 @@
+//!
+//! In-process stand-ins for the notifier endpoints, for tests
+//!
+//! An HTTP receiver, a bare MQTT broker and an SMTP server, each on a free
+//! local port, keeping what they are sent so a test can assert exactly what
+//! a notifier delivered. The simulator serves them on fixed ports.
+//!
+
+use serde_json::Value;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+#[derive(Clone)]
+struct Inbox<T> {
+    items: Arc<Mutex<Vec<T>>>,
+}
+
+impl<T: Clone> Inbox<T> {
+    fn new() -> Self {
+        Self {
+            items: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+
+    fn push(&self, item: T) {
+        self.items.lock().unwrap().push(item);
+    }
+
+    fn wait_for(&self, count: usize, timeout: Duration) -> Vec<T> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            let items = self.items.lock().unwrap().clone();
+            if items.len() >= count || Instant::now() >= deadline {
+                return items;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    //
+    // what has arrived since the last take, so a long running receiver does
+    // not keep every item
+    //
+    fn take(&self) -> Vec<T> {
+        std::mem::take(&mut *self.items.lock().unwrap())
+    }
+}
+
+const ANY_PORT: &str = "127.0.0.1:0";
+
+fn listen<F>(name: &str, addr: &str, handle: F) -> SocketAddr
+where
+    F: Fn(TcpStream) + Clone + Send + 'static,
+{
+    let listener = TcpListener::bind(addr)
+        .unwrap_or_else(|e| panic!("Error binding {} to {}: {}", name, addr, e));
+    let addr = listener
+        .local_addr()
+        .unwrap_or_else(|e| panic!("Error reading {} address: {}", name, e));
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let handle = handle.clone();
+            thread::spawn(move || handle(stream));
+        }
+    });
+    addr
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Request {
+    pub method: String,
+    pub path: String,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+impl Request {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+
+    pub fn json(&self) -> Value {
+        serde_json::from_str(&self.body).expect("request body is not JSON")
+    }
+}
+
+pub struct WebhookReceiver {
+    addr: SocketAddr,
+    requests: Inbox<Request>,
+}
+
+impl WebhookReceiver {
+    pub fn start() -> Self {
+        Self::bind(ANY_PORT, 0)
+    }
+
+    //
+    // answers the first `failures` requests with a 500
+    //
+    pub fn failing(failures: usize) -> Self {
+        Self::bind(ANY_PORT, failures)
+    }
+
+    pub fn bind(addr: &str, failures: usize) -> Self {
+        let requests = Inbox::new();
+        let received = requests.clone();
+        let failures = Arc::new(AtomicUsize::new(failures));
+        let addr = listen("webhook receiver", addr, move |stream| {
+            let Some(request) = read_request(&stream) else {
+                return;
+            };
+            received.push(request);
+            let failed = failures
+                .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1))
+                .is_ok();
+            let status = if failed {
+                "500 Internal Server Error"
+            } else {
+                "200 OK"
+            };
+            let _ = write!(
+                &stream,
+                "HTTP/1.1 {}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                status
+            );
+        });
+        Self { addr, requests }
+    }
+
+    pub fn url(&self) -> String {
+        format!("http://{}/hook", self.addr)
+    }
+
+    pub fn wait_for(&self, requests: usize, timeout: Duration) -> Vec<Request> {
+        self.requests.wait_for(requests, timeout)
+    }
+
+    pub fn take(&self) -> Vec<Request> {
+        self.requests.take()
+    }
+}
+
+fn read_request(stream: &TcpStream) -> Option<Request> {
+    let mut reader = BufReader::new(stream);
+    let mut line = String::new();
+    reader.read_line(&mut line).ok()?;
+    let mut parts = line.split_whitespace();
+    let (method, path) = (parts.next()?.to_string(), parts.next()?.to_string());
+
+    let mut headers = Vec::new();
+    loop {
+        let mut line = String::new();
+        reader.read_line(&mut line).ok()?;
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        let (name, value) = line.split_once(':')?;
+        headers.push((name.trim().to_string(), value.trim().to_string()));
+    }
+    let length = headers
+        .iter()
+        .find(|(n, _)| n.eq_ignore_ascii_case("content-length"))
+        .and_then(|(_, v)| v.parse().ok())
+        .unwrap_or(0);
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body).ok()?;
+
+    Some(Request {
+        method,
+        path,
+        headers,
+        body: String::from_utf8_lossy(&body).to_string(),
+    })
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Publish {
+    pub topic: String,
+    pub payload: String,
+    pub retain: bool,
+}
+
+impl Publish {
+    pub fn json(&self) -> Value {
+        serde_json::from_str(&self.payload).expect("payload is not JSON")
+    }
+}
+
+//
+// just enough of MQTT 3.1.1 to take a client's publishes: CONNACK, PUBACK
+// and PINGRESP, with no subscriptions
+//
+pub struct MqttReceiver {
+    addr: SocketAddr,
+    published: Inbox<Publish>,
+}
+
+impl MqttReceiver {
+    pub fn start() -> Self {
+        Self::bind(ANY_PORT)
+    }
+
+    pub fn bind(addr: &str) -> Self {
+        let published = Inbox::new();
+        let received = published.clone();
+        let addr = listen("MQTT receiver", addr, move |mut stream| {
+            while let Some((header, body)) = read_packet(&mut stream) {
+                let reply: &[u8] = match header >> 4 {
+                    1 => &[0x20, 0x02, 0x00, 0x00],
+                    3 => {
+                        let qos = (header >> 1) & 0x03;
+                        let topic_len = u16::from_be_bytes([body[0], body[1]]) as usize;
+                        let topic = String::from_utf8_lossy(&body[2..2 + topic_len]).to_string();
+                        let mut offset = 2 + topic_len;
+                        let id = if qos > 0 {
+                            offset += 2;
+                            Some([body[offset - 2], body[offset - 1]])
+                        } else {
+                            None
+                        };
+                        received.push(Publish {
+                            topic,
+                            payload: String::from_utf8_lossy(&body[offset..]).to_string(),
+                            retain: header & 0x01 == 1,
+                        });
+                        if let Some(id) = id {
+                            let _ = stream.write_all(&[0x40, 0x02, id[0], id[1]]);
+                        }
+                        continue;
+                    }
+                    12 => &[0xd0, 0x00],
+                    14 => return,
+                    _ => continue,
+                };
+                let _ = stream.write_all(reply);
+            }
+        });
+        Self { addr, published }
+    }
+
+    pub fn port(&self) -> u16 {
+        self.addr.port()
+    }
+
+    pub fn wait_for(&self, messages: usize, timeout: Duration) -> Vec<Publish> {
+        self.published.wait_for(messages, timeout)
+    }
+
+    pub fn take(&self) -> Vec<Publish> {
+        self.published.take()
+    }
+}
+
+fn read_packet(stream: &mut TcpStream) -> Option<(u8, Vec<u8>)> {
+    let mut byte = [0u8; 1];
+    stream.read_exact(&mut byte).ok()?;
+    let header = byte[0];
+
+    let (mut length, mut shift) = (0usize, 0);
+    loop {
+        stream.read_exact(&mut byte).ok()?;
+        length |= ((byte[0] & 0x7f) as usize) << shift;
+        if byte[0] & 0x80 == 0 {
+            break;
+        }
+        shift += 7;
+    }
+    let mut body = vec![0; length];
+    stream.read_exact(&mut body).ok()?;
+    Some((header, body))
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Mail {
+    pub from: String,
+    pub to: Vec<String>,
+    pub data: String,
+}
+
+impl Mail {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.data
+            .lines()
+            .take_while(|line| !line.is_empty())
+            .find_map(|line| {
+                let (n, v) = line.split_once(':')?;
+                n.eq_ignore_ascii_case(name).then_some(v.trim())
+            })
+    }
+
+    //
+    // the body, decoded if the mail library sent it quoted-printable
+    //
+    pub fn text(&self) -> String {
+        let text = self
+            .data
+            .split_once("\r\n\r\n")
+            .map(|(_, text)| text.trim_end())
+            .unwrap_or_default();
+        match self.header("Content-Transfer-Encoding") {
+            Some(encoding) if encoding.eq_ignore_ascii_case("quoted-printable") => {
+                quoted_printable(text)
+            }
+            _ => text.to_string(),
+        }
+    }
+}
+
+fn quoted_printable(text: &str) -> String {
+    let text = text.replace("=\r\n", "");
+    let mut bytes = Vec::new();
+    let mut rest = text.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        let hex = tail
+            .get(..2)
+            .and_then(|h| u8::from_str_radix(std::str::from_utf8(h).ok()?, 16).ok());
+        match hex {
+            Some(decoded) if byte == b'=' => {
+                bytes.push(decoded);
+                rest = &tail[2..];
+            }
+            _ => {
+                bytes.push(byte);
+                rest = tail;
+            }
+        }
+    }
+    String::from_utf8_lossy(&bytes).to_string()
+}
+
+//
+// an SMTP server without TLS or authentication that accepts every message
+//
+pub struct SmtpReceiver {
+    addr: SocketAddr,
+    mail: Inbox<Mail>,
+}
+
+impl SmtpReceiver {
+    pub fn start() -> Self {
+        Self::bind(ANY_PORT)
+    }
+
+    pub fn bind(addr: &str) -> Self {
+        let mail = Inbox::new();
+        let received = mail.clone();
+        let addr = listen("SMTP receiver", addr, move |stream| {
+            let mut writer = &stream;
+            let mut reader = BufReader::new(&stream);
+            let _ = writer.write_all(b"220 localhost ESMTP\r\n");
+
+            let mut envelope = Mail {
+                from: String::new(),
+                to: Vec::new(),
+                data: String::new(),
+            };
+            let mut line = String::new();
+            while reader.read_line(&mut line).is_ok_and(|n| n > 0) {
+                let command = line.trim_end().to_string();
+                line.clear();
+                let upper = command.to_ascii_uppercase();
+                let reply = if upper.starts_with("EHLO") || upper.starts_with("HELO") {
+                    "250 localhost"
+                } else if upper.starts_with("MAIL FROM:") {
+                    envelope.from = address(&command);
+                    "250 OK"
+                } else if upper.starts_with("RCPT TO:") {
+                    envelope.to.push(address(&command));
+                    "250 OK"
+                } else if upper == "DATA" {
+                    let _ = writer.write_all(b"354 end with .\r\n");
+                    let mut data = String::new();
+                    while reader.read_line(&mut line).is_ok_and(|n| n > 0) {
+                        if line == ".\r\n" {
+                            break;
+                        }
+                        data.push_str(line.strip_prefix('.').unwrap_or(&line));
+                        line.clear();
+                    }
+                    line.clear();
+                    envelope.data = data;
+                    received.push(envelope.clone());
+                    envelope.to.clear();
+                    "250 OK"
+                } else if upper == "QUIT" {
+                    let _ = writer.write_all(b"221 bye\r\n");
+                    return;
+                } else if upper == "RSET" || upper == "NOOP" {
+                    "250 OK"
+                } else {
+                    "502 not implemented"
+                };
+                let _ = write!(writer, "{}\r\n", reply);
+            }
+        });
+        Self { addr, mail }
+    }
+
+    pub fn port(&self) -> u16 {
+        self.addr.port()
+    }
+
+    pub fn wait_for(&self, messages: usize, timeout: Duration) -> Vec<Mail> {
+        self.mail.wait_for(messages, timeout)
+    }
+
+    pub fn take(&self) -> Vec<Mail> {
+        self.mail.take()
+    }
+}
+
+fn address(command: &str) -> String {
+    let start = command.find('<').map_or(0, |i| i + 1);
+    let end = command.rfind('>').unwrap_or(command.len());
+    command[start..end].to_string()
+}
@@ -0,0 +1,71 @@
+//!
+//! HTTP webhook notifier
+//!
+//! POSTs the rendered JSON body to the URL, which covers ntfy, Gotify and
+//! Slack style incoming webhooks alike. Header values are templates too.
+//!
+
+use std::time::Duration;
+
+use crate::config::WebhookConfig;
+use crate::engine::Alert;
+use crate::error::{Error, Result};
+use crate::notify::Notifier;
+use crate::template;
+
+pub struct WebhookNotifier {
+    config: WebhookConfig,
+    //
+    // built on first use, on the notifier's own thread
+    //
+    client: Option<reqwest::blocking::Client>,
+}
+
+impl WebhookNotifier {
+    pub fn new(config: WebhookConfig) -> Self {
+        Self {
+            config,
+            client: None,
+        }
+    }
+
+    fn client(&mut self) -> Result<&reqwest::blocking::Client> {
+        if self.client.is_none() {
+            let client = reqwest::blocking::Client::builder()
+                .timeout(Duration::from_secs(self.config.timeout_secs))
+                .build()?;
+            self.client = Some(client);
+        }
+        Ok(self.client.as_ref().expect("client was just built"))
+    }
+}
+
+impl Notifier for WebhookNotifier {
+    fn notify(&mut self, alert: &Alert) -> Result<()> {
+        let body = match &self.config.body {
+            Some(body) => template::render_json(body, alert),
+            None => template::render_json(&template::default_json(), alert),
+        };
+        let headers: Vec<(String, String)> = self
+            .config
+            .headers
+            .iter()
+            .map(|(name, value)| (name.clone(), template::render(value, alert)))
+            .collect();
+        let url = self.config.url.clone();
+
+        let mut request = self.client()?.post(&url).json(&body);
+        for (name, value) in headers {
+            request = request.header(name, value);
+        }
+        let response = request.send()?;
+        if !response.status().is_success() {
+            return Err(Error::Delivery(format!(
+                "{} answered {}",
+                url,
+                response.status()
+            )));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,245 @@
+use std::sync::{Arc, Mutex};
+use vineiq_alerts::config::{AlertsConfig, RetryConfig};
+use vineiq_alerts::engine::glob;
+use vineiq_alerts::notify::{Channel, Dispatcher, Notifier};
+use vineiq_alerts::{Alert, AlertSink, AlertState, Engine};
+use vineiq_core::testing::CaptureSink;
+use vineiq_core::{Observation, Sink};
+
+const MINUTE: i64 = 60_000_000;
+
+const RULES: &str = r#"
+rules:
+  - name: block-frost
+    table: yolink
+    metric: temperature
+    match: { block: "*" }
+    below: 0.5
+    temperature: celsius
+    for_secs: 600
+    hysteresis: 0.5
+    severity: critical
+    notify: [crew]
+notifiers:
+  crew: { type: log }
+"#;
+
+fn config() -> AlertsConfig {
+    let config: AlertsConfig = serde_yaml::from_str(RULES).unwrap();
+    config.validate().unwrap();
+    config
+}
+
+fn reading(minute: i64, block: Option<&str>, units: &str, temperature: f64) -> Observation {
+    let mut observation = Observation::new("yolink", minute * MINUTE);
+    observation.symbol("sensorName", "Row 4");
+    if let Some(block) = block {
+        observation.symbol("block", block);
+    }
+    observation
+        .symbol("units", units)
+        .column_f64("temperature", temperature);
+    observation
+}
+
+fn states(alerts: &[Alert]) -> Vec<AlertState> {
+    alerts.iter().map(|a| a.state).collect()
+}
+
+#[test]
+fn fires_after_the_duration_and_resolves_past_the_hysteresis() {
+    let mut engine = Engine::new(&config()).unwrap();
+
+    assert!(engine
+        .evaluate(&reading(0, Some("north"), "metric", 0.2))
+        .is_empty());
+    assert!(engine
+        .evaluate(&reading(5, Some("north"), "metric", 0.1))
+        .is_empty());
+    let fired = engine.evaluate(&reading(10, Some("north"), "metric", 0.0));
+    assert_eq!(states(&fired), vec![AlertState::Firing]);
+    assert_eq!(fired[0].duration_secs, 600);
+    assert_eq!(fired[0].severity, "critical");
+    //
+    // firing once, then back above the threshold but within the hysteresis
+    //
+    assert!(engine
+        .evaluate(&reading(11, Some("north"), "metric", -0.5))
+        .is_empty());
+    assert!(engine
+        .evaluate(&reading(12, Some("north"), "metric", 0.8))
+        .is_empty());
+    let resolved = engine.evaluate(&reading(13, Some("north"), "metric", 1.0));
+    assert_eq!(states(&resolved), vec![AlertState::Resolved]);
+    assert_eq!(resolved[0].duration_secs, 780);
+}
+
+#[test]
+fn a_short_dip_does_not_fire() {
+    let mut engine = Engine::new(&config()).unwrap();
+    assert!(engine
+        .evaluate(&reading(0, Some("north"), "metric", 0.2))
+        .is_empty());
+    assert!(engine
+        .evaluate(&reading(5, Some("north"), "metric", 2.0))
+        .is_empty());
+    assert!(engine
+        .evaluate(&reading(12, Some("north"), "metric", 0.2))
+        .is_empty());
+    //
+    // a sensor outside any block, or another table, is not matched
+    //
+    assert!(engine
+        .evaluate(&reading(0, None, "metric", -5.0))
+        .is_empty());
+    assert!(engine
+        .evaluate(&reading(20, None, "metric", -5.0))
+        .is_empty());
+}
+
+#[test]
+fn threshold_follows_the_units_of_the_row() {
+    let mut engine = Engine::new(&config()).unwrap();
+    //
+    // 33F is above 0.5C, 32F is below it
+    //
+    engine.evaluate(&reading(0, Some("north"), "imperial", 33.0));
+    assert!(engine
+        .evaluate(&reading(10, Some("north"), "imperial", 33.0))
+        .is_empty());
+    engine.evaluate(&reading(20, Some("north"), "imperial", 32.0));
+    let fired = engine.evaluate(&reading(30, Some("north"), "imperial", 32.0));
+    assert_eq!(states(&fired), vec![AlertState::Firing]);
+    assert!((fired[0].threshold - 32.9).abs() < 1e-9);
+}
+
+//
+// minute 0 is 16:00 the day before in Los Angeles
+//
+fn quiet_config() -> AlertsConfig {
+    let mut config = config();
+    config.timezone = "America/Los_Angeles".to_string();
+    config.rules[0].for_secs = 0;
+    config.rules[0].quiet_hours = serde_yaml::from_str("{ from: '16:00', to: '16:30' }").ok();
+    config
+}
+
+#[test]
+fn quiet_hours_hold_a_firing_until_they_end() {
+    let mut engine = Engine::new(&quiet_config()).unwrap();
+    let fired = engine.evaluate(&reading(0, Some("north"), "metric", 0.0));
+    assert_eq!(states(&fired), vec![AlertState::Firing]);
+    assert!(fired[0].quiet);
+    assert_eq!(fired[0].observation().get_symbol("state"), Some("firing"));
+    assert!(engine
+        .evaluate(&reading(10, Some("north"), "metric", 0.0))
+        .is_empty());
+
+    //
+    // still firing after the quiet hours, so it is sent now
+    //
+    let sent = engine.evaluate(&reading(30, Some("north"), "metric", 0.0));
+    assert_eq!(states(&sent), vec![AlertState::Firing]);
+    assert!(!sent[0].quiet);
+    assert_eq!(sent[0].duration_secs, 1800);
+    assert!(engine
+        .evaluate(&reading(40, Some("north"), "metric", 0.0))
+        .is_empty());
+    let resolved = engine.evaluate(&reading(50, Some("north"), "metric", 2.0));
+    assert_eq!(states(&resolved), vec![AlertState::Resolved]);
+    assert!(!resolved[0].quiet);
+}
+
+#[test]
+fn a_held_firing_resolves_quietly() {
+    let mut engine = Engine::new(&quiet_config()).unwrap();
+    assert!(engine.evaluate(&reading(0, Some("north"), "metric", 0.0))[0].quiet);
+    let resolved = engine.evaluate(&reading(40, Some("north"), "metric", 2.0));
+    assert_eq!(states(&resolved), vec![AlertState::Resolved]);
+    assert!(resolved[0].quiet);
+
+    //
+    // in UTC minute 0 is midnight, outside the quiet hours
+    //
+    let mut config = quiet_config();
+    config.timezone = "UTC".to_string();
+    let mut engine = Engine::new(&config).unwrap();
+    assert!(!engine.evaluate(&reading(0, Some("north"), "metric", 0.0))[0].quiet);
+}
+
+#[test]
+fn invalid_rules_are_refused() {
+    let mut config = config();
+    config.rules[0].above = Some(5.0);
+    assert!(config.validate().is_err());
+    assert!(Engine::new(&config).is_err());
+
+    let mut config = self::config();
+    config.rules[0].quiet_hours = serde_yaml::from_str("{ from: '10pm', to: '06:00' }").ok();
+    assert!(config.validate().is_err());
+
+    let mut config = self::config();
+    config.rules[0].notify = Some(vec!["nobody".to_string()]);
+    assert!(config.validate().is_err());
+
+    let mut config = self::config();
+    config.timezone = "Mars/Olympus".to_string();
+    assert!(config.validate().is_err());
+}
+
+#[test]
+fn globs() {
+    assert!(glob("*", "north"));
+    assert!(glob("Row ?", "Row 4"));
+    assert!(glob("Row*", "Row 14"));
+    assert!(glob("*west*", "south west corner"));
+    assert!(!glob("Row ?", "Row 14"));
+    assert!(!glob("north", "northeast"));
+}
+
+struct Recorded(Arc<Mutex<Vec<String>>>);
+
+impl Notifier for Recorded {
+    fn notify(&mut self, alert: &Alert) -> vineiq_alerts::Result<()> {
+        self.0.lock().unwrap().push(alert.message());
+        Ok(())
+    }
+}
+
+#[test]
+fn sink_records_and_notifies() {
+    let rows = CaptureSink::default();
+    let messages = Arc::new(Mutex::new(Vec::new()));
+    let crew = Channel::new(
+        "crew",
+        Box::new(Recorded(messages.clone())),
+        RetryConfig::default(),
+        None,
+    );
+
+    let mut sink = AlertSink::new(
+        Box::new(rows.clone()),
+        Engine::new(&config()).unwrap(),
+        Dispatcher::new(vec![crew]),
+    );
+    for (minute, temperature) in [(0, 0.0), (10, 0.0), (20, 2.0)] {
+        sink.write(&reading(minute, Some("north"), "metric", temperature))
+            .unwrap();
+    }
+    sink.shutdown();
+
+    let rows = rows.rows();
+    let alerts: Vec<&Observation> = rows.iter().filter(|o| o.table == "alert").collect();
+    assert_eq!(rows.len(), 5);
+    assert_eq!(alerts.len(), 2);
+    assert_eq!(alerts[0].get_symbol("rule"), Some("block-frost"));
+    assert_eq!(alerts[0].get_symbol("block"), Some("north"));
+    assert_eq!(alerts[1].get_symbol("state"), Some("resolved"));
+
+    let messages = messages.lock().unwrap();
+    assert_eq!(messages.len(), 2);
+    assert_eq!(
+        messages[0],
+        "block-frost fired: yolink temperature is 0.0 (threshold 0.5) on sensorName=Row 4,block=north"
+    );
+}
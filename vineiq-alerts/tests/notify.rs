@@ -0,0 +1,215 @@
+use serde_json::json;
+use std::collections::BTreeMap;
+use std::time::Duration;
+use vineiq_alerts::config::{ChannelConfig, NotifierConfig, SmtpTls};
+use vineiq_alerts::notify::{notifier, Channel, Dispatcher};
+use vineiq_alerts::template::render_json;
+use vineiq_alerts::testing::{MqttReceiver, SmtpReceiver, WebhookReceiver};
+use vineiq_alerts::{Alert, AlertState};
+
+const WAIT: Duration = Duration::from_secs(5);
+
+fn alert() -> Alert {
+    Alert {
+        rule: "block-frost".to_string(),
+        severity: "critical".to_string(),
+        state: AlertState::Firing,
+        table: "yolink".to_string(),
+        symbols: vec![("block".to_string(), "north".to_string())],
+        metric: "temperature".to_string(),
+        value: -0.4,
+        threshold: 0.5,
+        time: 1_700_000_000_000_000,
+        duration_secs: 600,
+        quiet: false,
+        notify: None,
+    }
+}
+
+fn notifiers(yaml: &str) -> BTreeMap<String, NotifierConfig> {
+    serde_yaml::from_str(yaml).unwrap()
+}
+
+#[test]
+fn parses_the_notifier_settings() {
+    let config = notifiers(
+        r#"
+hook: { type: webhook, url: "http://127.0.0.1/hook", rate_limit: { count: 5, per_secs: 60 } }
+broker: { type: mqtt, broker: 127.0.0.1, topic: "vineiq/{rule}" }
+mail: { type: smtp, host: mail, tls: tls, from: a@b.c, to: [d@e.f], retry: { attempts: 5 } }
+"#,
+    );
+    let hook = &config["hook"];
+    assert!(matches!(&hook.channel, ChannelConfig::Webhook(c) if c.timeout_secs == 10));
+    assert_eq!(hook.retry.attempts, 3);
+    assert_eq!(hook.rate_limit.as_ref().map(|r| r.count), Some(5));
+    assert!(
+        matches!(&config["broker"].channel, ChannelConfig::Mqtt(c) if c.port == 1883 && !c.retain)
+    );
+    let mail = &config["mail"];
+    assert!(matches!(&mail.channel, ChannelConfig::Smtp(c) if c.tls == SmtpTls::Tls));
+    assert_eq!(mail.retry.attempts, 5);
+    assert_eq!(mail.retry.backoff_secs, 2.0);
+}
+
+#[test]
+fn templates_keep_numbers_as_numbers() {
+    let body = render_json(
+        &json!({ "title": "{rule} on {sensor}", "value": "{value}", "tags": ["{severity}"] }),
+        &alert(),
+    );
+    assert_eq!(
+        body,
+        json!({ "title": "block-frost on block=north", "value": -0.4, "tags": ["critical"] })
+    );
+}
+
+#[test]
+fn webhook_posts_the_alert() {
+    let receiver = WebhookReceiver::start();
+    let config = notifiers(&format!(
+        r#"
+plain: {{ type: webhook, url: "{url}" }}
+ntfy:
+  type: webhook
+  url: "{url}"
+  headers: {{ Title: "{{rule}} {{state}}" }}
+  body: {{ topic: frost, message: "{{message}}" }}
+"#,
+        url = receiver.url()
+    ));
+    notifier("plain", &config["plain"].channel)
+        .notify(&alert())
+        .unwrap();
+    notifier("ntfy", &config["ntfy"].channel)
+        .notify(&alert())
+        .unwrap();
+
+    let requests = receiver.wait_for(2, WAIT);
+    assert_eq!(requests.len(), 2);
+    assert_eq!(requests[0].method, "POST");
+    let plain = requests[0].json();
+    assert_eq!(plain["rule"], "block-frost");
+    assert_eq!(plain["state"], "firing");
+    assert_eq!(plain["threshold"], 0.5);
+    assert_eq!(plain["duration_secs"], 600);
+    assert_eq!(plain["time"], "2023-11-14T22:13:20Z");
+
+    assert_eq!(requests[1].header("title"), Some("block-frost firing"));
+    assert_eq!(
+        requests[1].json(),
+        json!({ "topic": "frost", "message": alert().message() })
+    );
+
+    //
+    // taken requests are gone from the receiver
+    //
+    assert_eq!(receiver.take().len(), 2);
+    assert!(receiver.take().is_empty());
+    assert!(receiver.wait_for(0, WAIT).is_empty());
+}
+
+#[test]
+fn a_failing_webhook_is_retried() {
+    let receiver = WebhookReceiver::failing(2);
+    let config = notifiers(&format!(
+        "hook: {{ type: webhook, url: \"{}\", retry: {{ attempts: 3, backoff_secs: 0.01 }} }}",
+        receiver.url()
+    ));
+    let mut dispatcher = Dispatcher::new(vec![Channel::from_config("hook", &config["hook"])]);
+    dispatcher.send(alert());
+    assert_eq!(receiver.wait_for(3, WAIT).len(), 3);
+    dispatcher.shutdown();
+
+    //
+    // out of attempts, the alert is given up on
+    //
+    let receiver = WebhookReceiver::failing(5);
+    let config = notifiers(&format!(
+        "hook: {{ type: webhook, url: \"{}\", retry: {{ attempts: 2, backoff_secs: 0.01 }} }}",
+        receiver.url()
+    ));
+    let mut dispatcher = Dispatcher::new(vec![Channel::from_config("hook", &config["hook"])]);
+    dispatcher.send(alert());
+    assert_eq!(receiver.wait_for(3, Duration::from_millis(200)).len(), 2);
+    dispatcher.shutdown();
+}
+
+#[test]
+fn shutdown_drops_the_pending_retries() {
+    let receiver = WebhookReceiver::failing(5);
+    let config = notifiers(&format!(
+        "hook: {{ type: webhook, url: \"{}\", retry: {{ attempts: 3, backoff_secs: 60 }} }}",
+        receiver.url()
+    ));
+    let mut dispatcher = Dispatcher::new(vec![Channel::from_config("hook", &config["hook"])]);
+    dispatcher.send(alert());
+    assert_eq!(receiver.wait_for(1, WAIT).len(), 1);
+    let started = std::time::Instant::now();
+    dispatcher.shutdown();
+    assert!(started.elapsed() < WAIT);
+    assert_eq!(receiver.wait_for(2, Duration::from_millis(200)).len(), 1);
+}
+
+#[test]
+fn alerts_past_the_rate_limit_are_dropped() {
+    let receiver = WebhookReceiver::start();
+    let config = notifiers(&format!(
+        "hook: {{ type: webhook, url: \"{}\", rate_limit: {{ count: 2, per_secs: 3600 }} }}",
+        receiver.url()
+    ));
+    let mut dispatcher = Dispatcher::new(vec![Channel::from_config("hook", &config["hook"])]);
+    for _ in 0..4 {
+        dispatcher.send(alert());
+    }
+    dispatcher.shutdown();
+    assert_eq!(receiver.wait_for(3, Duration::from_millis(200)).len(), 2);
+}
+
+#[test]
+fn mqtt_publishes_to_the_rule_topic() {
+    let receiver = MqttReceiver::start();
+    let config = notifiers(&format!(
+        "broker: {{ type: mqtt, broker: 127.0.0.1, port: {}, topic: \"vineiq/alerts/{{rule}}\", retain: true }}",
+        receiver.port()
+    ));
+    notifier("broker", &config["broker"].channel)
+        .notify(&alert())
+        .unwrap();
+
+    let published = receiver.wait_for(1, WAIT);
+    assert_eq!(published.len(), 1);
+    assert_eq!(published[0].topic, "vineiq/alerts/block-frost");
+    assert!(published[0].retain);
+    assert_eq!(published[0].json()["value"], -0.4);
+}
+
+#[test]
+fn smtp_mails_the_recipients() {
+    let receiver = SmtpReceiver::start();
+    let config = notifiers(&format!(
+        r#"
+mail:
+  type: smtp
+  host: 127.0.0.1
+  port: {}
+  tls: none
+  from: "VineIQ <alerts@example.com>"
+  to: [crew@example.com, owner@example.com]
+"#,
+        receiver.port()
+    ));
+    notifier("mail", &config["mail"].channel)
+        .notify(&alert())
+        .unwrap();
+
+    let mail = receiver.wait_for(1, WAIT);
+    assert_eq!(mail.len(), 1);
+    assert_eq!(mail[0].from, "alerts@example.com");
+    assert_eq!(mail[0].to, vec!["crew@example.com", "owner@example.com"]);
+    assert_eq!(
+        mail[0].header("Subject"),
+        Some("[critical] block-frost firing")
+    );
+    assert_eq!(mail[0].text(), alert().message());
+}
@@ -0,0 +1,205 @@
+//!
+//! Daily minimum and maximum temperatures, per sensor and per vineyard block
+//!
+//! Read from the `tempest_station` and `yolink` tables and brought back to
+//! celsius whatever units each row was written in. Days are local days in
+//! the configured timezone.
+//!
+
+use chrono::NaiveDate;
+use std::collections::BTreeMap;
+use vineiq_core::units::Temperature;
+
+use crate::error::{Error, Result};
+use crate::query::{get_f64, get_str, quote, QueryClient, Row};
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct DailyTemperature {
+    pub day: NaiveDate,
+    pub tmin: f64,
+    pub tmax: f64,
+}
+
+//
+// a Tempest station or YoLink sensor, or the average of the sensors in a
+// block; the block of a sensor is the one it was logged with
+//
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub struct Series {
+    pub source: String,
+    pub sensor: Option<String>,
+    pub block: Option<String>,
+}
+
+impl Series {
+    pub fn scope(&self) -> &'static str {
+        match self.sensor {
+            Some(_) => "sensor",
+            None => "block",
+        }
+    }
+}
+
+//
+// the current local date, as QuestDB sees it
+//
+pub async fn today(client: &QueryClient, timezone: &str) -> Result<NaiveDate> {
+    let rows = client
+        .rows(&format!(
+            "SELECT to_timezone(now(), {}) today",
+            quote(timezone)
+        ))
+        .await?;
+    rows.first()
+        .and_then(|row| parse_day(row, "today"))
+        .ok_or_else(|| Error::Query("no result for the current date".to_string()))
+}
+
+pub async fn read(
+    client: &QueryClient,
+    timezone: &str,
+    since: NaiveDate,
+) -> Result<BTreeMap<Series, Vec<DailyTemperature>>> {
+    let mut days: BTreeMap<Series, BTreeMap<NaiveDate, (f64, f64)>> = BTreeMap::new();
+
+    let tempest = client
+        .keyed_rows("tempest_station", &["device_id"], |keys| {
+            daily_query("tempest_station", keys, timezone, since)
+        })
+        .await?;
+    for row in &tempest {
+        let series = Series {
+            source: "tempest".to_string(),
+            sensor: get_str(row, "device_id").map(str::to_string),
+            block: None,
+        };
+        merge(&mut days, series, row);
+    }
+
+    let yolink = client
+        .keyed_rows("yolink", &["sensorName", "block"], |keys| {
+            daily_query("yolink", keys, timezone, since)
+        })
+        .await?;
+    for row in &yolink {
+        let series = Series {
+            source: "yolink".to_string(),
+            sensor: get_str(row, "sensorName").map(str::to_string),
+            block: get_str(row, "block").map(str::to_string),
+        };
+        merge(&mut days, series, row);
+    }
+
+    let mut daily: BTreeMap<Series, Vec<DailyTemperature>> = days
+        .into_iter()
+        .filter(|(series, _)| series.sensor.is_some())
+        .map(|(series, days)| (series, to_daily(days)))
+        .collect();
+    let blocks = block_averages(&daily);
+    daily.extend(blocks);
+    Ok(daily)
+}
+
+/*
+  SELECT device_id, units,
+         timestamp_floor('d', to_timezone(time, 'UTC')) day,
+         min(temperature) tmin, max(temperature) tmax
+  FROM tempest_station
+  WHERE time >= '2026-03-31'
+  GROUP BY device_id, units, day
+
+  the filter starts a day early so a timezone ahead of UTC still gets the
+  whole first day; the caller drops days before the one it asked for
+*/
+fn daily_query(table: &str, keys: &str, timezone: &str, since: NaiveDate) -> String {
+    let from = since.pred_opt().unwrap_or(since);
+    format!(
+        "SELECT {keys}, units, timestamp_floor('d', to_timezone(time, {tz})) day, \
+         min(temperature) tmin, max(temperature) tmax \
+         FROM {table} WHERE time >= {from} GROUP BY {keys}, units, day",
+        keys = keys,
+        tz = quote(timezone),
+        table = table,
+        from = quote(&from.format("%Y-%m-%d").to_string()),
+    )
+}
+
+//
+// rows for the same day can come in several units if the setting changed
+//
+fn merge(days: &mut BTreeMap<Series, BTreeMap<NaiveDate, (f64, f64)>>, series: Series, row: &Row) {
+    let (Some(day), Some(tmin), Some(tmax)) = (
+        parse_day(row, "day"),
+        get_f64(row, "tmin"),
+        get_f64(row, "tmax"),
+    ) else {
+        return;
+    };
+    let unit = Temperature::from_label(get_str(row, "units"));
+    let (tmin, tmax) = (unit.to_celsius(tmin), unit.to_celsius(tmax));
+
+    let entry = days
+        .entry(series)
+        .or_default()
+        .entry(day)
+        .or_insert((tmin, tmax));
+    entry.0 = entry.0.min(tmin);
+    entry.1 = entry.1.max(tmax);
+}
+
+fn to_daily(days: BTreeMap<NaiveDate, (f64, f64)>) -> Vec<DailyTemperature> {
+    days.into_iter()
+        .map(|(day, (tmin, tmax))| DailyTemperature { day, tmin, tmax })
+        .collect()
+}
+
+//
+// a block's day is the mean of the minimums and of the maximums of its
+// sensors that reported that day
+//
+fn block_averages(
+    daily: &BTreeMap<Series, Vec<DailyTemperature>>,
+) -> BTreeMap<Series, Vec<DailyTemperature>> {
+    let mut sums: BTreeMap<Series, BTreeMap<NaiveDate, (f64, f64, f64)>> = BTreeMap::new();
+    for (series, days) in daily {
+        let Some(block) = &series.block else {
+            continue;
+        };
+        let key = Series {
+            source: series.source.clone(),
+            sensor: None,
+            block: Some(block.clone()),
+        };
+        for day in days {
+            let sum = sums
+                .entry(key.clone())
+                .or_default()
+                .entry(day.day)
+                .or_insert((0.0, 0.0, 0.0));
+            sum.0 += day.tmin;
+            sum.1 += day.tmax;
+            sum.2 += 1.0;
+        }
+    }
+    sums.into_iter()
+        .map(|(series, days)| {
+            let days = days
+                .into_iter()
+                .map(|(day, (tmin, tmax, n))| DailyTemperature {
+                    day,
+                    tmin: tmin / n,
+                    tmax: tmax / n,
+                })
+                .collect();
+            (series, days)
+        })
+        .collect()
+}
+
+//
+// QuestDB returns timestamps as "2026-04-01T00:00:00.000000Z"
+//
+fn parse_day(row: &Row, name: &str) -> Option<NaiveDate> {
+    let text = get_str(row, name)?;
+    NaiveDate::parse_from_str(text.get(..10)?, "%Y-%m-%d").ok()
+}
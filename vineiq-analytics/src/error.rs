@@ -0,0 +1,41 @@
+//!
+//! Errors surfaced while reading from or writing back to QuestDB
+//!
+
+use std::fmt;
+
+#[derive(Debug)]
+pub enum Error {
+    Http(reqwest::Error),
+    //
+    // QuestDB rejected the query; the message is the one it returned
+    //
+    Query(String),
+    Sink(vineiq_core::Error),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Http(e) => write!(f, "http error: {}", e),
+            Error::Query(e) => write!(f, "query error: {}", e),
+            Error::Sink(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<reqwest::Error> for Error {
+    fn from(e: reqwest::Error) -> Self {
+        Error::Http(e)
+    }
+}
+
+impl From<vineiq_core::Error> for Error {
+    fn from(e: vineiq_core::Error) -> Self {
+        Error::Sink(e)
+    }
+}
@@ -0,0 +1,467 @@
+//!
+//! Radiative frost forecast from the evening cooling curve
+//!
+//! A couple of hours after sunset the readings of every Tempest station and
+//! YoLink sensor since sunset give a forecast of the overnight minimum and of
+//! how long the temperature will stay below the threshold before sunrise.
+//! After sunrise the forecast is written again next to what happened:
+//!
+//!   frost:
+//!     latitude: 38.5
+//!     longitude: -122.4
+//!     threshold: 0.0        # celsius
+//!     method: brunt         # or allen, with the coefficients for the site
+//!     allen: { a: 0.6, b: 0.5, c: -2.8 }
+//!     lead_hours: 2.0       # how long after sunset to forecast
+//!     interval_secs: 300
+//!
+//! Rows go to the `frost_forecast` table with the `stage` symbol set to
+//! `forecast` at dusk and to `verified` in the morning.
+//!
+
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use serde_derive::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Instant;
+use vineiq_core::derived;
+use vineiq_core::units::Units;
+use vineiq_core::{Observation, Sink};
+
+use crate::daily::Series;
+use crate::error::Result;
+use crate::query::{get_str, quote, QueryClient};
+use crate::readings::{self, Reading};
+use crate::sun;
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Method {
+    #[default]
+    Brunt,
+    Allen,
+}
+
+impl Method {
+    fn label(&self) -> &'static str {
+        match self {
+            Method::Brunt => "brunt",
+            Method::Allen => "allen",
+        }
+    }
+}
+
+//
+// Tmin = a * T + b * Td + c, from a regression of the site's own records
+//
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+pub struct AllenCoefficients {
+    pub a: f64,
+    pub b: f64,
+    pub c: f64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct FrostConfig {
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+    pub threshold: f64,
+    pub method: Method,
+    pub allen: Option<AllenCoefficients>,
+    pub lead_hours: f64,
+    pub interval_secs: u64,
+}
+
+impl Default for FrostConfig {
+    fn default() -> Self {
+        Self {
+            latitude: None,
+            longitude: None,
+            threshold: 0.0,
+            method: Method::default(),
+            allen: None,
+            lead_hours: 2.0,
+            interval_secs: 300,
+        }
+    }
+}
+
+impl FrostConfig {
+    //
+    // called at startup, so a bad setting is reported before anything runs
+    //
+    pub fn validate(&self) -> std::result::Result<(), String> {
+        let (Some(latitude), Some(longitude)) = (self.latitude, self.longitude) else {
+            return Err("frost needs the latitude and longitude of the vineyard".to_string());
+        };
+        if !(-90.0..=90.0).contains(&latitude) || !(-180.0..=180.0).contains(&longitude) {
+            return Err(format!(
+                "frost: location {}, {} is out of range",
+                latitude, longitude
+            ));
+        }
+        if self.method == Method::Allen && self.allen.is_none() {
+            return Err("the allen method needs its a, b and c coefficients".to_string());
+        }
+        Ok(())
+    }
+
+    pub fn get_location(&self) -> (f64, f64) {
+        match (self.latitude, self.longitude) {
+            (Some(latitude), Some(longitude)) => (latitude, longitude),
+            _ => panic!("frost needs the latitude and longitude of the vineyard"),
+        }
+    }
+
+    pub fn get_allen(&self) -> AllenCoefficients {
+        self.allen
+            .expect("the allen method needs its a, b and c coefficients")
+    }
+}
+
+//
+// T(t) = a + b * sqrt(t), t in hours since sunset
+//
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct CoolingCurve {
+    pub a: f64,
+    pub b: f64,
+}
+
+impl CoolingCurve {
+    pub fn at(&self, hours: f64) -> f64 {
+        self.a + self.b * hours.max(0.0).sqrt()
+    }
+
+    //
+    // how long the curve is below the threshold between sunset and `until`;
+    // a cooling curve only ever falls, so that is from the crossing onwards
+    //
+    pub fn hours_below(&self, threshold: f64, until: f64) -> f64 {
+        if self.a < threshold {
+            return until;
+        }
+        if self.b >= 0.0 {
+            return 0.0;
+        }
+        let crossing = ((threshold - self.a) / self.b).powi(2);
+        (until - crossing).max(0.0)
+    }
+}
+
+/*
+  Brunt (1941): under clear skies and light wind the surface cools with the
+  square root of the time since sunset, so a least squares fit of
+
+    T = a + b * sqrt(t)
+
+  to the readings of the first hours gives the rest of the night. A curve
+  that is not falling is taken as flat at the last reading.
+*/
+pub fn brunt(readings: &[Reading], sunset: DateTime<Utc>) -> Option<CoolingCurve> {
+    let points: Vec<(f64, f64)> = readings
+        .iter()
+        .map(|r| (hours(sunset, r.time).max(0.0).sqrt(), r.celsius))
+        .collect();
+    if points.len() < 3 {
+        return None;
+    }
+    let n = points.len() as f64;
+    let mean_x = points.iter().map(|p| p.0).sum::<f64>() / n;
+    let mean_y = points.iter().map(|p| p.1).sum::<f64>() / n;
+    let sxx: f64 = points.iter().map(|p| (p.0 - mean_x).powi(2)).sum();
+    let sxy: f64 = points.iter().map(|p| (p.0 - mean_x) * (p.1 - mean_y)).sum();
+    if sxx <= f64::EPSILON {
+        return None;
+    }
+
+    let b = sxy / sxx;
+    if b >= 0.0 {
+        let last = readings.last()?.celsius;
+        return Some(CoolingCurve { a: last, b: 0.0 });
+    }
+    Some(CoolingCurve {
+        a: mean_y - b * mean_x,
+        b,
+    })
+}
+
+/*
+  Allen (1957): the minimum at sunrise from the temperature and dew point a
+  couple of hours after sunset,
+
+    Tmin = a * T + b * Td + c
+
+  and, as in the FAO frost protection guide, a square root curve from the
+  last reading down to it for the time below the threshold
+*/
+pub fn allen(
+    coefficients: &AllenCoefficients,
+    readings: &[Reading],
+    sunset: DateTime<Utc>,
+    sunrise: DateTime<Utc>,
+) -> Option<CoolingCurve> {
+    let last = readings.last()?;
+    let dew_point = derived::dew_point(last.celsius, last.humidity?)?;
+    let minimum = coefficients.a * last.celsius + coefficients.b * dew_point + coefficients.c;
+
+    let (from, to) = (
+        hours(sunset, last.time).max(0.0).sqrt(),
+        hours(sunset, sunrise).sqrt(),
+    );
+    if to <= from {
+        return None;
+    }
+    let b = ((minimum - last.celsius) / (to - from)).min(0.0);
+    Some(CoolingCurve {
+        a: last.celsius - b * from,
+        b,
+    })
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Forecast {
+    pub curve: CoolingCurve,
+    pub dusk: Reading,
+    pub predicted_min: f64,
+    pub predicted_hours_below: f64,
+}
+
+pub fn forecast(
+    config: &FrostConfig,
+    readings: &[Reading],
+    sunset: DateTime<Utc>,
+    sunrise: DateTime<Utc>,
+) -> Option<Forecast> {
+    let curve = match config.method {
+        Method::Brunt => brunt(readings, sunset)?,
+        Method::Allen => allen(&config.get_allen(), readings, sunset, sunrise)?,
+    };
+    let night = hours(sunset, sunrise);
+    Some(Forecast {
+        curve,
+        dusk: *readings.last()?,
+        predicted_min: curve.at(night),
+        predicted_hours_below: curve.hours_below(config.threshold, night),
+    })
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Actual {
+    pub min: f64,
+    pub hours_below: f64,
+}
+
+//
+// each reading below the threshold counts until the next one, so a gap in
+// the readings is not taken as frost for more than an hour
+//
+pub fn actual(readings: &[Reading], threshold: f64) -> Option<Actual> {
+    let min = readings.iter().map(|r| r.celsius).reduce(f64::min)?;
+    let hours_below = readings
+        .windows(2)
+        .filter(|pair| pair[0].celsius < threshold)
+        .map(|pair| hours(pair[0].time, pair[1].time).min(1.0))
+        .sum();
+    Some(Actual { min, hours_below })
+}
+
+fn hours(from: DateTime<Utc>, to: DateTime<Utc>) -> f64 {
+    (to - from).num_seconds() as f64 / 3600.0
+}
+
+//
+// the evening of `night` and the morning after it, at the vineyard
+//
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Night {
+    pub date: NaiveDate,
+    pub sunset: DateTime<Utc>,
+    pub sunrise: DateTime<Utc>,
+}
+
+impl Night {
+    pub fn new(date: NaiveDate, latitude: f64, longitude: f64) -> Option<Self> {
+        Some(Self {
+            date,
+            sunset: sun::sunset(date, latitude, longitude)?,
+            sunrise: sun::sunrise(date.succ_opt()?, latitude, longitude)?,
+        })
+    }
+
+    pub fn label(&self) -> String {
+        self.date.format("%Y-%m-%d").to_string()
+    }
+}
+
+pub fn observation(
+    config: &FrostConfig,
+    series: &Series,
+    night: &Night,
+    units: &Units,
+    forecast: &Forecast,
+    actual: Option<&Actual>,
+) -> Observation {
+    let mut observation = Observation::new("frost_forecast", night.sunset.timestamp_micros());
+    observation
+        .symbol(
+            "stage",
+            if actual.is_some() {
+                "verified"
+            } else {
+                "forecast"
+            },
+        )
+        .symbol("method", config.method.label())
+        .symbol("source", &series.source);
+    if let Some(sensor) = &series.sensor {
+        observation.symbol("sensor", sensor);
+    }
+    if let Some(block) = &series.block {
+        observation.symbol("block", block);
+    }
+    let dew_point = forecast
+        .dusk
+        .humidity
+        .and_then(|humidity| derived::dew_point(forecast.dusk.celsius, humidity));
+    observation
+        .symbol("night", &night.label())
+        .symbol("units", &units.label())
+        .column_f64("threshold", units.temperature(config.threshold))
+        .column_f64("dusk_temperature", units.temperature(forecast.dusk.celsius))
+        .column_opt_f64("dusk_dew_point", dew_point.map(|t| units.temperature(t)))
+        .column_f64("predicted_min", units.temperature(forecast.predicted_min))
+        .column_f64("predicted_hours_below", forecast.predicted_hours_below);
+    if let Some(actual) = actual {
+        observation
+            .column_f64("actual_min", units.temperature(actual.min))
+            .column_f64("actual_hours_below", actual.hours_below)
+            .column_f64("error", units.degrees(forecast.predicted_min - actual.min));
+    }
+    observation
+}
+
+//
+// forecast the nights that have reached dusk plus the lead time and verify
+// the ones whose morning is an hour old, each once. Returns the number of
+// rows written.
+//
+pub async fn update(
+    config: &FrostConfig,
+    client: &QueryClient,
+    sink: &mut (dyn Sink + Send),
+    units: &Units,
+    now: DateTime<Utc>,
+) -> Result<usize> {
+    let (latitude, longitude) = config.get_location();
+    let lead = Duration::seconds((config.lead_hours * 3600.0) as i64);
+    let settle = Duration::hours(1);
+
+    let mut count = 0;
+    for offset in (0..3).rev() {
+        let date = now.date_naive() - Duration::days(offset);
+        let Some(night) = Night::new(date, latitude, longitude) else {
+            continue;
+        };
+        let dusk = night.sunset + lead;
+        let verify = now >= night.sunrise + settle && now < night.sunrise + Duration::days(1);
+        if !(verify || (now >= dusk && now < night.sunrise)) {
+            continue;
+        }
+
+        let written = written(client, &night).await?;
+        let stage = if verify { "verified" } else { "forecast" };
+        let evening = readings::read(client, night.sunset, dusk).await?;
+        let overnight = if verify {
+            readings::read(client, night.sunset, night.sunrise).await?
+        } else {
+            Default::default()
+        };
+        for (series, readings) in &evening {
+            if written.contains(&(series.clone(), stage.to_string())) {
+                continue;
+            }
+            let Some(forecast) = forecast(config, readings, night.sunset, night.sunrise) else {
+                continue;
+            };
+            let actual = overnight
+                .get(series)
+                .and_then(|r| actual(r, config.threshold));
+            if verify && actual.is_none() {
+                continue;
+            }
+            if !verify {
+                println!(
+                    "frost: {} {} predicted minimum {:.1}C, {:.1}h below {:.1}C",
+                    series.source,
+                    series.sensor.as_deref().unwrap_or_default(),
+                    forecast.predicted_min,
+                    forecast.predicted_hours_below,
+                    config.threshold
+                );
+            }
+            sink.write(&observation(
+                config,
+                series,
+                &night,
+                units,
+                &forecast,
+                actual.as_ref(),
+            ))?;
+            count += 1;
+        }
+    }
+    Ok(count)
+}
+
+async fn written(client: &QueryClient, night: &Night) -> Result<BTreeSet<(Series, String)>> {
+    let rows = client
+        .keyed_rows("frost_forecast", &["source", "sensor", "block"], |keys| {
+            format!(
+                "SELECT DISTINCT {}, stage FROM frost_forecast WHERE night = {}",
+                keys,
+                quote(&night.label())
+            )
+        })
+        .await?;
+    Ok(rows
+        .iter()
+        .filter_map(|row| {
+            let series = Series {
+                source: get_str(row, "source")?.to_string(),
+                sensor: get_str(row, "sensor").map(str::to_string),
+                block: get_str(row, "block").map(str::to_string),
+            };
+            Some((series, get_str(row, "stage")?.to_string()))
+        })
+        .collect())
+}
+
+//
+// update every interval until shutdown is set; a failed update is reported
+// and tried again at the next interval
+//
+pub async fn run(
+    config: &FrostConfig,
+    client: &QueryClient,
+    sink: &mut (dyn Sink + Send),
+    units: &Units,
+    shutdown: &AtomicBool,
+) {
+    let interval = std::time::Duration::from_secs(config.interval_secs.max(1));
+    let mut ticker = tokio::time::interval(std::time::Duration::from_secs(1));
+    let mut next = Instant::now();
+    while !shutdown.load(Ordering::Relaxed) {
+        if Instant::now() >= next {
+            match update(config, client, sink, units, Utc::now()).await {
+                Ok(0) => {}
+                Ok(count) => println!("frost: wrote {} rows", count),
+                Err(e) => println!("frost: update failed: {}", e),
+            }
+            next = Instant::now() + interval;
+        }
+        sink.tick();
+        ticker.tick().await;
+    }
+}
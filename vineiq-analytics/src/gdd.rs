@@ -0,0 +1,302 @@
+//!
+//! Season-to-date growing degree days
+//!
+//! Winkler degree days (base 10C), the Huglin heliothermal index and
+//! biologically effective degree days, accumulated from the daily minimum
+//! and maximum of every Tempest station and YoLink sensor, and of every
+//! vineyard block, and written once a day to the `gdd` table:
+//!
+//!   gdd:
+//!     hemisphere: north     # or south; picks the default season start
+//!     season_start: "04-01" # optional, MM-DD
+//!     latitude: 38.5        # optional, for the Huglin day length coefficient
+//!     timezone: "America/Los_Angeles"
+//!     interval_secs: 3600
+//!
+
+use chrono::{NaiveDate, NaiveTime};
+use serde_derive::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+use vineiq_core::units::Units;
+use vineiq_core::{Observation, Sink};
+
+use crate::daily::{self, DailyTemperature, Series};
+use crate::error::Result;
+use crate::query::{get_str, quote, QueryClient};
+use crate::season::{Hemisphere, Season, SeasonStart};
+
+const BASE: f64 = 10.0;
+
+//
+// Winkler and BEDD run to the end of October (April in the south), Huglin
+// to the end of September (March)
+//
+const WINKLER_MONTHS: u32 = 7;
+const HUGLIN_MONTHS: u32 = 6;
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct GddConfig {
+    pub hemisphere: Hemisphere,
+    pub season_start: Option<String>,
+    pub latitude: Option<f64>,
+    pub timezone: String,
+    pub interval_secs: u64,
+}
+
+impl Default for GddConfig {
+    fn default() -> Self {
+        Self {
+            hemisphere: Hemisphere::default(),
+            season_start: None,
+            latitude: None,
+            timezone: "UTC".to_string(),
+            interval_secs: 3600,
+        }
+    }
+}
+
+impl GddConfig {
+    //
+    // called at startup, so a bad setting is reported before anything runs
+    //
+    pub fn validate(&self) -> std::result::Result<(), String> {
+        if let Some(text) = &self.season_start {
+            SeasonStart::parse(text)?;
+        }
+        match self.latitude {
+            Some(latitude) if !(-90.0..=90.0).contains(&latitude) => {
+                Err(format!("gdd: latitude {} is out of range", latitude))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    pub fn get_season_start(&self) -> SeasonStart {
+        match &self.season_start {
+            Some(text) => SeasonStart::parse(text).unwrap_or_else(|e| panic!("{}", e)),
+            None => self.hemisphere.season_start(),
+        }
+    }
+
+    pub fn get_huglin_coefficient(&self) -> f64 {
+        self.latitude.map(huglin_coefficient).unwrap_or(1.0)
+    }
+}
+
+pub fn winkler(day: &DailyTemperature) -> f64 {
+    (mean(day) - BASE).max(0.0)
+}
+
+//
+// Huglin (1978): the mean and the maximum above 10C, averaged and scaled up
+// for the longer days away from the equator
+//
+pub fn huglin(day: &DailyTemperature, k: f64) -> f64 {
+    (((mean(day) - BASE) + (day.tmax - BASE)) / 2.0).max(0.0) * k
+}
+
+pub fn huglin_coefficient(latitude: f64) -> f64 {
+    match latitude.abs() {
+        l if l <= 40.0 => 1.0,
+        l if l <= 42.0 => 1.02,
+        l if l <= 44.0 => 1.03,
+        l if l <= 46.0 => 1.04,
+        l if l <= 48.0 => 1.05,
+        _ => 1.06,
+    }
+}
+
+/*
+  biologically effective degree days (Gladstones 1992), without the day
+  length term:
+
+    BEDD = min(max(min(Tmean, 19) - 10, 0) + adj, 9)
+
+    adj  = 0.25 * (range - 13)   when the diurnal range is above 13C
+         = 0.25 * (range - 10)   when it is below 10C
+         = 0                     otherwise
+*/
+pub fn bedd(day: &DailyTemperature) -> f64 {
+    let range = day.tmax - day.tmin;
+    let adjustment = if range > 13.0 {
+        0.25 * (range - 13.0)
+    } else if range < 10.0 {
+        0.25 * (range - 10.0)
+    } else {
+        0.0
+    };
+    ((mean(day).min(19.0) - BASE).max(0.0) + adjustment).clamp(0.0, 9.0)
+}
+
+fn mean(day: &DailyTemperature) -> f64 {
+    (day.tmin + day.tmax) / 2.0
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct GddDay {
+    pub temperature: DailyTemperature,
+    pub winkler_day: f64,
+    pub winkler: f64,
+    pub huglin_day: f64,
+    pub huglin: f64,
+    pub bedd_day: f64,
+    pub bedd: f64,
+    pub days: i64,
+}
+
+//
+// running totals through the season for days in order; days outside the
+// season are dropped and each index stops adding once its period is over
+//
+pub fn accumulate(season: &Season, k: f64, daily: &[DailyTemperature]) -> Vec<GddDay> {
+    let winkler_end = season.last_day(WINKLER_MONTHS);
+    let huglin_end = season.last_day(HUGLIN_MONTHS);
+
+    let (mut winkler_total, mut huglin_total, mut bedd_total) = (0.0, 0.0, 0.0);
+    let mut days = 0;
+    daily
+        .iter()
+        .filter(|d| d.day >= season.start && d.day <= winkler_end)
+        .map(|d| {
+            let winkler_day = winkler(d);
+            let huglin_day = if d.day <= huglin_end {
+                huglin(d, k)
+            } else {
+                0.0
+            };
+            let bedd_day = bedd(d);
+            winkler_total += winkler_day;
+            huglin_total += huglin_day;
+            bedd_total += bedd_day;
+            days += 1;
+            GddDay {
+                temperature: *d,
+                winkler_day,
+                winkler: winkler_total,
+                huglin_day,
+                huglin: huglin_total,
+                bedd_day,
+                bedd: bedd_total,
+                days,
+            }
+        })
+        .collect()
+}
+
+pub fn observation(series: &Series, season: &Season, units: &Units, day: &GddDay) -> Observation {
+    let midnight = day.temperature.day.and_time(NaiveTime::MIN).and_utc();
+    let mut observation = Observation::new("gdd", midnight.timestamp_micros());
+    observation
+        .symbol("scope", series.scope())
+        .symbol("source", &series.source);
+    if let Some(sensor) = &series.sensor {
+        observation.symbol("sensor", sensor);
+    }
+    if let Some(block) = &series.block {
+        observation.symbol("block", block);
+    }
+    observation
+        .symbol("season", &season.label())
+        .symbol("units", &units.label())
+        .column_f64("tmin", units.temperature(day.temperature.tmin))
+        .column_f64("tmax", units.temperature(day.temperature.tmax))
+        .column_f64("winkler_day", units.degrees(day.winkler_day))
+        .column_f64("winkler", units.degrees(day.winkler))
+        .column_f64("huglin_day", units.degrees(day.huglin_day))
+        .column_f64("huglin", units.degrees(day.huglin))
+        .column_f64("bedd_day", units.degrees(day.bedd_day))
+        .column_f64("bedd", units.degrees(day.bedd))
+        .column_i64("days", day.days);
+    observation
+}
+
+//
+// write every complete day of the season not yet in the table; today is
+// left until it is over. Returns the number of rows written.
+//
+pub async fn update(
+    config: &GddConfig,
+    client: &QueryClient,
+    sink: &mut (dyn Sink + Send),
+    units: &Units,
+) -> Result<usize> {
+    let today = daily::today(client, &config.timezone).await?;
+    let Some(yesterday) = today.pred_opt() else {
+        return Ok(0);
+    };
+    let season = Season::containing(yesterday, config.get_season_start());
+    let written = last_written(client, &season).await?;
+    let k = config.get_huglin_coefficient();
+
+    let mut count = 0;
+    for (series, days) in daily::read(client, &config.timezone, season.start).await? {
+        let last = written.get(&series).copied();
+        for day in accumulate(&season, k, &days) {
+            let date = day.temperature.day;
+            if date > yesterday || last.is_some_and(|last| date <= last) {
+                continue;
+            }
+            sink.write(&observation(&series, &season, units, &day))?;
+            count += 1;
+        }
+    }
+    Ok(count)
+}
+
+async fn last_written(
+    client: &QueryClient,
+    season: &Season,
+) -> Result<BTreeMap<Series, NaiveDate>> {
+    let rows = client
+        .keyed_rows("gdd", &["source", "sensor", "block"], |keys| {
+            format!(
+                "SELECT {}, max(time) last FROM gdd WHERE season = {}",
+                keys,
+                quote(&season.label())
+            )
+        })
+        .await?;
+    Ok(rows
+        .iter()
+        .filter_map(|row| {
+            let series = Series {
+                source: get_str(row, "source")?.to_string(),
+                sensor: get_str(row, "sensor").map(str::to_string),
+                block: get_str(row, "block").map(str::to_string),
+            };
+            let last = get_str(row, "last")?.get(..10)?;
+            Some((series, NaiveDate::parse_from_str(last, "%Y-%m-%d").ok()?))
+        })
+        .collect())
+}
+
+//
+// update every interval until shutdown is set; a failed update is reported
+// and tried again at the next interval
+//
+pub async fn run(
+    config: &GddConfig,
+    client: &QueryClient,
+    sink: &mut (dyn Sink + Send),
+    units: &Units,
+    shutdown: &AtomicBool,
+) {
+    let interval = Duration::from_secs(config.interval_secs.max(1));
+    let mut ticker = tokio::time::interval(Duration::from_secs(1));
+    let mut next = Instant::now();
+    while !shutdown.load(Ordering::Relaxed) {
+        if Instant::now() >= next {
+            match update(config, client, sink, units).await {
+                Ok(0) => {}
+                Ok(count) => println!("gdd: wrote {} rows", count),
+                Err(e) => println!("gdd: update failed: {}", e),
+            }
+            next = Instant::now() + interval;
+        }
+        sink.tick();
+        ticker.tick().await;
+    }
+}
@@ -0,0 +1,19 @@
+//!
+//! Vineyard analytics computed from the logged tables
+//!
+//! Each service reads what the loggers wrote back out of QuestDB and writes
+//! its results to a table of its own through the same sink.
+//!
+
+pub mod daily;
+pub mod error;
+pub mod frost;
+pub mod gdd;
+pub mod mildew;
+pub mod query;
+pub mod readings;
+pub mod season;
+pub mod sun;
+
+pub use error::{Error, Result};
+pub use query::QueryClient;
@@ -0,0 +1,438 @@
+//!
+//! Powdery mildew risk index (Gubler-Thomas)
+//!
+//! The UC Davis risk assessment for grape powdery mildew, computed once a day
+//! for every Tempest station and YoLink sensor and for every vineyard block,
+//! and written to the `disease_risk` table with the spray interval it
+//! suggests:
+//!
+//!   mildew:
+//!     hemisphere: north     # or south; picks the default season start
+//!     season_start: "04-01" # optional, MM-DD
+//!     timezone: "America/Los_Angeles"
+//!     spray_interval_days: { low: 14, moderate: 10, high: 7 }
+//!     interval_secs: 3600
+//!
+//! The index starts after three days in a row with six or more continuous
+//! hours between 21C and 30C. From then on such a day adds 20 points, any
+//! other day takes 10 off, and a day reaching 35C for 15 minutes takes 10
+//! off; no day loses more than 10. The index stays within 0 to 100 and,
+//! should it fall back to 0, waits for another three days to start again.
+//! The ascospore stage of the model, which needs leaf wetness, is not
+//! covered.
+//!
+
+use chrono::{Duration, NaiveDate, NaiveDateTime, NaiveTime};
+use serde_derive::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Instant;
+use vineiq_core::{Observation, Sink};
+
+use crate::daily::{self, Series};
+use crate::error::Result;
+use crate::query::{get_f64, get_str, quote, QueryClient};
+use crate::readings::{self, LocalReading};
+use crate::season::{Hemisphere, Season, SeasonStart};
+
+const FAVOURABLE: (f64, f64) = (21.0, 30.0);
+const FAVOURABLE_HOURS: f64 = 6.0;
+const HEAT: f64 = 35.0;
+const HEAT_MINUTES: f64 = 15.0;
+const TRIGGER_DAYS: i64 = 3;
+
+//
+// a reading is taken to hold until the next one, but for no longer than
+// this, so an outage breaks a run rather than stretching it
+//
+const MAX_GAP_MINUTES: i64 = 90;
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct MildewConfig {
+    pub hemisphere: Hemisphere,
+    pub season_start: Option<String>,
+    pub timezone: String,
+    pub spray_interval_days: SprayIntervals,
+    pub interval_secs: u64,
+}
+
+impl Default for MildewConfig {
+    fn default() -> Self {
+        Self {
+            hemisphere: Hemisphere::default(),
+            season_start: None,
+            timezone: "UTC".to_string(),
+            spray_interval_days: SprayIntervals::default(),
+            interval_secs: 3600,
+        }
+    }
+}
+
+impl MildewConfig {
+    //
+    // called at startup, so a bad setting is reported before anything runs
+    //
+    pub fn validate(&self) -> std::result::Result<(), String> {
+        if let Some(text) = &self.season_start {
+            SeasonStart::parse(text)?;
+        }
+        Ok(())
+    }
+
+    pub fn get_season_start(&self) -> SeasonStart {
+        match &self.season_start {
+            Some(text) => SeasonStart::parse(text).unwrap_or_else(|e| panic!("{}", e)),
+            None => self.hemisphere.season_start(),
+        }
+    }
+}
+
+//
+// days between sprays for each level of risk; the defaults are the UC
+// guidelines for sulfur, longer intervals suit the sterol inhibitors
+//
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+pub struct SprayIntervals {
+    pub low: i64,
+    pub moderate: i64,
+    pub high: i64,
+}
+
+impl Default for SprayIntervals {
+    fn default() -> Self {
+        Self {
+            low: 14,
+            moderate: 10,
+            high: 7,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Risk {
+    Low,
+    Moderate,
+    High,
+}
+
+impl Risk {
+    //
+    // 0 to 30 is low, 40 and 50 moderate, 60 and above high
+    //
+    pub fn from_index(index: i64) -> Self {
+        match index {
+            i if i < 40 => Risk::Low,
+            i if i < 60 => Risk::Moderate,
+            _ => Risk::High,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Risk::Low => "low",
+            Risk::Moderate => "moderate",
+            Risk::High => "high",
+        }
+    }
+
+    pub fn spray_interval(&self, intervals: &SprayIntervals) -> i64 {
+        match self {
+            Risk::Low => intervals.low,
+            Risk::Moderate => intervals.moderate,
+            Risk::High => intervals.high,
+        }
+    }
+}
+
+//
+// the longest continuous stretches of a local day in the favourable band
+// and at or above the heat threshold
+//
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct MildewDay {
+    pub day: NaiveDate,
+    pub favourable_hours: f64,
+    pub heat_minutes: f64,
+}
+
+impl MildewDay {
+    pub fn from_readings(day: NaiveDate, readings: &[LocalReading]) -> Self {
+        let end = day.and_time(NaiveTime::MIN) + Duration::days(1);
+        let favourable = longest(readings, end, |t| t >= FAVOURABLE.0 && t <= FAVOURABLE.1);
+        let heat = longest(readings, end, |t| t >= HEAT);
+        Self {
+            day,
+            favourable_hours: favourable.num_seconds() as f64 / 3600.0,
+            heat_minutes: heat.num_seconds() as f64 / 60.0,
+        }
+    }
+
+    pub fn favourable(&self) -> bool {
+        self.favourable_hours >= FAVOURABLE_HOURS
+    }
+
+    pub fn hot(&self) -> bool {
+        self.heat_minutes >= HEAT_MINUTES
+    }
+}
+
+fn longest(
+    readings: &[LocalReading],
+    end: NaiveDateTime,
+    inside: impl Fn(f64) -> bool,
+) -> Duration {
+    let gap = Duration::minutes(MAX_GAP_MINUTES);
+    let (mut best, mut run) = (Duration::zero(), Duration::zero());
+    for (i, reading) in readings.iter().enumerate() {
+        let next = readings.get(i + 1).map_or(end, |r| r.time.min(end));
+        let held = next - reading.time;
+        if !inside(reading.celsius) {
+            run = Duration::zero();
+            continue;
+        }
+        run += held.min(gap);
+        best = best.max(run);
+        if held > gap {
+            run = Duration::zero();
+        }
+    }
+    best
+}
+
+//
+// a block's day is the mean of those of its sensors that reported that day
+//
+pub fn block_averages(
+    days: &BTreeMap<Series, MildewDay>,
+    day: NaiveDate,
+) -> BTreeMap<Series, MildewDay> {
+    let mut sums: BTreeMap<Series, (f64, f64, f64)> = BTreeMap::new();
+    for (series, mildew) in days {
+        let Some(block) = &series.block else {
+            continue;
+        };
+        let key = Series {
+            source: series.source.clone(),
+            sensor: None,
+            block: Some(block.clone()),
+        };
+        let sum = sums.entry(key).or_insert((0.0, 0.0, 0.0));
+        sum.0 += mildew.favourable_hours;
+        sum.1 += mildew.heat_minutes;
+        sum.2 += 1.0;
+    }
+    sums.into_iter()
+        .map(|(series, (hours, minutes, n))| {
+            let mildew = MildewDay {
+                day,
+                favourable_hours: hours / n,
+                heat_minutes: minutes / n,
+            };
+            (series, mildew)
+        })
+        .collect()
+}
+
+//
+// where a series stands at the end of a day: the index, and while it has
+// not started the favourable days in a row so far
+//
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct MildewIndex {
+    pub index: i64,
+    pub trigger_days: i64,
+}
+
+impl MildewIndex {
+    pub fn started(&self) -> bool {
+        self.index > 0
+    }
+
+    pub fn next(&self, day: &MildewDay) -> Self {
+        if !self.started() {
+            let trigger_days = if day.favourable() {
+                self.trigger_days + 1
+            } else {
+                0
+            };
+            return if trigger_days >= TRIGGER_DAYS {
+                Self {
+                    index: 20 * TRIGGER_DAYS,
+                    trigger_days: 0,
+                }
+            } else {
+                Self {
+                    index: 0,
+                    trigger_days,
+                }
+            };
+        }
+
+        let mut points = if day.favourable() { 20 } else { -10 };
+        if day.hot() {
+            points = (points - 10).max(-10);
+        }
+        Self {
+            index: (self.index + points).clamp(0, 100),
+            trigger_days: 0,
+        }
+    }
+}
+
+pub fn observation(
+    series: &Series,
+    season: &Season,
+    intervals: &SprayIntervals,
+    day: &MildewDay,
+    index: &MildewIndex,
+) -> Observation {
+    let midnight = day.day.and_time(NaiveTime::MIN).and_utc();
+    let risk = Risk::from_index(index.index);
+    let mut observation = Observation::new("disease_risk", midnight.timestamp_micros());
+    observation
+        .symbol("scope", series.scope())
+        .symbol("source", &series.source);
+    if let Some(sensor) = &series.sensor {
+        observation.symbol("sensor", sensor);
+    }
+    if let Some(block) = &series.block {
+        observation.symbol("block", block);
+    }
+    observation
+        .symbol("disease", "powdery_mildew")
+        .symbol("model", "gubler_thomas")
+        .symbol("season", &season.label())
+        .symbol("risk", risk.label())
+        .column_f64("favourable_hours", day.favourable_hours)
+        .column_f64("heat_minutes", day.heat_minutes)
+        .column_i64("risk_index", index.index)
+        .column_i64("trigger_days", index.trigger_days)
+        .column_i64("spray_interval_days", risk.spray_interval(intervals));
+    observation
+}
+
+//
+// carry every series on through yesterday, a day at a time, from the day
+// after the newest row; today is left until it is over. A series that has
+// been quiet, or is new, picks up from there with the index it last had, so
+// one sensor gone for good does not send every update back over the days it
+// missed. Returns the number of rows written.
+//
+pub async fn update(
+    config: &MildewConfig,
+    client: &QueryClient,
+    sink: &mut (dyn Sink + Send),
+) -> Result<usize> {
+    let today = daily::today(client, &config.timezone).await?;
+    let Some(yesterday) = today.pred_opt() else {
+        return Ok(0);
+    };
+    let season = Season::containing(yesterday, config.get_season_start());
+    let mut written = last_written(client, &season).await?;
+    let mut day = written
+        .values()
+        .map(|(last, _)| *last)
+        .max()
+        .and_then(|last| last.succ_opt())
+        .unwrap_or(season.start);
+
+    let mut count = 0;
+    while day <= yesterday {
+        let mut days: BTreeMap<Series, MildewDay> =
+            readings::read_day(client, &config.timezone, day)
+                .await?
+                .into_iter()
+                .map(|(series, readings)| (series, MildewDay::from_readings(day, &readings)))
+                .collect();
+        let blocks = block_averages(&days, day);
+        days.extend(blocks);
+
+        for (series, mildew) in days {
+            let (last, index) = written.get(&series).copied().unwrap_or_default();
+            if written.contains_key(&series) && day <= last {
+                continue;
+            }
+            let index = index.next(&mildew);
+            sink.write(&observation(
+                &series,
+                &season,
+                &config.spray_interval_days,
+                &mildew,
+                &index,
+            ))?;
+            written.insert(series, (day, index));
+            count += 1;
+        }
+        day = match day.succ_opt() {
+            Some(next) => next,
+            None => break,
+        };
+    }
+    Ok(count)
+}
+
+//
+// the newest day written for each series with its index; `last()` follows
+// the designated timestamp, so it is the index of that day
+//
+async fn last_written(
+    client: &QueryClient,
+    season: &Season,
+) -> Result<BTreeMap<Series, (NaiveDate, MildewIndex)>> {
+    let rows = client
+        .keyed_rows("disease_risk", &["source", "sensor", "block"], |keys| {
+            format!(
+                "SELECT {keys}, max(time) last, last(risk_index) risk_index, \
+                 last(trigger_days) trigger_days FROM disease_risk \
+                 WHERE disease = 'powdery_mildew' AND season = {season}",
+                keys = keys,
+                season = quote(&season.label())
+            )
+        })
+        .await?;
+    Ok(rows
+        .iter()
+        .filter_map(|row| {
+            let series = Series {
+                source: get_str(row, "source")?.to_string(),
+                sensor: get_str(row, "sensor").map(str::to_string),
+                block: get_str(row, "block").map(str::to_string),
+            };
+            let last = get_str(row, "last")?.get(..10)?;
+            let last = NaiveDate::parse_from_str(last, "%Y-%m-%d").ok()?;
+            let index = MildewIndex {
+                index: get_f64(row, "risk_index")? as i64,
+                trigger_days: get_f64(row, "trigger_days")? as i64,
+            };
+            Some((series, (last, index)))
+        })
+        .collect())
+}
+
+//
+// update every interval until shutdown is set; a failed update is reported
+// and tried again at the next interval
+//
+pub async fn run(
+    config: &MildewConfig,
+    client: &QueryClient,
+    sink: &mut (dyn Sink + Send),
+    shutdown: &AtomicBool,
+) {
+    let interval = std::time::Duration::from_secs(config.interval_secs.max(1));
+    let mut ticker = tokio::time::interval(std::time::Duration::from_secs(1));
+    let mut next = Instant::now();
+    while !shutdown.load(Ordering::Relaxed) {
+        if Instant::now() >= next {
+            match update(config, client, sink).await {
+                Ok(0) => {}
+                Ok(count) => println!("mildew: wrote {} rows", count),
+                Err(e) => println!("mildew: update failed: {}", e),
+            }
+            next = Instant::now() + interval;
+        }
+        sink.tick();
+        ticker.tick().await;
+    }
+}
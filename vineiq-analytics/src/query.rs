@@ -0,0 +1,149 @@
+//!
+//! Read access to QuestDB through its REST endpoint
+//!
+//! The ILP port is write only, so the analytics read the logged tables back
+//! with SQL over `/exec`.
+//!
+
+use serde_derive::Deserialize;
+use serde_json::{Map, Value};
+use std::collections::BTreeSet;
+
+use crate::error::{Error, Result};
+
+/*
+  {
+    "query": "SELECT ...",
+    "columns": [{"name": "device_id", "type": "SYMBOL"}, ...],
+    "dataset": [["1110", ...], ...],
+    "count": 1
+  }
+
+  or, for a query QuestDB rejects,
+
+  {"query": "SELECT ...", "error": "table does not exist [table=gdd]", "position": 21}
+*/
+#[derive(Deserialize)]
+struct Response {
+    #[serde(default)]
+    columns: Vec<Column>,
+    #[serde(default)]
+    dataset: Vec<Vec<Value>>,
+    error: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Column {
+    name: String,
+}
+
+//
+// one result row, by column name
+//
+pub type Row = Map<String, Value>;
+
+#[derive(Clone)]
+pub struct QueryClient {
+    url: String,
+    client: reqwest::Client,
+}
+
+impl QueryClient {
+    //
+    // the QuestDB HTTP endpoint, e.g. http://vinedb:9000
+    //
+    pub fn new(url: &str) -> Self {
+        Self {
+            url: url.trim_end_matches('/').to_string(),
+            client: reqwest::Client::new(),
+        }
+    }
+
+    pub async fn rows(&self, sql: &str) -> Result<Vec<Row>> {
+        let response = self
+            .client
+            .get(format!("{}/exec", self.url))
+            .query(&[("query", sql)])
+            .send()
+            .await?;
+        let body: Response = response.json().await?;
+        if let Some(error) = body.error {
+            return Err(Error::Query(error));
+        }
+
+        Ok(body
+            .dataset
+            .into_iter()
+            .map(|values| {
+                body.columns
+                    .iter()
+                    .map(|c| c.name.clone())
+                    .zip(values)
+                    .collect()
+            })
+            .collect())
+    }
+
+    //
+    // like `rows`, but a table that has not been written to yet reads as empty
+    //
+    pub async fn rows_if_exists(&self, sql: &str) -> Result<Vec<Row>> {
+        match self.rows(sql).await {
+            Err(Error::Query(e)) if e.contains("does not exist") => Ok(Vec::new()),
+            result => result,
+        }
+    }
+
+    //
+    // the names of a table's columns, none for a table not written to yet
+    //
+    pub async fn table_columns(&self, table: &str) -> Result<BTreeSet<String>> {
+        let rows = self
+            .rows_if_exists(&format!(
+                "SELECT \"column\" FROM table_columns({})",
+                quote(table)
+            ))
+            .await?;
+        Ok(rows
+            .iter()
+            .filter_map(|row| get_str(row, "column"))
+            .map(str::to_string)
+            .collect())
+    }
+
+    //
+    // a symbol column such as `block` only exists once some row has carried
+    // it, so `query` is given the select list of those `keys` the table has.
+    // A table with none of them, or no table at all, reads as empty.
+    //
+    pub async fn keyed_rows<F>(&self, table: &str, keys: &[&str], query: F) -> Result<Vec<Row>>
+    where
+        F: FnOnce(&str) -> String,
+    {
+        let columns = self.table_columns(table).await?;
+        let keys: Vec<&str> = keys
+            .iter()
+            .copied()
+            .filter(|key| columns.contains(*key))
+            .collect();
+        if keys.is_empty() {
+            return Ok(Vec::new());
+        }
+        self.rows(&query(&keys.join(", "))).await
+    }
+}
+
+//
+// a string literal for a query
+//
+pub fn quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "''"))
+}
+
+pub fn get_str<'a>(row: &'a Row, name: &str) -> Option<&'a str> {
+    row.get(name).and_then(Value::as_str)
+}
+
+pub fn get_f64(row: &Row, name: &str) -> Option<f64> {
+    row.get(name).and_then(Value::as_f64)
+}
@@ -0,0 +1,168 @@
+//!
+//! Individual temperature and humidity readings, per sensor
+//!
+//! Read from the `tempest_station` and `yolink` tables over a time window, or
+//! over a local day, and brought back to celsius.
+//!
+
+use chrono::{DateTime, Duration, NaiveDate, NaiveDateTime, NaiveTime, SecondsFormat, Utc};
+use std::collections::BTreeMap;
+use vineiq_core::units::Temperature;
+
+use crate::daily::Series;
+use crate::error::Result;
+use crate::query::{get_f64, get_str, quote, QueryClient, Row};
+
+const TEMPEST_KEYS: &[&str] = &["device_id"];
+const YOLINK_KEYS: &[&str] = &["sensorName", "block"];
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Reading {
+    pub time: DateTime<Utc>,
+    pub celsius: f64,
+    pub humidity: Option<f64>,
+}
+
+//
+// a reading timed by the local clock
+//
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct LocalReading {
+    pub time: NaiveDateTime,
+    pub celsius: f64,
+}
+
+//
+// readings from `from` up to `to`, oldest first
+//
+pub async fn read(
+    client: &QueryClient,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+) -> Result<BTreeMap<Series, Vec<Reading>>> {
+    let mut readings: BTreeMap<Series, Vec<Reading>> = BTreeMap::new();
+
+    let tempest = client
+        .keyed_rows("tempest_station", TEMPEST_KEYS, |keys| {
+            window_query("tempest_station", keys, from, to)
+        })
+        .await?;
+    for row in &tempest {
+        push(&mut readings, tempest_series(row), row);
+    }
+
+    let yolink = client
+        .keyed_rows("yolink", YOLINK_KEYS, |keys| {
+            window_query("yolink", keys, from, to)
+        })
+        .await?;
+    for row in &yolink {
+        push(&mut readings, yolink_series(row), row);
+    }
+
+    readings.retain(|series, _| series.sensor.is_some());
+    Ok(readings)
+}
+
+//
+// the readings of a local day in `timezone`, oldest first. The window is
+// widened by a day either side so that any offset from UTC is covered, then
+// cut back to the day on the local clock.
+//
+pub async fn read_day(
+    client: &QueryClient,
+    timezone: &str,
+    day: NaiveDate,
+) -> Result<BTreeMap<Series, Vec<LocalReading>>> {
+    let midnight = day.and_time(NaiveTime::MIN);
+    let (start, end) = (midnight, midnight + Duration::days(1));
+    let (from, to) = (
+        (start - Duration::days(1)).and_utc(),
+        (end + Duration::days(1)).and_utc(),
+    );
+    let local = format!("to_timezone(time, {}) time", quote(timezone));
+    let mut readings: BTreeMap<Series, Vec<LocalReading>> = BTreeMap::new();
+
+    let tempest = client
+        .keyed_rows("tempest_station", TEMPEST_KEYS, |keys| {
+            local_query("tempest_station", keys, &local, from, to)
+        })
+        .await?;
+    let yolink = client
+        .keyed_rows("yolink", YOLINK_KEYS, |keys| {
+            local_query("yolink", keys, &local, from, to)
+        })
+        .await?;
+    let rows = tempest
+        .iter()
+        .map(|row| (tempest_series(row), row))
+        .chain(yolink.iter().map(|row| (yolink_series(row), row)));
+    for (series, row) in rows {
+        let Some(reading) = parse(row) else {
+            continue;
+        };
+        let time = reading.time.naive_utc();
+        if series.sensor.is_some() && time >= start && time < end {
+            readings.entry(series).or_default().push(LocalReading {
+                time,
+                celsius: reading.celsius,
+            });
+        }
+    }
+    Ok(readings)
+}
+
+fn tempest_series(row: &Row) -> Series {
+    Series {
+        source: "tempest".to_string(),
+        sensor: get_str(row, "device_id").map(str::to_string),
+        block: None,
+    }
+}
+
+fn yolink_series(row: &Row) -> Series {
+    Series {
+        source: "yolink".to_string(),
+        sensor: get_str(row, "sensorName").map(str::to_string),
+        block: get_str(row, "block").map(str::to_string),
+    }
+}
+
+fn window_query(table: &str, keys: &str, from: DateTime<Utc>, to: DateTime<Utc>) -> String {
+    local_query(table, keys, "time", from, to)
+}
+
+fn local_query(
+    table: &str,
+    keys: &str,
+    time: &str,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+) -> String {
+    format!(
+        "SELECT {keys}, units, {time}, temperature, humidity FROM {table} \
+         WHERE time >= {from} AND time < {to} ORDER BY time",
+        keys = keys,
+        time = time,
+        table = table,
+        from = quote(&from.to_rfc3339_opts(SecondsFormat::Micros, true)),
+        to = quote(&to.to_rfc3339_opts(SecondsFormat::Micros, true)),
+    )
+}
+
+fn push(readings: &mut BTreeMap<Series, Vec<Reading>>, series: Series, row: &Row) {
+    if let Some(reading) = parse(row) {
+        readings.entry(series).or_default().push(reading);
+    }
+}
+
+fn parse(row: &Row) -> Option<Reading> {
+    let time = get_str(row, "time").and_then(|t| DateTime::parse_from_rfc3339(t).ok())?;
+    let temperature = get_f64(row, "temperature")?;
+    let unit = Temperature::from_label(get_str(row, "units"));
+    Some(Reading {
+        time: time.with_timezone(&Utc),
+        celsius: unit.to_celsius(temperature),
+        humidity: get_f64(row, "humidity"),
+    })
+}
@@ -0,0 +1,84 @@
+//!
+//! Growing seasons
+//!
+//! A season starts on the same day each year: by default the 1st of April in
+//! the northern hemisphere and the 1st of October in the southern one.
+//!
+
+use chrono::{Datelike, Months, NaiveDate};
+use serde_derive::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Hemisphere {
+    #[default]
+    North,
+    South,
+}
+
+impl Hemisphere {
+    pub fn season_start(&self) -> SeasonStart {
+        match self {
+            Hemisphere::North => SeasonStart { month: 4, day: 1 },
+            Hemisphere::South => SeasonStart { month: 10, day: 1 },
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct SeasonStart {
+    pub month: u32,
+    pub day: u32,
+}
+
+impl SeasonStart {
+    //
+    // "MM-DD"; the 29th of February is refused since most years lack it
+    //
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let date = NaiveDate::parse_from_str(&format!("2001-{}", text), "%Y-%m-%d")
+            .map_err(|e| format!("invalid season start {:?}: {}", text, e))?;
+        Ok(Self {
+            month: date.month(),
+            day: date.day(),
+        })
+    }
+
+    fn in_year(&self, year: i32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, self.month, self.day).expect("valid season start")
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Season {
+    pub start: NaiveDate,
+}
+
+impl Season {
+    //
+    // the season under way on the given day, or the last one to have
+    // started if it has already ended
+    //
+    pub fn containing(day: NaiveDate, start: SeasonStart) -> Self {
+        let this_year = start.in_year(day.year());
+        let start = if day >= this_year {
+            this_year
+        } else {
+            start.in_year(day.year() - 1)
+        };
+        Self { start }
+    }
+
+    //
+    // the last day of a period of whole months from the start
+    //
+    pub fn last_day(&self, months: u32) -> NaiveDate {
+        (self.start + Months::new(months))
+            .pred_opt()
+            .expect("valid date")
+    }
+
+    pub fn label(&self) -> String {
+        self.start.format("%Y-%m-%d").to_string()
+    }
+}
@@ -0,0 +1,54 @@
+//!
+//! Sunrise and sunset
+//!
+
+use chrono::{DateTime, Duration, NaiveDate, NaiveTime, Utc};
+
+/*
+  the sunrise equation, good to a minute or two away from the poles:
+
+    n      = days from 2000-01-01 12:00 UTC to noon of the date
+    J*     = n - longitude / 360                      (east positive)
+    M      = 357.5291 + 0.98560028 J*                 mean anomaly
+    C      = 1.9148 sin M + 0.0200 sin 2M + 0.0003 sin 3M
+    L      = M + C + 180 + 102.9372                   ecliptic longitude
+    Jt     = J* + 0.0053 sin M - 0.0069 sin 2L        solar transit
+    sin d  = sin L sin 23.4397                        declination
+    cos w  = (sin -0.833 - sin lat sin d) / (cos lat cos d)
+
+  sunrise and sunset are Jt -/+ w / 360 days; there are none when the sun
+  stays above or below the horizon all day
+*/
+fn hour_angle(date: NaiveDate, latitude: f64, longitude: f64) -> Option<(DateTime<Utc>, f64)> {
+    let epoch = NaiveDate::from_ymd_opt(2000, 1, 1).expect("valid date");
+    let n = (date - epoch).num_days() as f64;
+    let j = n - longitude / 360.0;
+    let m = (357.5291 + 0.98560028 * j).rem_euclid(360.0).to_radians();
+    let c = 1.9148 * m.sin() + 0.02 * (2.0 * m).sin() + 0.0003 * (3.0 * m).sin();
+    let l = (m.to_degrees() + c + 180.0 + 102.9372)
+        .rem_euclid(360.0)
+        .to_radians();
+    let transit = j + 0.0053 * m.sin() - 0.0069 * (2.0 * l).sin();
+    let declination = (l.sin() * 23.4397_f64.to_radians().sin()).asin();
+
+    let latitude = latitude.to_radians();
+    let cos_w = ((-0.833_f64).to_radians().sin() - latitude.sin() * declination.sin())
+        / (latitude.cos() * declination.cos());
+    if !(-1.0..=1.0).contains(&cos_w) {
+        return None;
+    }
+
+    let noon = epoch.and_time(NaiveTime::from_hms_opt(12, 0, 0).expect("valid time"));
+    let transit = noon.and_utc() + Duration::milliseconds((transit * 86_400_000.0) as i64);
+    Some((transit, cos_w.acos().to_degrees() / 360.0))
+}
+
+pub fn sunrise(date: NaiveDate, latitude: f64, longitude: f64) -> Option<DateTime<Utc>> {
+    hour_angle(date, latitude, longitude)
+        .map(|(transit, w)| transit - Duration::milliseconds((w * 86_400_000.0) as i64))
+}
+
+pub fn sunset(date: NaiveDate, latitude: f64, longitude: f64) -> Option<DateTime<Utc>> {
+    hour_angle(date, latitude, longitude)
+        .map(|(transit, w)| transit + Duration::milliseconds((w * 86_400_000.0) as i64))
+}
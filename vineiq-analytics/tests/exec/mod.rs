@@ -0,0 +1,118 @@
+//!
+//! In-process stand-in for QuestDB's `/exec` endpoint
+//!
+//! Each query is answered with the response of the first route whose text
+//! it contains, and with a QuestDB error when none does. The queries are
+//! kept so a test can check what was asked.
+//!
+
+use serde_json::{json, Value};
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpListener;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use vineiq_analytics::QueryClient;
+
+pub struct ExecServer {
+    url: String,
+    queries: Arc<Mutex<Vec<String>>>,
+}
+
+impl ExecServer {
+    pub fn start<S: Into<String>>(routes: Vec<(S, Value)>) -> Self {
+        let routes: Vec<(String, Value)> = routes
+            .into_iter()
+            .map(|(text, response)| (text.into(), response))
+            .collect();
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let queries = Arc::new(Mutex::new(Vec::new()));
+
+        let asked = queries.clone();
+        thread::spawn(move || {
+            for mut stream in listener.incoming().flatten() {
+                let mut request = String::new();
+                let mut reader = BufReader::new(&stream);
+                let _ = reader.read_line(&mut request);
+                loop {
+                    let mut header = String::new();
+                    if reader.read_line(&mut header).unwrap_or(0) == 0 || header == "\r\n" {
+                        break;
+                    }
+                }
+                let query = query_param(&request);
+                let body = routes
+                    .iter()
+                    .find(|(text, _)| query.contains(text.as_str()))
+                    .map(|(_, response)| response.clone())
+                    .unwrap_or_else(|| error(&format!("unexpected query: {}", query)));
+                asked.lock().unwrap().push(query);
+                let body = body.to_string();
+                let _ = write!(
+                    stream,
+                    "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\n\
+                     Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    body.len(),
+                    body
+                );
+            }
+        });
+
+        Self { url, queries }
+    }
+
+    pub fn client(&self) -> QueryClient {
+        QueryClient::new(&self.url)
+    }
+
+    pub fn queries(&self) -> Vec<String> {
+        self.queries.lock().unwrap().clone()
+    }
+}
+
+//
+// a result set, as QuestDB returns it
+//
+pub fn table(columns: &[&str], dataset: Value) -> Value {
+    let columns: Vec<Value> = columns.iter().map(|name| json!({ "name": name })).collect();
+    json!({ "columns": columns, "dataset": dataset })
+}
+
+pub fn error(message: &str) -> Value {
+    json!({ "error": message })
+}
+
+//
+// the columns of a table, as `table_columns()` lists them
+//
+pub fn columns(names: &[&str]) -> Value {
+    let rows: Vec<Value> = names.iter().map(|name| json!([name])).collect();
+    table(&["column"], Value::Array(rows))
+}
+
+pub fn missing(table: &str) -> Value {
+    error(&format!("table does not exist [table={}]", table))
+}
+
+//
+// "GET /exec?query=SELECT+... HTTP/1.1", form decoded
+//
+fn query_param(request: &str) -> String {
+    let target = request.split_whitespace().nth(1).unwrap_or("");
+    let encoded = target.split_once("query=").map_or("", |(_, q)| q);
+    let encoded = encoded.split('&').next().unwrap_or("");
+    let mut bytes = Vec::new();
+    let mut chars = encoded.bytes();
+    while let Some(c) = chars.next() {
+        match c {
+            b'+' => bytes.push(b' '),
+            b'%' => {
+                let hex: Vec<u8> = chars.by_ref().take(2).collect();
+                let hex = std::str::from_utf8(&hex).unwrap_or("");
+                bytes.push(u8::from_str_radix(hex, 16).unwrap_or(b'?'));
+            }
+            c => bytes.push(c),
+        }
+    }
+    String::from_utf8_lossy(&bytes).to_string()
+}
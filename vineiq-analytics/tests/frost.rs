@@ -0,0 +1,267 @@
+use chrono::{DateTime, Duration, NaiveDate, SecondsFormat, TimeZone, Utc};
+use serde_json::{json, Value};
+use vineiq_analytics::daily::Series;
+use vineiq_analytics::frost::{self, AllenCoefficients, CoolingCurve, FrostConfig, Method, Night};
+use vineiq_analytics::readings::Reading;
+use vineiq_analytics::sun;
+use vineiq_core::testing::CaptureSink;
+use vineiq_core::units::Units;
+
+mod exec;
+use exec::{columns, missing, table, ExecServer};
+
+fn close(actual: f64, expected: f64, tolerance: f64) {
+    assert!(
+        (actual - expected).abs() <= tolerance,
+        "{} is not within {} of {}",
+        actual,
+        tolerance,
+        expected
+    );
+}
+
+fn minutes_between(a: DateTime<Utc>, b: DateTime<Utc>) -> i64 {
+    (a - b).num_minutes().abs()
+}
+
+//
+// readings every 10 minutes for the first two hours after sunset along
+// T = 8 - 3 sqrt(t)
+//
+fn evening(sunset: DateTime<Utc>, humidity: Option<f64>) -> Vec<Reading> {
+    (1..=12)
+        .map(|i| {
+            let hours = i as f64 / 6.0;
+            Reading {
+                time: sunset + Duration::minutes(i * 10),
+                celsius: 8.0 - 3.0 * hours.sqrt(),
+                humidity,
+            }
+        })
+        .collect()
+}
+
+#[test]
+fn sunrise_and_sunset() {
+    //
+    // Napa on the summer solstice: sunset 20:34 PDT, sunrise 05:49 PDT
+    //
+    let date = NaiveDate::from_ymd_opt(2026, 6, 21).unwrap();
+    let sunset = sun::sunset(date, 38.3, -122.29).unwrap();
+    let sunrise = sun::sunrise(date, 38.3, -122.29).unwrap();
+    assert!(minutes_between(sunset, Utc.with_ymd_and_hms(2026, 6, 22, 3, 34, 0).unwrap()) <= 3);
+    assert!(
+        minutes_between(
+            sunrise,
+            Utc.with_ymd_and_hms(2026, 6, 21, 12, 49, 0).unwrap()
+        ) <= 3
+    );
+    //
+    // no sunset in the arctic summer
+    //
+    assert_eq!(sun::sunset(date, 78.2, 15.6), None);
+}
+
+#[test]
+fn brunt_fits_the_square_root_curve() {
+    let sunset = Utc.with_ymd_and_hms(2026, 4, 10, 2, 30, 0).unwrap();
+    let curve = frost::brunt(&evening(sunset, None), sunset).unwrap();
+    close(curve.a, 8.0, 1e-6);
+    close(curve.b, -3.0, 1e-6);
+    //
+    // -1C after 9 hours, below zero from 7h07m
+    //
+    close(curve.at(9.0), -1.0, 1e-6);
+    close(curve.hours_below(0.0, 9.0), 9.0 - 64.0 / 9.0, 1e-6);
+
+    let warming: Vec<Reading> = evening(sunset, None)
+        .into_iter()
+        .map(|r| Reading {
+            celsius: 20.0 - r.celsius,
+            ..r
+        })
+        .collect();
+    let flat = frost::brunt(&warming, sunset).unwrap();
+    assert_eq!(flat.b, 0.0);
+    close(flat.at(9.0), warming.last().unwrap().celsius, 1e-9);
+    assert!(frost::brunt(&warming[..2], sunset).is_none());
+}
+
+#[test]
+fn hours_below() {
+    let curve = CoolingCurve { a: -0.5, b: -1.0 };
+    assert_eq!(curve.hours_below(0.0, 10.0), 10.0);
+    let flat = CoolingCurve { a: 3.0, b: 0.0 };
+    assert_eq!(flat.hours_below(0.0, 10.0), 0.0);
+    let curve = CoolingCurve { a: 4.0, b: -2.0 };
+    assert_eq!(curve.hours_below(0.0, 10.0), 6.0);
+}
+
+#[test]
+fn allen_reaches_its_minimum_at_sunrise() {
+    let sunset = Utc.with_ymd_and_hms(2026, 4, 10, 2, 30, 0).unwrap();
+    let sunrise = sunset + Duration::hours(11);
+    let readings = evening(sunset, Some(60.0));
+    let config = FrostConfig {
+        method: Method::Allen,
+        allen: Some(AllenCoefficients {
+            a: 0.5,
+            b: 0.5,
+            c: -2.0,
+        }),
+        ..FrostConfig::default()
+    };
+    let forecast = frost::forecast(&config, &readings, sunset, sunrise).unwrap();
+
+    let dusk = readings.last().unwrap().celsius;
+    let dew_point = vineiq_core::derived::dew_point(dusk, 60.0).unwrap();
+    close(
+        forecast.predicted_min,
+        0.5 * dusk + 0.5 * dew_point - 2.0,
+        1e-9,
+    );
+    close(forecast.curve.at(2.0), dusk, 1e-9);
+    assert!(forecast.predicted_hours_below > 0.0 && forecast.predicted_hours_below < 9.0);
+    //
+    // no humidity, no dew point, no forecast
+    //
+    assert!(frost::forecast(&config, &evening(sunset, None), sunset, sunrise).is_none());
+}
+
+#[test]
+fn verified_row_carries_both() {
+    let night = Night::new(NaiveDate::from_ymd_opt(2026, 4, 9).unwrap(), 38.3, -122.29).unwrap();
+    let config = FrostConfig::default();
+    let forecast = frost::forecast(
+        &config,
+        &evening(night.sunset, None),
+        night.sunset,
+        night.sunrise,
+    )
+    .unwrap();
+
+    let overnight: Vec<Reading> = (0..=20)
+        .map(|i| Reading {
+            time: night.sunset + Duration::minutes(i * 30),
+            celsius: 6.0 - i as f64 * 0.4,
+            humidity: None,
+        })
+        .collect();
+    let actual = frost::actual(&overnight, 0.0).unwrap();
+    close(actual.min, -2.0, 1e-9);
+    close(actual.hours_below, 2.0, 1e-9);
+
+    let series = Series {
+        source: "tempest".to_string(),
+        sensor: Some("ST-00001".to_string()),
+        block: None,
+    };
+    let row = frost::observation(
+        &config,
+        &series,
+        &night,
+        &Units::metric(),
+        &forecast,
+        Some(&actual),
+    );
+    assert_eq!(row.table, "frost_forecast");
+    assert_eq!(row.time, night.sunset.timestamp_micros());
+    assert_eq!(row.get_symbol("stage"), Some("verified"));
+    assert_eq!(row.get_symbol("night"), Some("2026-04-09"));
+    close(row.get_f64("actual_min").unwrap(), -2.0, 1e-9);
+    close(
+        row.get_f64("error").unwrap(),
+        forecast.predicted_min + 2.0,
+        1e-9,
+    );
+
+    let row = frost::observation(&config, &series, &night, &Units::metric(), &forecast, None);
+    assert_eq!(row.get_symbol("stage"), Some("forecast"));
+    assert_eq!(row.get_f64("actual_min"), None);
+}
+
+#[test]
+fn validates_the_location_and_method() {
+    let config: FrostConfig = serde_yaml::from_str("{ latitude: 38.3 }").unwrap();
+    assert!(config.validate().is_err());
+
+    let config: FrostConfig =
+        serde_yaml::from_str("{ latitude: 138.3, longitude: -122.3 }").unwrap();
+    assert!(config.validate().is_err());
+
+    let config: FrostConfig =
+        serde_yaml::from_str("{ latitude: 38.3, longitude: -122.3, method: allen }").unwrap();
+    assert!(config.validate().is_err());
+
+    let config: FrostConfig = serde_yaml::from_str(
+        "{ latitude: 38.3, longitude: -122.3, method: allen, allen: { a: 0.6, b: 0.5, c: -2.8 } }",
+    )
+    .unwrap();
+    assert!(config.validate().is_ok());
+}
+
+//
+// the evening readings of a YoLink sensor without a block, and nothing
+// written to `frost_forecast` yet
+//
+#[tokio::test]
+async fn update_forecasts_without_a_block_column() {
+    let config: FrostConfig =
+        serde_yaml::from_str("{ latitude: 38.3, longitude: -122.29 }").unwrap();
+    let night = Night::new(NaiveDate::from_ymd_opt(2026, 4, 10).unwrap(), 38.3, -122.29).unwrap();
+    let timestamp = |time: DateTime<Utc>| time.to_rfc3339_opts(SecondsFormat::Micros, true);
+    let readings: Vec<Value> = evening(night.sunset, None)
+        .iter()
+        .map(|r| json!(["Row 4", "metric", timestamp(r.time), r.celsius, null]))
+        .collect();
+    let server = ExecServer::start(vec![
+        (
+            "table_columns('frost_forecast')".to_string(),
+            missing("frost_forecast"),
+        ),
+        (
+            "table_columns('tempest_station')".to_string(),
+            missing("tempest_station"),
+        ),
+        (
+            "table_columns('yolink')".to_string(),
+            columns(&["sensorName", "units", "temperature", "humidity", "time"]),
+        ),
+        (
+            format!("time >= '{}'", timestamp(night.sunset)),
+            table(
+                &["sensorName", "units", "time", "temperature", "humidity"],
+                Value::Array(readings),
+            ),
+        ),
+        (
+            "FROM yolink".to_string(),
+            table(
+                &["sensorName", "units", "time", "temperature", "humidity"],
+                json!([]),
+            ),
+        ),
+    ]);
+
+    let mut sink = CaptureSink::default();
+    let now = night.sunset + Duration::minutes(150);
+    let count = frost::update(&config, &server.client(), &mut sink, &Units::metric(), now)
+        .await
+        .unwrap();
+    assert_eq!(count, 1);
+    let rows = sink.rows();
+    assert_eq!(rows[0].table, "frost_forecast");
+    assert_eq!(rows[0].get_symbol("stage"), Some("forecast"));
+    assert_eq!(rows[0].get_symbol("sensor"), Some("Row 4"));
+    assert_eq!(rows[0].get_symbol("block"), None);
+    let hours = (night.sunrise - night.sunset).num_seconds() as f64 / 3600.0;
+    close(
+        rows[0].get_f64("predicted_min").unwrap(),
+        8.0 - 3.0 * hours.sqrt(),
+        1e-6,
+    );
+    assert!(server
+        .queries()
+        .iter()
+        .all(|query| !query.contains("block")));
+}
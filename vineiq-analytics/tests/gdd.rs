@@ -0,0 +1,223 @@
+use chrono::NaiveDate;
+use serde_json::json;
+use vineiq_analytics::daily::{DailyTemperature, Series};
+use vineiq_analytics::gdd::{self, GddConfig};
+use vineiq_analytics::season::{Hemisphere, Season, SeasonStart};
+use vineiq_core::testing::CaptureSink;
+use vineiq_core::units::Units;
+
+mod exec;
+use exec::{columns, missing, table, ExecServer};
+
+fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+    NaiveDate::from_ymd_opt(year, month, day).unwrap()
+}
+
+fn day(day: NaiveDate, tmin: f64, tmax: f64) -> DailyTemperature {
+    DailyTemperature { day, tmin, tmax }
+}
+
+fn close(actual: f64, expected: f64) {
+    assert!(
+        (actual - expected).abs() <= 1e-9,
+        "{} is not {}",
+        actual,
+        expected
+    );
+}
+
+#[test]
+fn daily_indices() {
+    let warm = day(date(2026, 7, 1), 14.0, 30.0);
+    close(gdd::winkler(&warm), 12.0);
+    close(gdd::huglin(&warm, 1.0), 16.0);
+    close(gdd::huglin(&warm, 1.04), 16.64);
+    //
+    // the mean is capped at 19C and the 16C range adds 0.75
+    //
+    close(gdd::bedd(&warm), 9.0);
+    close(gdd::bedd(&day(date(2026, 7, 1), 10.0, 24.0)), 7.25);
+    //
+    // a narrow range takes some back
+    //
+    close(gdd::bedd(&day(date(2026, 7, 1), 12.0, 18.0)), 4.0);
+
+    let cold = day(date(2026, 4, 2), 0.0, 8.0);
+    close(gdd::winkler(&cold), 0.0);
+    close(gdd::huglin(&cold, 1.0), 0.0);
+    close(gdd::bedd(&cold), 0.0);
+}
+
+#[test]
+fn huglin_coefficient_grows_with_latitude() {
+    close(gdd::huglin_coefficient(38.5), 1.0);
+    close(gdd::huglin_coefficient(45.0), 1.04);
+    close(gdd::huglin_coefficient(-41.3), 1.02);
+    close(gdd::huglin_coefficient(50.0), 1.06);
+}
+
+#[test]
+fn season_start() {
+    assert_eq!(
+        Hemisphere::South.season_start(),
+        SeasonStart { month: 10, day: 1 }
+    );
+    assert_eq!(
+        SeasonStart::parse("03-15").unwrap(),
+        SeasonStart { month: 3, day: 15 }
+    );
+    assert!(SeasonStart::parse("02-29").is_err());
+    assert!(SeasonStart::parse("13-01").is_err());
+
+    let config: GddConfig = serde_yaml::from_str("hemisphere: south").unwrap();
+    assert_eq!(config.get_season_start(), SeasonStart { month: 10, day: 1 });
+    assert_eq!(config.timezone, "UTC");
+    assert!(config.validate().is_ok());
+
+    let config: GddConfig = serde_yaml::from_str("season_start: 02-29").unwrap();
+    assert!(config.validate().is_err());
+    let config: GddConfig = serde_yaml::from_str("latitude: -95.0").unwrap();
+    assert!(config.validate().is_err());
+
+    let north = Hemisphere::North.season_start();
+    assert_eq!(
+        Season::containing(date(2026, 6, 1), north).start,
+        date(2026, 4, 1)
+    );
+    assert_eq!(
+        Season::containing(date(2026, 1, 15), north).start,
+        date(2025, 4, 1)
+    );
+    let south = Hemisphere::South.season_start();
+    assert_eq!(
+        Season::containing(date(2026, 1, 15), south).start,
+        date(2025, 10, 1)
+    );
+}
+
+#[test]
+fn accumulates_within_the_season() {
+    let season = Season::containing(date(2026, 5, 1), Hemisphere::North.season_start());
+    let daily = vec![
+        day(date(2026, 3, 31), 15.0, 25.0),
+        day(date(2026, 4, 1), 10.0, 20.0),
+        day(date(2026, 4, 2), 12.0, 24.0),
+        day(date(2026, 9, 30), 10.0, 20.0),
+        day(date(2026, 10, 1), 10.0, 20.0),
+        day(date(2026, 11, 1), 10.0, 20.0),
+    ];
+    let totals = gdd::accumulate(&season, 1.0, &daily);
+
+    assert_eq!(totals.len(), 4);
+    assert_eq!(totals[0].temperature.day, date(2026, 4, 1));
+    close(totals[0].winkler, 5.0);
+    close(totals[1].winkler, 13.0);
+    close(totals[1].huglin, 7.5 + 11.0);
+    close(totals[2].huglin, 7.5 + 11.0 + 7.5);
+    //
+    // October still counts for Winkler but not for Huglin
+    //
+    close(totals[3].winkler_day, 5.0);
+    close(totals[3].winkler, 23.0);
+    close(totals[3].huglin_day, 0.0);
+    close(totals[3].huglin, 26.0);
+    assert_eq!(totals[3].days, 4);
+}
+
+#[test]
+fn writes_a_row_per_day() {
+    let season = Season::containing(date(2026, 5, 1), Hemisphere::North.season_start());
+    let series = Series {
+        source: "yolink".to_string(),
+        sensor: Some("Row 4".to_string()),
+        block: Some("north".to_string()),
+    };
+    let totals = gdd::accumulate(&season, 1.0, &[day(date(2026, 4, 1), 10.0, 20.0)]);
+    let row = gdd::observation(&series, &season, &Units::imperial(), &totals[0]);
+
+    assert_eq!(row.table, "gdd");
+    assert_eq!(row.time, 1_775_001_600_000_000);
+    assert_eq!(row.get_symbol("scope"), Some("sensor"));
+    assert_eq!(row.get_symbol("block"), Some("north"));
+    assert_eq!(row.get_symbol("season"), Some("2026-04-01"));
+    assert_eq!(row.get_symbol("units"), Some("imperial"));
+    close(row.get_f64("tmax").unwrap(), 68.0);
+    close(row.get_f64("winkler").unwrap(), 9.0);
+}
+
+//
+// a YoLink sensor never given a block, so neither `yolink` nor `gdd` has
+// a block column, and no Tempest station at all
+//
+fn unblocked(gdd: serde_json::Value, last: serde_json::Value) -> ExecServer {
+    ExecServer::start(vec![
+        (
+            "now()",
+            table(&["today"], json!([["2026-04-04T00:00:00.000000Z"]])),
+        ),
+        (
+            "table_columns('tempest_station')",
+            missing("tempest_station"),
+        ),
+        (
+            "table_columns('yolink')",
+            columns(&["sensorName", "units", "temperature", "humidity", "time"]),
+        ),
+        ("table_columns('gdd')", gdd),
+        (
+            "FROM yolink",
+            table(
+                &["sensorName", "units", "day", "tmin", "tmax"],
+                json!([
+                    ["Row 4", "metric", "2026-04-01T00:00:00.000000Z", 8.0, 22.0],
+                    ["Row 4", "metric", "2026-04-02T00:00:00.000000Z", 9.0, 24.0],
+                    ["Row 4", "metric", "2026-04-03T00:00:00.000000Z", 10.0, 26.0],
+                    ["Row 4", "metric", "2026-04-04T00:00:00.000000Z", 11.0, 20.0]
+                ]),
+            ),
+        ),
+        ("FROM gdd", last),
+    ])
+}
+
+#[tokio::test]
+async fn update_without_a_block_column() {
+    let config = GddConfig::default();
+    let units = Units::metric();
+
+    let server = unblocked(missing("gdd"), missing("gdd"));
+    let mut sink = CaptureSink::default();
+    let count = gdd::update(&config, &server.client(), &mut sink, &units)
+        .await
+        .unwrap();
+    assert_eq!(count, 3);
+    let rows = sink.rows();
+    assert_eq!(rows[0].get_symbol("sensor"), Some("Row 4"));
+    assert_eq!(rows[0].get_symbol("block"), None);
+    assert_eq!(rows[2].get_f64("winkler"), Some(5.0 + 6.5 + 8.0));
+    assert!(server
+        .queries()
+        .iter()
+        .all(|query| !query.contains("block")));
+
+    //
+    // once written, only the days after the last one are
+    //
+    let server = unblocked(
+        columns(&["scope", "source", "sensor", "season", "time"]),
+        table(
+            &["source", "sensor", "last"],
+            json!([["yolink", "Row 4", "2026-04-02T00:00:00.000000Z"]]),
+        ),
+    );
+    let mut sink = CaptureSink::default();
+    let count = gdd::update(&config, &server.client(), &mut sink, &units)
+        .await
+        .unwrap();
+    assert_eq!(count, 1);
+    assert_eq!(sink.rows()[0].time, 1775174400000000);
+    assert!(server
+        .queries()
+        .iter()
+        .all(|query| !query.contains("block")));
+}
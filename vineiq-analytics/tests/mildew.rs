@@ -0,0 +1,306 @@
+use chrono::{NaiveDate, NaiveDateTime};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use vineiq_analytics::daily::Series;
+use vineiq_analytics::mildew::{self, MildewConfig, MildewDay, MildewIndex, Risk, SprayIntervals};
+use vineiq_analytics::readings::LocalReading;
+use vineiq_analytics::season::{Season, SeasonStart};
+use vineiq_core::testing::CaptureSink;
+use vineiq_core::Field;
+
+mod exec;
+use exec::{columns, missing, table, ExecServer};
+
+fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+    NaiveDate::from_ymd_opt(year, month, day).unwrap()
+}
+
+fn at(day: NaiveDate, hour: u32, minute: u32) -> NaiveDateTime {
+    day.and_hms_opt(hour, minute, 0).unwrap()
+}
+
+fn reading(time: NaiveDateTime, celsius: f64) -> LocalReading {
+    LocalReading { time, celsius }
+}
+
+fn mildew_day(favourable_hours: f64, heat_minutes: f64) -> MildewDay {
+    MildewDay {
+        day: date(2026, 6, 1),
+        favourable_hours,
+        heat_minutes,
+    }
+}
+
+fn close(actual: f64, expected: f64) {
+    assert!(
+        (actual - expected).abs() <= 1e-9,
+        "{} is not {}",
+        actual,
+        expected
+    );
+}
+
+#[test]
+fn continuous_hours_in_the_band() {
+    let day = date(2026, 6, 1);
+    //
+    // hourly readings: 25C from 10:00 to 16:00 held until the 20C at 17:00
+    //
+    let mut readings: Vec<LocalReading> = (10..17).map(|h| reading(at(day, h, 0), 25.0)).collect();
+    readings.push(reading(at(day, 17, 0), 20.0));
+    let summary = MildewDay::from_readings(day, &readings);
+    close(summary.favourable_hours, 7.0);
+    close(summary.heat_minutes, 0.0);
+    assert!(summary.favourable());
+
+    //
+    // a four hour outage breaks the run, each side holding for 90 minutes
+    //
+    let readings = [
+        reading(at(day, 10, 0), 25.0),
+        reading(at(day, 14, 0), 25.0),
+        reading(at(day, 20, 0), 15.0),
+    ];
+    let summary = MildewDay::from_readings(day, &readings);
+    close(summary.favourable_hours, 1.5);
+    assert!(!summary.favourable());
+
+    //
+    // the last reading of the day holds until midnight at most
+    //
+    let readings = [reading(at(day, 23, 30), 22.0)];
+    close(
+        MildewDay::from_readings(day, &readings).favourable_hours,
+        0.5,
+    );
+}
+
+#[test]
+fn minutes_of_heat() {
+    let day = date(2026, 7, 20);
+    let readings: Vec<LocalReading> = [29.0, 35.5, 36.0, 35.2, 34.0, 20.0]
+        .iter()
+        .enumerate()
+        .map(|(i, t)| reading(at(day, 14, 5 * i as u32), *t))
+        .collect();
+    let summary = MildewDay::from_readings(day, &readings);
+    close(summary.heat_minutes, 15.0);
+    assert!(summary.hot());
+    close(summary.favourable_hours, 5.0 / 60.0);
+}
+
+#[test]
+fn index_starts_scores_and_resets() {
+    let good = mildew_day(7.0, 0.0);
+    let poor = mildew_day(2.0, 0.0);
+    let hot = mildew_day(7.0, 30.0);
+    let scorching = mildew_day(1.0, 60.0);
+
+    let mut index = MildewIndex::default();
+    let mut scores = Vec::new();
+    for day in [
+        good, good, poor, good, good, good, hot, good, good, good, scorching, poor,
+    ] {
+        index = index.next(&day);
+        scores.push((index.index, index.trigger_days));
+    }
+    assert_eq!(
+        scores,
+        vec![
+            (0, 1),
+            (0, 2),
+            (0, 0),
+            (0, 1),
+            (0, 2),
+            (60, 0),
+            (70, 0),
+            (90, 0),
+            (100, 0),
+            (100, 0),
+            (90, 0),
+            (80, 0)
+        ]
+    );
+
+    //
+    // down to 0 the index waits for three more days before it starts again
+    //
+    let mut index = MildewIndex {
+        index: 10,
+        trigger_days: 0,
+    };
+    index = index.next(&poor);
+    assert_eq!(index, MildewIndex::default());
+    index = index.next(&good).next(&good);
+    assert!(!index.started());
+    assert_eq!(index.next(&good).index, 60);
+}
+
+#[test]
+fn risk_and_spray_interval() {
+    let intervals = SprayIntervals::default();
+    let levels: Vec<(&str, i64)> = [0, 30, 40, 50, 60, 100]
+        .iter()
+        .map(|i| {
+            let risk = Risk::from_index(*i);
+            (risk.label(), risk.spray_interval(&intervals))
+        })
+        .collect();
+    assert_eq!(
+        levels,
+        vec![
+            ("low", 14),
+            ("low", 14),
+            ("moderate", 10),
+            ("moderate", 10),
+            ("high", 7),
+            ("high", 7)
+        ]
+    );
+}
+
+#[test]
+fn block_rows() {
+    let day = date(2026, 6, 1);
+    let sensor = |name: &str, block: &str| Series {
+        source: "yolink".to_string(),
+        sensor: Some(name.to_string()),
+        block: Some(block.to_string()),
+    };
+    let mut days = BTreeMap::new();
+    days.insert(sensor("Row 4", "north"), mildew_day(8.0, 0.0));
+    days.insert(sensor("Row 9", "north"), mildew_day(4.0, 20.0));
+    let blocks = mildew::block_averages(&days, day);
+    assert_eq!(blocks.len(), 1);
+    let (series, block) = blocks.iter().next().unwrap();
+    assert_eq!(series.scope(), "block");
+    close(block.favourable_hours, 6.0);
+    close(block.heat_minutes, 10.0);
+
+    let config: MildewConfig =
+        serde_yaml::from_str("{ spray_interval_days: { low: 21, moderate: 14, high: 10 } }")
+            .unwrap();
+    assert!(config.validate().is_ok());
+    let bad: MildewConfig = serde_yaml::from_str("season_start: 04-31").unwrap();
+    assert!(bad.validate().is_err());
+    let season = Season::containing(day, SeasonStart { month: 4, day: 1 });
+    let index = MildewIndex {
+        index: 40,
+        trigger_days: 0,
+    };
+    let row = mildew::observation(series, &season, &config.spray_interval_days, block, &index);
+    assert_eq!(row.table, "disease_risk");
+    assert_eq!(row.get_symbol("block"), Some("north"));
+    assert_eq!(row.get_symbol("disease"), Some("powdery_mildew"));
+    assert_eq!(row.get_symbol("risk"), Some("moderate"));
+    assert_eq!(row.get_symbol("season"), Some("2026-04-01"));
+    assert_eq!(row.get_f64("favourable_hours"), Some(6.0));
+    assert!(row
+        .columns
+        .contains(&("spray_interval_days".to_string(), Field::I64(14))));
+}
+
+//
+// hourly readings at 25C from 10:00 to 17:00, a favourable day
+//
+fn favourable_day(sensor: &str, day: &str) -> Vec<Value> {
+    (10..=17)
+        .map(|hour| {
+            json!([
+                sensor,
+                "metric",
+                format!("{}T{:02}:00:00.000000Z", day, hour),
+                25.0,
+                null
+            ])
+        })
+        .collect()
+}
+
+//
+// `disease_risk` written before any sensor had a block, so the column
+// does not exist
+//
+#[tokio::test]
+async fn update_carries_the_index_on_without_a_block_column() {
+    let server = ExecServer::start(vec![
+        (
+            "now()",
+            table(&["today"], json!([["2026-06-04T00:00:00.000000Z"]])),
+        ),
+        (
+            "table_columns('disease_risk')",
+            columns(&[
+                "scope",
+                "source",
+                "sensor",
+                "risk_index",
+                "trigger_days",
+                "time",
+            ]),
+        ),
+        (
+            "FROM disease_risk",
+            table(
+                &["source", "sensor", "last", "risk_index", "trigger_days"],
+                json!([
+                    ["yolink", "Row 4", "2026-06-02T00:00:00.000000Z", 60, 0],
+                    ["yolink", "Row 9", "2026-05-01T00:00:00.000000Z", 0, 2]
+                ]),
+            ),
+        ),
+        (
+            "table_columns('tempest_station')",
+            missing("tempest_station"),
+        ),
+        (
+            "table_columns('yolink')",
+            columns(&["sensorName", "units", "temperature", "humidity", "time"]),
+        ),
+        (
+            "FROM yolink",
+            table(
+                &["sensorName", "units", "time", "temperature", "humidity"],
+                Value::Array(
+                    [
+                        favourable_day("Row 4", "2026-06-03"),
+                        favourable_day("Row 9", "2026-06-03"),
+                    ]
+                    .concat(),
+                ),
+            ),
+        ),
+    ]);
+
+    let mut sink = CaptureSink::default();
+    let config = MildewConfig::default();
+    let count = mildew::update(&config, &server.client(), &mut sink)
+        .await
+        .unwrap();
+    assert_eq!(count, 2);
+    let rows = sink.rows();
+    assert_eq!(rows[0].get_symbol("sensor"), Some("Row 4"));
+    assert_eq!(rows[0].get_symbol("block"), None);
+    assert!(rows[0]
+        .columns
+        .contains(&("risk_index".to_string(), Field::I64(80))));
+    assert!(server
+        .queries()
+        .iter()
+        .all(|query| !query.contains("block")));
+
+    //
+    // Row 9, quiet since May, carries on from its own index but only from
+    // the day after the newest row: a single day is read
+    //
+    assert_eq!(rows[1].get_symbol("sensor"), Some("Row 9"));
+    assert!(rows[1]
+        .columns
+        .contains(&("risk_index".to_string(), Field::I64(60))));
+    let days = server
+        .queries()
+        .iter()
+        .filter(|query| query.contains("FROM yolink"))
+        .count();
+    assert_eq!(days, 1);
+}
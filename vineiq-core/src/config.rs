@@ -0,0 +1,91 @@
+//!
+//! Configuration sections shared by the loggers
+//!
+
+use serde_derive::{Deserialize, Serialize};
+use std::time::Duration;
+
+use crate::dead_letter::DeadLetter;
+use crate::ilp::{Batch, QuestDbSink};
+use crate::spool::{self, Spool};
+
+pub const DEFAULT_SPOOL_DIR: &str = "/opt/vineiq/spool";
+
+#[derive(Serialize, Deserialize, PartialEq, Clone, Debug)]
+#[serde(default)]
+pub struct SpoolConfig {
+    pub path: Option<String>,
+    pub max_bytes: u64,
+}
+
+impl Default for SpoolConfig {
+    fn default() -> Self {
+        Self {
+            path: None,
+            max_bytes: spool::DEFAULT_MAX_BYTES,
+        }
+    }
+}
+
+impl SpoolConfig {
+    //
+    // each logger gets its own spool file unless a path is configured
+    //
+    pub fn open(&self, name: &str) -> Spool {
+        let path = match &self.path {
+            Some(path) => path.clone(),
+            None => format!("{}/{}.ilp", DEFAULT_SPOOL_DIR, name),
+        };
+        Spool::new(&path, self.max_bytes)
+    }
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Clone, Debug)]
+#[serde(default)]
+pub struct BatchConfig {
+    pub rows: usize,
+    pub interval_secs: u64,
+}
+
+impl Default for BatchConfig {
+    fn default() -> Self {
+        Self {
+            rows: 100,
+            interval_secs: 5,
+        }
+    }
+}
+
+impl From<&BatchConfig> for Batch {
+    fn from(config: &BatchConfig) -> Self {
+        Batch {
+            rows: config.rows,
+            interval: Duration::from_secs(config.interval_secs),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Clone, Debug, Default)]
+#[serde(default)]
+pub struct DeadLetterConfig {
+    pub path: Option<String>,
+}
+
+impl DeadLetterConfig {
+    pub fn open(&self, name: &str) -> DeadLetter {
+        let path = match &self.path {
+            Some(path) => path.clone(),
+            None => format!("{}/{}.dead.jsonl", DEFAULT_SPOOL_DIR, name),
+        };
+        DeadLetter::new(name, Some(&path))
+    }
+}
+
+pub fn questdb_sink(
+    db_url: &str,
+    name: &str,
+    spool: &SpoolConfig,
+    batch: &BatchConfig,
+) -> QuestDbSink {
+    QuestDbSink::new(db_url, spool.open(name), batch.into())
+}
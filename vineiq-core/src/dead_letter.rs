@@ -0,0 +1,85 @@
+//!
+//! Dead-letter file for messages that could not be processed
+//!
+
+use serde_derive::{Deserialize, Serialize};
+use std::fmt::Display;
+use std::fs::{self, OpenOptions};
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::jsonl;
+
+//
+// one JSON line per rejected message; the payload is kept exactly as it was
+// received so it can be fed through the parser again once that is fixed
+//
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Record {
+    pub time: i64,
+    pub source: String,
+    pub reason: String,
+    pub payload: String,
+}
+
+pub struct DeadLetter {
+    source: String,
+    path: Option<PathBuf>,
+    count: u64,
+}
+
+impl DeadLetter {
+    //
+    // without a path rejected messages are only logged and counted
+    //
+    pub fn new(source: &str, path: Option<&str>) -> Self {
+        Self {
+            source: source.to_string(),
+            path: path.map(PathBuf::from),
+            count: 0,
+        }
+    }
+
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    pub fn record(&mut self, payload: &str, reason: &dyn Display) {
+        self.count += 1;
+        println!(
+            "Error processing {} message ({} rejected so far): {} -- {}",
+            self.source, self.count, reason, payload
+        );
+        let Some(path) = &self.path else {
+            return;
+        };
+        let record = Record {
+            time: jsonl::epoch_millis(),
+            source: self.source.clone(),
+            reason: reason.to_string(),
+            payload: payload.to_string(),
+        };
+        if let Err(e) = append(path, &record) {
+            println!(
+                "Error writing to dead-letter file {}: {}",
+                path.display(),
+                e
+            );
+        }
+    }
+}
+
+fn append(path: &Path, record: &Record) -> io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    //
+    // opened per record, so the file can be moved aside while a logger runs
+    //
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    jsonl::write_line(&mut file, record)
+}
+
+pub fn read(path: &Path) -> io::Result<Vec<Record>> {
+    jsonl::read(path)
+}
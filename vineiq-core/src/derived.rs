@@ -0,0 +1,170 @@
+//!
+//! Metrics derived from the raw sensor readings
+//!
+//! Everything here takes celsius, relative humidity in percent and wind in
+//! m/s, and returns celsius; the appenders convert to the configured units
+//! when the row is written.
+//!
+
+use crate::observation::Observation;
+use crate::units::{to_fahrenheit, Units};
+
+//
+// vapor pressure deficit in kPa, using the Tetens equation for the
+// saturation vapor pressure over water at the given air temperature
+//
+pub fn vpd(celsius: f64, humidity: f64) -> f64 {
+    let saturation = 0.6108 * ((17.27 * celsius) / (celsius + 237.3)).exp();
+    let actual = saturation * (humidity / 100.0);
+    saturation - actual
+}
+
+/*
+  dew point from the Magnus formula with the Alduchov & Eskridge (1996)
+  coefficients, good to about 0.35C between -40C and 50C:
+
+    gamma = ln(RH / 100) + b * T / (c + T)
+    Td    = c * gamma / (b - gamma)        b = 17.625, c = 243.04C
+
+  undefined for a humidity of zero
+*/
+pub fn dew_point(celsius: f64, humidity: f64) -> Option<f64> {
+    if humidity <= 0.0 {
+        return None;
+    }
+    const B: f64 = 17.625;
+    const C: f64 = 243.04;
+    let gamma = (humidity / 100.0).ln() + B * celsius / (C + celsius);
+    Some(C * gamma / (B - gamma))
+}
+
+/*
+  heat index, following the US National Weather Service: Steadman's simple
+  formula, replaced by the Rothfusz regression when the result is 80F or
+  above, with the NWS adjustments for very dry and very humid air.
+  Computed in fahrenheit and returned in celsius.
+*/
+pub fn heat_index(celsius: f64, humidity: f64) -> f64 {
+    let t = to_fahrenheit(celsius);
+    let rh = humidity;
+
+    let simple = 0.5 * (t + 61.0 + ((t - 68.0) * 1.2) + (rh * 0.094));
+    let fahrenheit = if (simple + t) / 2.0 < 80.0 {
+        simple
+    } else {
+        let mut hi = -42.379 + 2.04901523 * t + 10.14333127 * rh
+            - 0.22475541 * t * rh
+            - 0.00683783 * t * t
+            - 0.05481717 * rh * rh
+            + 0.00122874 * t * t * rh
+            + 0.00085282 * t * rh * rh
+            - 0.00000199 * t * t * rh * rh;
+        if rh < 13.0 && (80.0..=112.0).contains(&t) {
+            hi -= ((13.0 - rh) / 4.0) * ((17.0 - (t - 95.0).abs()) / 17.0).sqrt();
+        } else if rh > 85.0 && (80.0..=87.0).contains(&t) {
+            hi += ((rh - 85.0) / 10.0) * ((87.0 - t) / 5.0);
+        }
+        hi
+    };
+    (fahrenheit - 32.0) / 1.8
+}
+
+/*
+  wind chill, from the 2001 North American (JAG/TI) formula with the wind
+  in km/h at 10m:
+
+    WC = 13.12 + 0.6215 T - 11.37 V^0.16 + 0.3965 T V^0.16
+
+  only defined at or below 10C with more than 4.8 km/h of wind
+*/
+pub fn wind_chill(celsius: f64, wind: f64) -> Option<f64> {
+    let kph = wind * 3.6;
+    if celsius > 10.0 || kph <= 4.8 {
+        return None;
+    }
+    let v = kph.powf(0.16);
+    Some(13.12 + 0.6215 * celsius - 11.37 * v + 0.3965 * celsius * v)
+}
+
+//
+// what the air feels like: the wind chill when it is cold and windy, the
+// heat index from 26.7C (80F) up, and the air temperature in between
+//
+pub fn feels_like(celsius: f64, humidity: f64, wind: f64) -> f64 {
+    if let Some(chill) = wind_chill(celsius, wind) {
+        chill
+    } else if celsius >= 26.7 {
+        heat_index(celsius, humidity)
+    } else {
+        celsius
+    }
+}
+
+/*
+  wet-bulb temperature from Stull (2011), an empirical fit at sea level
+  pressure, good to about 1C for 5% to 99% humidity and -20C to 50C:
+
+    Tw = T atan(0.151977 (RH + 8.313659)^0.5) + atan(T + RH)
+         - atan(RH - 1.676331) + 0.00391838 RH^1.5 atan(0.023101 RH)
+         - 4.686035
+*/
+pub fn wet_bulb(celsius: f64, humidity: f64) -> f64 {
+    let t = celsius;
+    let rh = humidity;
+    t * (0.151977 * (rh + 8.313659).sqrt()).atan() + (t + rh).atan() - (rh - 1.676331).atan()
+        + 0.00391838 * rh.powf(1.5) * (0.023101 * rh).atan()
+        - 4.686035
+}
+
+//
+// temperature and humidity with what can be derived from them alone; the
+// derived columns need both readings, so they are left out when either one
+// is null
+//
+pub fn append_climate(
+    observation: &mut Observation,
+    units: &Units,
+    celsius: Option<f64>,
+    humidity: Option<f64>,
+) {
+    observation
+        .column_opt_f64("temperature", celsius.map(|c| units.temperature(c)))
+        .column_opt_f64("humidity", humidity);
+    if let (Some(celsius), Some(humidity)) = (celsius, humidity) {
+        observation
+            .column_f64("vpd", vpd(celsius, humidity))
+            .column_opt_f64(
+                "dew_point",
+                dew_point(celsius, humidity).map(|c| units.temperature(c)),
+            )
+            .column_f64(
+                "heat_index",
+                units.temperature(heat_index(celsius, humidity)),
+            )
+            .column_f64("wet_bulb", units.temperature(wet_bulb(celsius, humidity)));
+    }
+}
+
+//
+// for stations that also measure the wind
+//
+pub fn append_feels_like(
+    observation: &mut Observation,
+    units: &Units,
+    celsius: Option<f64>,
+    humidity: Option<f64>,
+    wind: Option<f64>,
+) {
+    let (Some(celsius), Some(humidity), Some(wind)) = (celsius, humidity, wind) else {
+        return;
+    };
+    observation
+        .column_opt_f64(
+            "wind_chill",
+            wind_chill(celsius, wind).map(|c| units.temperature(c)),
+        )
+        .column_f64(
+            "feels_like",
+            units.temperature(feels_like(celsius, humidity, wind)),
+        );
+}
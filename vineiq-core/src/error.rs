@@ -0,0 +1,44 @@
+//!
+//! Errors surfaced while processing a message
+//!
+
+use std::fmt;
+
+#[derive(Debug)]
+pub enum Error {
+    Database(questdb::Error),
+    Io(std::io::Error),
+    Parse(serde_json::Error),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Database(e) => write!(f, "database error: {}", e),
+            Error::Io(e) => write!(f, "i/o error: {}", e),
+            Error::Parse(e) => write!(f, "parse error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<questdb::Error> for Error {
+    fn from(e: questdb::Error) -> Self {
+        Error::Database(e)
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(e: std::io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(e: serde_json::Error) -> Self {
+        Error::Parse(e)
+    }
+}
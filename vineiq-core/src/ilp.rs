@@ -0,0 +1,241 @@
+//!
+//! QuestDB sink writing the InfluxDB line protocol over TCP
+//!
+//! Flushes block on the network, so at runtime the sink is run on a thread
+//! of its own by `writer::WriterSink`.
+//!
+
+use std::io::{self, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::{Duration, Instant};
+
+use questdb::ingress::{Buffer, TimestampMicros, TimestampNanos};
+
+use crate::line::{parse_line, Line, Value};
+use crate::observation::{Field, Observation};
+use crate::sink::Sink;
+use crate::spool::Spool;
+use crate::Result;
+
+const RECONNECT_INTERVAL: Duration = Duration::from_secs(10);
+const REPLAY_CHUNK_BYTES: usize = 256 * 1024;
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+const WRITE_TIMEOUT: Duration = Duration::from_secs(10);
+
+//
+// ILP over TCP is a one way stream of text. The questdb client has no
+// connect or write timeout for it, so the sink keeps the socket itself and
+// only uses the client's `Buffer` to build the rows.
+//
+struct Connection {
+    stream: TcpStream,
+}
+
+impl Connection {
+    fn open(db_url: &str) -> io::Result<Self> {
+        let mut failed = io::Error::new(io::ErrorKind::NotFound, "no address to connect to");
+        for addr in db_url.to_socket_addrs()? {
+            match TcpStream::connect_timeout(&addr, CONNECT_TIMEOUT) {
+                Ok(stream) => {
+                    stream.set_write_timeout(Some(WRITE_TIMEOUT))?;
+                    stream.set_nodelay(true)?;
+                    return Ok(Self { stream });
+                }
+                Err(e) => failed = e,
+            }
+        }
+        Err(failed)
+    }
+
+    fn flush(&mut self, buffer: &mut Buffer) -> io::Result<()> {
+        self.stream.write_all(buffer.as_str().as_bytes())?;
+        buffer.clear();
+        Ok(())
+    }
+}
+
+pub struct Batch {
+    pub rows: usize,
+    pub interval: Duration,
+}
+
+pub struct QuestDbSink {
+    db_url: String,
+    db_appender: Option<Connection>,
+    connect_attempted_at: Option<Instant>,
+    spool: Spool,
+    buffer: Buffer,
+    batch_rows: usize,
+    batch_interval: Duration,
+    flushed_at: Instant,
+}
+
+impl QuestDbSink {
+    pub fn new(db_url: &str, spool: Spool, batch: Batch) -> Self {
+        let mut sink = Self {
+            db_url: db_url.to_string(),
+            db_appender: None,
+            connect_attempted_at: None,
+            spool,
+            buffer: Buffer::new(),
+            batch_rows: batch.rows,
+            batch_interval: batch.interval,
+            flushed_at: Instant::now(),
+        };
+        sink.connect();
+        sink
+    }
+
+    fn connect(&mut self) {
+        if let Some(attempted_at) = self.connect_attempted_at {
+            if attempted_at.elapsed() < RECONNECT_INTERVAL {
+                return;
+            }
+        }
+        self.connect_attempted_at = Some(Instant::now());
+        match Connection::open(&self.db_url) {
+            Ok(connection) => {
+                println!("connected to questdb at {}", self.db_url);
+                self.db_appender = Some(connection);
+            }
+            Err(e) => println!("Error: failed to connect to questdb: {}", e),
+        }
+    }
+
+    //
+    // write the batch to QuestDB, replaying any spooled rows first; while the
+    // database is unreachable the rows go to the spool instead. Whatever was
+    // replayed leaves the spool, even when the connection fails before the
+    // rest of the spool or the batch is through.
+    //
+    fn flush(&mut self) {
+        self.flushed_at = Instant::now();
+        if self.buffer.is_empty() {
+            return;
+        }
+        if self.db_appender.is_none() {
+            self.connect();
+        }
+        if let Some(connection) = self.db_appender.as_mut() {
+            let (replayed, result) = Self::replay(&self.spool, connection);
+            if replayed > 0 {
+                println!("replayed {} spooled bytes to questdb", replayed);
+                if let Err(e) = self.spool.consume(replayed) {
+                    println!("Error truncating spool: {}", e);
+                }
+            }
+            match result.and_then(|()| Ok(connection.flush(&mut self.buffer)?)) {
+                Ok(()) => return,
+                Err(e) => {
+                    println!("Error flushing to questdb: {} -- spooling rows", e);
+                    self.db_appender = None;
+                }
+            }
+        }
+        if let Err(e) = self.spool.append(self.buffer.as_str()) {
+            println!(
+                "Error writing to spool: {} -- dropping {} rows",
+                e,
+                self.buffer.row_count()
+            );
+        }
+        self.buffer.clear();
+    }
+
+    fn replay(spool: &Spool, connection: &mut Connection) -> (u64, Result<()>) {
+        spool.replay(REPLAY_CHUNK_BYTES, |chunk| {
+            let mut buffer = Buffer::new();
+            for text in chunk.lines().filter(|text| !text.is_empty()) {
+                buffer.set_marker()?;
+                let appended = parse_line(text).and_then(|line| {
+                    Self::append_line(&mut buffer, &line).map_err(|e| e.to_string())
+                });
+                if let Err(e) = appended {
+                    buffer.rewind_to_marker()?;
+                    println!("skipping unreadable spooled row {:?}: {}", text, e);
+                }
+                buffer.clear_marker();
+            }
+            if !buffer.is_empty() {
+                connection.flush(&mut buffer)?;
+            }
+            Ok(())
+        })
+    }
+
+    fn append_line(buffer: &mut Buffer, line: &Line) -> Result<()> {
+        buffer.table(line.table.as_str())?;
+        for (name, value) in &line.symbols {
+            buffer.symbol(name.as_str(), value)?;
+        }
+        for (name, value) in &line.columns {
+            match value {
+                Value::Float(v) => buffer.column_f64(name.as_str(), *v)?,
+                Value::Integer(v) => buffer.column_i64(name.as_str(), *v)?,
+                Value::Boolean(v) => buffer.column_bool(name.as_str(), *v)?,
+                Value::String(v) => buffer.column_str(name.as_str(), v)?,
+                Value::Timestamp(v) => buffer.column_ts(name.as_str(), TimestampMicros::new(*v))?,
+            };
+        }
+        match line.timestamp {
+            Some(nanos) => buffer.at(TimestampNanos::new(nanos))?,
+            None => buffer.at_now()?,
+        }
+        Ok(())
+    }
+
+    fn append_row(buffer: &mut Buffer, observation: &Observation) -> Result<()> {
+        buffer.table(observation.table.as_str())?;
+        for (name, value) in &observation.symbols {
+            buffer.symbol(name.as_str(), value)?;
+        }
+        for (name, value) in &observation.columns {
+            match value {
+                Field::F64(v) => buffer.column_f64(name.as_str(), *v)?,
+                Field::I64(v) => buffer.column_i64(name.as_str(), *v)?,
+                Field::Bool(v) => buffer.column_bool(name.as_str(), *v)?,
+                Field::Str(v) => buffer.column_str(name.as_str(), v)?,
+            };
+        }
+        buffer
+            .column_ts("time", TimestampMicros::new(observation.time))?
+            .at(TimestampNanos::now())?;
+        Ok(())
+    }
+}
+
+impl Sink for QuestDbSink {
+    //
+    // add one row to the batch; a row that fails half way through is rolled
+    // back so it cannot corrupt the rows around it
+    //
+    fn write(&mut self, observation: &Observation) -> Result<()> {
+        self.buffer.set_marker()?;
+        if let Err(e) = Self::append_row(&mut self.buffer, observation) {
+            self.buffer.rewind_to_marker()?;
+            return Err(e);
+        }
+        self.buffer.clear_marker();
+
+        if self.buffer.row_count() >= self.batch_rows {
+            self.flush();
+        } else {
+            self.tick();
+        }
+        Ok(())
+    }
+
+    //
+    // flush a partial batch once it has waited long enough
+    //
+    fn tick(&mut self) {
+        if self.flushed_at.elapsed() >= self.batch_interval {
+            self.flush();
+        }
+    }
+
+    fn shutdown(&mut self) {
+        println!("flushing {} buffered rows", self.buffer.row_count());
+        self.flush();
+    }
+}
@@ -0,0 +1,78 @@
+//!
+//! The half of a logger's appender that does not depend on its message
+//! format: the sink the rows go to, the dead-letter file, the raw recording
+//! and the units
+//!
+
+use std::fmt::Display;
+
+use crate::dead_letter::DeadLetter;
+use crate::observation::Observation;
+use crate::recorder::Recorder;
+use crate::sink::Sink;
+use crate::units::Units;
+use crate::Result;
+
+pub struct Intake {
+    source: &'static str,
+    sink: Box<dyn Sink + Send>,
+    dead_letter: DeadLetter,
+    recorder: Option<Recorder>,
+    units: Units,
+}
+
+impl Intake {
+    pub fn new(source: &'static str, sink: Box<dyn Sink + Send>, dead_letter: DeadLetter) -> Self {
+        Self {
+            source,
+            sink,
+            dead_letter,
+            recorder: None,
+            units: Units::default(),
+        }
+    }
+
+    //
+    // keep every raw payload, as received, before it is parsed
+    //
+    pub fn set_recorder(&mut self, recorder: Recorder) {
+        self.recorder = Some(recorder);
+    }
+
+    //
+    // the units readings are converted to before they are written
+    //
+    pub fn set_units(&mut self, units: Units) {
+        self.units = units;
+    }
+
+    pub fn units(&self) -> Units {
+        self.units
+    }
+
+    pub fn record(&self, payload: &str) {
+        if let Some(recorder) = &self.recorder {
+            recorder.record(self.source, payload);
+        }
+    }
+
+    pub fn reject(&mut self, payload: &str, reason: &dyn Display) {
+        self.dead_letter.record(payload, reason);
+    }
+
+    pub fn rejected(&self) -> u64 {
+        self.dead_letter.count()
+    }
+
+    pub fn write(&mut self, observation: &Observation) -> Result<()> {
+        self.sink.write(observation)
+    }
+
+    pub fn tick(&mut self) {
+        self.sink.tick();
+    }
+
+    pub fn shutdown(&mut self) {
+        self.sink.shutdown();
+    }
+}
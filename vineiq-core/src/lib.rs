@@ -0,0 +1,28 @@
+//!
+//! Shared building blocks for the VineIQ loggers: the normalized
+//! observation, the sink it is written to and the common configuration
+//!
+
+pub mod config;
+pub mod dead_letter;
+pub mod derived;
+pub mod error;
+pub mod ilp;
+pub mod intake;
+pub mod jsonl;
+pub mod line;
+pub mod observation;
+pub mod recorder;
+pub mod shutdown;
+pub mod sink;
+pub mod spool;
+#[cfg(feature = "test-util")]
+pub mod testing;
+pub mod units;
+pub mod writer;
+
+pub use dead_letter::DeadLetter;
+pub use error::{Error, Result};
+pub use observation::{Field, Observation};
+pub use recorder::Recorder;
+pub use sink::{SharedSink, Sink};
@@ -0,0 +1,161 @@
+//!
+//! InfluxDB line protocol read back into rows
+//!
+//! The spool keeps rows as the ILP text that would have gone over the wire;
+//! replaying them means parsing that text again.
+//!
+
+use std::iter::Peekable;
+use std::str::Chars;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Float(f64),
+    Integer(i64),
+    Boolean(bool),
+    String(String),
+    //
+    // a `t` suffixed column, in microseconds
+    //
+    Timestamp(i64),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Line {
+    pub table: String,
+    pub symbols: Vec<(String, String)>,
+    pub columns: Vec<(String, Value)>,
+    //
+    // the designated timestamp, in nanoseconds
+    //
+    pub timestamp: Option<i64>,
+}
+
+impl Line {
+    pub fn symbol(&self, name: &str) -> Option<&str> {
+        self.symbols
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| v.as_str())
+    }
+
+    pub fn column(&self, name: &str) -> Option<&Value> {
+        self.columns.iter().find(|(n, _)| n == name).map(|(_, v)| v)
+    }
+
+    pub fn column_names(&self) -> Vec<&str> {
+        self.columns.iter().map(|(n, _)| n.as_str()).collect()
+    }
+}
+
+/*
+  table,symbol=value,... column=value,... timestamp
+
+  names and symbol values escape ' ', ',' and '=' with a backslash; string
+  columns are double quoted and escape '"' and '\'
+*/
+pub fn parse_line(text: &str) -> Result<Line, String> {
+    let mut chars = text.chars().peekable();
+
+    let table = read_token(&mut chars, &[',', ' ']);
+    if table.is_empty() {
+        return Err("missing table name".to_string());
+    }
+    let mut symbols = Vec::new();
+    while chars.next_if_eq(&',').is_some() {
+        let name = read_token(&mut chars, &['=']);
+        expect(&mut chars, '=')?;
+        let value = read_token(&mut chars, &[',', ' ']);
+        symbols.push((name, value));
+    }
+    expect(&mut chars, ' ')?;
+
+    let mut columns = Vec::new();
+    loop {
+        let name = read_token(&mut chars, &['=']);
+        expect(&mut chars, '=')?;
+        let value = if chars.next_if_eq(&'"').is_some() {
+            Value::String(read_quoted(&mut chars)?)
+        } else {
+            parse_value(&read_token(&mut chars, &[',', ' ']))?
+        };
+        columns.push((name, value));
+        if chars.next_if_eq(&',').is_none() {
+            break;
+        }
+    }
+
+    let timestamp = match chars.next_if_eq(&' ') {
+        Some(_) => {
+            let rest: String = chars.collect();
+            Some(rest.trim().parse::<i64>().map_err(|e| e.to_string())?)
+        }
+        None if chars.peek().is_none() => None,
+        None => return Err(format!("unexpected {:?}", chars.collect::<String>())),
+    };
+
+    Ok(Line {
+        table,
+        symbols,
+        columns,
+        timestamp,
+    })
+}
+
+fn read_token(chars: &mut Peekable<Chars>, stop: &[char]) -> String {
+    let mut token = String::new();
+    while let Some(&c) = chars.peek() {
+        if stop.contains(&c) {
+            break;
+        }
+        chars.next();
+        if c == '\\' {
+            if let Some(escaped) = chars.next() {
+                token.push(escaped);
+            }
+        } else {
+            token.push(c);
+        }
+    }
+    token
+}
+
+fn read_quoted(chars: &mut Peekable<Chars>) -> Result<String, String> {
+    let mut value = String::new();
+    loop {
+        match chars.next() {
+            Some('"') => return Ok(value),
+            Some('\\') => value.push(chars.next().ok_or("unterminated escape")?),
+            Some(c) => value.push(c),
+            None => return Err("unterminated string".to_string()),
+        }
+    }
+}
+
+fn expect(chars: &mut Peekable<Chars>, expected: char) -> Result<(), String> {
+    match chars.next() {
+        Some(c) if c == expected => Ok(()),
+        Some(c) => Err(format!("expected {:?}, found {:?}", expected, c)),
+        None => Err(format!(
+            "expected {:?}, found the end of the line",
+            expected
+        )),
+    }
+}
+
+fn parse_value(token: &str) -> Result<Value, String> {
+    let error = |e: &dyn std::fmt::Display| format!("{:?}: {}", token, e);
+    match token {
+        "t" | "T" | "true" | "True" | "TRUE" => Ok(Value::Boolean(true)),
+        "f" | "F" | "false" | "False" | "FALSE" => Ok(Value::Boolean(false)),
+        _ => {
+            if let Some(integer) = token.strip_suffix('i') {
+                integer.parse().map(Value::Integer).map_err(|e| error(&e))
+            } else if let Some(micros) = token.strip_suffix('t') {
+                micros.parse().map(Value::Timestamp).map_err(|e| error(&e))
+            } else {
+                token.parse().map(Value::Float).map_err(|e| error(&e))
+            }
+        }
+    }
+}
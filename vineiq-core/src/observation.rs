@@ -0,0 +1,91 @@
+//!
+//! A single normalized reading, independent of the vendor it came from
+//!
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Field {
+    F64(f64),
+    I64(i64),
+    Bool(bool),
+    Str(String),
+}
+
+//
+// one row of a table: the symbols identify the device, the columns carry the
+// measurements and `time` is when the device took the reading (microseconds)
+//
+#[derive(Clone, Debug, PartialEq)]
+pub struct Observation {
+    pub table: String,
+    pub symbols: Vec<(String, String)>,
+    pub columns: Vec<(String, Field)>,
+    pub time: i64,
+}
+
+impl Observation {
+    pub fn new(table: &str, time: i64) -> Self {
+        Self {
+            table: table.to_string(),
+            symbols: Vec::new(),
+            columns: Vec::new(),
+            time,
+        }
+    }
+
+    pub fn symbol(&mut self, name: &str, value: &str) -> &mut Self {
+        self.symbols.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    pub fn column_f64(&mut self, name: &str, value: f64) -> &mut Self {
+        self.columns.push((name.to_string(), Field::F64(value)));
+        self
+    }
+
+    //
+    // a reading the device left out (null) is left out of the row as well
+    //
+    pub fn column_opt_f64(&mut self, name: &str, value: Option<f64>) -> &mut Self {
+        if let Some(value) = value {
+            self.column_f64(name, value);
+        }
+        self
+    }
+
+    pub fn column_i64(&mut self, name: &str, value: i64) -> &mut Self {
+        self.columns.push((name.to_string(), Field::I64(value)));
+        self
+    }
+
+    pub fn column_opt_i64(&mut self, name: &str, value: Option<i64>) -> &mut Self {
+        if let Some(value) = value {
+            self.column_i64(name, value);
+        }
+        self
+    }
+
+    pub fn column_bool(&mut self, name: &str, value: bool) -> &mut Self {
+        self.columns.push((name.to_string(), Field::Bool(value)));
+        self
+    }
+
+    pub fn column_str(&mut self, name: &str, value: &str) -> &mut Self {
+        self.columns
+            .push((name.to_string(), Field::Str(value.to_string())));
+        self
+    }
+
+    pub fn get_symbol(&self, name: &str) -> Option<&str> {
+        self.symbols
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| v.as_str())
+    }
+
+    pub fn get_f64(&self, name: &str) -> Option<f64> {
+        self.columns.iter().find_map(|(n, v)| match v {
+            Field::F64(v) if n == name => Some(*v),
+            _ => None,
+        })
+    }
+}
@@ -0,0 +1,57 @@
+//!
+//! Capture of every raw payload as it was received, for replay
+//!
+
+use serde_derive::{Deserialize, Serialize};
+use std::fs::{self, File, OpenOptions};
+use std::io;
+use std::path::Path;
+use std::sync::{Arc, Mutex, PoisonError};
+
+use crate::jsonl;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Capture {
+    //
+    // receive time, milliseconds since the epoch
+    //
+    pub time: i64,
+    pub source: String,
+    pub payload: String,
+}
+
+//
+// cheap to clone, so every source in the process can write to the same file
+//
+#[derive(Clone)]
+pub struct Recorder {
+    file: Arc<Mutex<File>>,
+}
+
+impl Recorder {
+    pub fn open(path: &str) -> io::Result<Self> {
+        if let Some(parent) = Path::new(path).parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Self {
+            file: Arc::new(Mutex::new(file)),
+        })
+    }
+
+    pub fn record(&self, source: &str, payload: &str) {
+        let capture = Capture {
+            time: jsonl::epoch_millis(),
+            source: source.to_string(),
+            payload: payload.to_string(),
+        };
+        let mut file = self.file.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Err(e) = jsonl::write_line(&mut *file, &capture) {
+            println!("Error recording {} message: {}", source, e);
+        }
+    }
+}
+
+pub fn read(path: &Path) -> io::Result<Vec<Capture>> {
+    jsonl::read(path)
+}
@@ -0,0 +1,50 @@
+//!
+//! Graceful shutdown on SIGTERM or ctrl-c
+//!
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::signal::unix::{signal, SignalKind};
+
+//
+// a flag the sources poll between reads; it is set once a signal arrives
+//
+pub fn flag() -> Arc<AtomicBool> {
+    let shutdown = Arc::new(AtomicBool::new(false));
+    let flag = shutdown.clone();
+    tokio::spawn(async move {
+        shutdown_signal().await;
+        println!("shutting down");
+        flag.store(true, Ordering::Relaxed);
+    });
+    shutdown
+}
+
+const TICK_INTERVAL: Duration = Duration::from_secs(1);
+
+//
+// sleep while still calling `tick`, so partial batches keep flushing;
+// false when shutting down
+//
+pub async fn pause(duration: Duration, shutdown: &AtomicBool, mut tick: impl FnMut()) -> bool {
+    let mut remaining = duration;
+    while !remaining.is_zero() {
+        if shutdown.load(Ordering::Relaxed) {
+            return false;
+        }
+        tick();
+        let step = remaining.min(TICK_INTERVAL);
+        tokio::time::sleep(step).await;
+        remaining -= step;
+    }
+    true
+}
+
+pub async fn shutdown_signal() {
+    let mut terminate = signal(SignalKind::terminate()).expect("Error installing signal handler");
+    tokio::select! {
+        _ = tokio::signal::ctrl_c() => {}
+        _ = terminate.recv() => {}
+    }
+}
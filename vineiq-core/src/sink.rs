@@ -0,0 +1,55 @@
+//!
+//! Destination for observations
+//!
+
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+use crate::observation::Observation;
+use crate::Result;
+
+//
+// a sink may buffer what it is given; `tick` is called regularly so it can
+// flush on a timer and `shutdown` once before the process exits
+//
+pub trait Sink {
+    fn write(&mut self, observation: &Observation) -> Result<()>;
+
+    fn tick(&mut self) {}
+
+    fn shutdown(&mut self) {}
+}
+
+//
+// one sink written to by several sources; a source that panics while holding
+// the lock must not take the writer down with it, so a poisoned lock is reused
+//
+#[derive(Clone)]
+pub struct SharedSink {
+    inner: Arc<Mutex<Box<dyn Sink + Send>>>,
+}
+
+impl SharedSink {
+    pub fn new(sink: Box<dyn Sink + Send>) -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(sink)),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Box<dyn Sink + Send>> {
+        self.inner.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl Sink for SharedSink {
+    fn write(&mut self, observation: &Observation) -> Result<()> {
+        self.lock().write(observation)
+    }
+
+    fn tick(&mut self) {
+        self.lock().tick();
+    }
+
+    fn shutdown(&mut self) {
+        self.lock().shutdown();
+    }
+}
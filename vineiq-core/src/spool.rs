@@ -0,0 +1,117 @@
+//!
+//! Append-only spool for rows that could not be flushed to QuestDB
+//!
+//! Rows are replayed oldest first in chunks and only cut from the spool once
+//! the caller has seen them through. A connection that fails half way loses
+//! nothing, and the chunks sent before it failed are still cut; only the
+//! chunk in flight at the failure can reach the database twice.
+//!
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Seek, SeekFrom, Write};
+use std::path::PathBuf;
+
+use crate::Result;
+
+pub const DEFAULT_MAX_BYTES: u64 = 64 * 1024 * 1024;
+
+pub struct Spool {
+    path: PathBuf,
+    max_bytes: u64,
+}
+
+impl Spool {
+    pub fn new(path: &str, max_bytes: u64) -> Self {
+        Self {
+            path: PathBuf::from(path),
+            max_bytes,
+        }
+    }
+
+    pub fn len(&self) -> u64 {
+        fs::metadata(&self.path).map(|m| m.len()).unwrap_or(0)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    //
+    // rows are kept as ILP text, exactly as they would have gone over the
+    // wire; once the spool is full the rows are refused with an error
+    //
+    pub fn append(&mut self, lines: &str) -> io::Result<()> {
+        if self.len() + lines.len() as u64 > self.max_bytes {
+            return Err(io::Error::new(
+                io::ErrorKind::StorageFull,
+                format!(
+                    "spool {} is full ({} bytes)",
+                    self.path.display(),
+                    self.max_bytes
+                ),
+            ));
+        }
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        file.write_all(lines.as_bytes())
+    }
+
+    //
+    // hand the spooled rows to `send`, oldest first, in chunks of whole lines
+    // of about `chunk_bytes`, stopping at the first error. Returns the bytes
+    // sent, also when it stopped early, with how it ended; they stay in the
+    // spool until `consume` is called.
+    //
+    pub fn replay<F>(&self, chunk_bytes: usize, mut send: F) -> (u64, Result<()>)
+    where
+        F: FnMut(&str) -> Result<()>,
+    {
+        let file = match File::open(&self.path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return (0, Ok(())),
+            Err(e) => return (0, Err(e.into())),
+        };
+        let mut reader = BufReader::new(file);
+        let mut sent = 0;
+        let mut chunk = String::new();
+        loop {
+            let read = match reader.read_line(&mut chunk) {
+                Ok(read) => read,
+                Err(e) => return (sent, Err(e.into())),
+            };
+            if chunk.len() >= chunk_bytes || (read == 0 && !chunk.is_empty()) {
+                if let Err(e) = send(&chunk) {
+                    return (sent, Err(e));
+                }
+                sent += chunk.len() as u64;
+                chunk.clear();
+            }
+            if read == 0 {
+                return (sent, Ok(()));
+            }
+        }
+    }
+
+    //
+    // drop the first `bytes` of the spool, once they have been replayed
+    //
+    pub fn consume(&mut self, bytes: u64) -> io::Result<()> {
+        if bytes == 0 {
+            return Ok(());
+        }
+        if bytes >= self.len() {
+            File::create(&self.path)?;
+            return Ok(());
+        }
+        let mut file = File::open(&self.path)?;
+        file.seek(SeekFrom::Start(bytes))?;
+        let rest = self.path.with_extension("rest");
+        io::copy(&mut file, &mut File::create(&rest)?)?;
+        fs::rename(&rest, &self.path)
+    }
+}
@@ -0,0 +1,114 @@
+//!
+//! In-process stand-ins for the database, for tests
+//!
+//! `IlpReceiver` accepts TCP connections on a free local port, parses every
+//! line of InfluxDB line protocol it receives and keeps the rows so a test
+//! can assert exactly what a logger wrote. `CaptureSink` keeps the
+//! observations themselves, before they are turned into ILP.
+//!
+
+use std::io::{BufRead, BufReader};
+use std::net::{SocketAddr, TcpListener};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::config::{questdb_sink, BatchConfig, SpoolConfig};
+use crate::ilp::QuestDbSink;
+use crate::observation::Observation;
+use crate::sink::Sink;
+use crate::Result;
+
+pub use crate::line::{parse_line, Line, Value};
+
+pub struct IlpReceiver {
+    addr: SocketAddr,
+    lines: Arc<Mutex<Vec<Line>>>,
+}
+
+impl IlpReceiver {
+    pub fn start() -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("Error binding ILP receiver");
+        let addr = listener
+            .local_addr()
+            .expect("Error reading ILP receiver address");
+        let lines = Arc::new(Mutex::new(Vec::new()));
+
+        let received = lines.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let received = received.clone();
+                thread::spawn(move || {
+                    for text in BufReader::new(stream).lines().map_while(|l| l.ok()) {
+                        let line = parse_line(&text)
+                            .unwrap_or_else(|e| panic!("invalid ILP line {:?}: {}", text, e));
+                        received.lock().unwrap().push(line);
+                    }
+                });
+            }
+        });
+
+        Self { addr, lines }
+    }
+
+    pub fn addr(&self) -> String {
+        self.addr.to_string()
+    }
+
+    //
+    // a sink flushing every row straight to this receiver, spooling under the
+    // temp directory should the connection fail
+    //
+    pub fn sink(&self, name: &str) -> QuestDbSink {
+        let spool = SpoolConfig {
+            path: Some(
+                std::env::temp_dir()
+                    .join(format!("vineiq-{}-{}.ilp", std::process::id(), name))
+                    .to_string_lossy()
+                    .to_string(),
+            ),
+            ..SpoolConfig::default()
+        };
+        let batch = BatchConfig {
+            rows: 1,
+            interval_secs: 0,
+        };
+        questdb_sink(&self.addr(), name, &spool, &batch)
+    }
+
+    //
+    // the rows received so far, once there are at least `rows` of them or the
+    // timeout has passed
+    //
+    pub fn wait_for(&self, rows: usize, timeout: Duration) -> Vec<Line> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            let lines = self.lines.lock().unwrap().clone();
+            if lines.len() >= rows || Instant::now() >= deadline {
+                return lines;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+}
+
+//
+// a sink keeping every observation it is given; clones share the rows
+//
+#[derive(Clone, Default)]
+pub struct CaptureSink {
+    rows: Arc<Mutex<Vec<Observation>>>,
+}
+
+impl CaptureSink {
+    pub fn rows(&self) -> Vec<Observation> {
+        self.rows.lock().unwrap().clone()
+    }
+}
+
+impl Sink for CaptureSink {
+    fn write(&mut self, observation: &Observation) -> Result<()> {
+        self.rows.lock().unwrap().push(observation.clone());
+        Ok(())
+    }
+}
@@ -0,0 +1,248 @@
+//!
+//! Unit conversions and the unit system the tables are written in
+//!
+//! Readings arrive in metric (celsius, m/s, mb, mm, km) and are converted on
+//! the way to the database:
+//!
+//!   units:
+//!     system: metric        # or imperial
+//!     wind: km/h            # optional per-quantity overrides
+//!
+//! Without a `units` setting temperature is written in fahrenheit and the
+//! rest stays metric, as the loggers always have.
+//!
+
+use serde_derive::{Deserialize, Serialize};
+
+pub fn to_fahrenheit(celsius: f64) -> f64 {
+    (celsius * 1.8) + 32.0
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum System {
+    Metric,
+    Imperial,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum Temperature {
+    Celsius,
+    Fahrenheit,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+pub enum Speed {
+    #[serde(rename = "m/s")]
+    MetersPerSecond,
+    #[serde(rename = "km/h")]
+    KilometersPerHour,
+    #[serde(rename = "mph")]
+    MilesPerHour,
+    #[serde(rename = "knots")]
+    Knots,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+pub enum Pressure {
+    #[serde(rename = "mb")]
+    Millibars,
+    #[serde(rename = "kPa")]
+    Kilopascals,
+    #[serde(rename = "inHg")]
+    InchesOfMercury,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+pub enum Rain {
+    #[serde(rename = "mm")]
+    Millimeters,
+    #[serde(rename = "in")]
+    Inches,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+pub enum Distance {
+    #[serde(rename = "km")]
+    Kilometers,
+    #[serde(rename = "mi")]
+    Miles,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Clone, Debug, Default)]
+#[serde(default)]
+pub struct UnitsConfig {
+    pub system: Option<System>,
+    pub temperature: Option<Temperature>,
+    pub wind: Option<Speed>,
+    pub pressure: Option<Pressure>,
+    pub rain: Option<Rain>,
+    pub distance: Option<Distance>,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Units {
+    pub temperature: Temperature,
+    pub wind: Speed,
+    pub pressure: Pressure,
+    pub rain: Rain,
+    pub distance: Distance,
+}
+
+impl Units {
+    pub fn metric() -> Self {
+        Self {
+            temperature: Temperature::Celsius,
+            wind: Speed::MetersPerSecond,
+            pressure: Pressure::Millibars,
+            rain: Rain::Millimeters,
+            distance: Distance::Kilometers,
+        }
+    }
+
+    pub fn imperial() -> Self {
+        Self {
+            temperature: Temperature::Fahrenheit,
+            wind: Speed::MilesPerHour,
+            pressure: Pressure::InchesOfMercury,
+            rain: Rain::Inches,
+            distance: Distance::Miles,
+        }
+    }
+
+    pub fn temperature(&self, celsius: f64) -> f64 {
+        match self.temperature {
+            Temperature::Celsius => celsius,
+            Temperature::Fahrenheit => to_fahrenheit(celsius),
+        }
+    }
+
+    //
+    // a temperature difference, such as a degree day, rather than a reading
+    //
+    pub fn degrees(&self, celsius: f64) -> f64 {
+        match self.temperature {
+            Temperature::Celsius => celsius,
+            Temperature::Fahrenheit => celsius * 1.8,
+        }
+    }
+
+    pub fn wind(&self, meters_per_second: f64) -> f64 {
+        match self.wind {
+            Speed::MetersPerSecond => meters_per_second,
+            Speed::KilometersPerHour => meters_per_second * 3.6,
+            Speed::MilesPerHour => meters_per_second * 2.236936,
+            Speed::Knots => meters_per_second * 1.943844,
+        }
+    }
+
+    pub fn pressure(&self, millibars: f64) -> f64 {
+        match self.pressure {
+            Pressure::Millibars => millibars,
+            Pressure::Kilopascals => millibars / 10.0,
+            Pressure::InchesOfMercury => millibars * 0.02953,
+        }
+    }
+
+    pub fn rain(&self, millimeters: f64) -> f64 {
+        match self.rain {
+            Rain::Millimeters => millimeters,
+            Rain::Inches => millimeters / 25.4,
+        }
+    }
+
+    pub fn distance(&self, kilometers: f64) -> f64 {
+        match self.distance {
+            Distance::Kilometers => kilometers,
+            Distance::Miles => kilometers * 0.621371,
+        }
+    }
+
+    //
+    // written with every row: "metric", "imperial", or the unit of each
+    // quantity when they are mixed, e.g. "F,m/s,mb,mm,km"
+    //
+    pub fn label(&self) -> String {
+        if *self == Self::metric() {
+            return "metric".to_string();
+        }
+        if *self == Self::imperial() {
+            return "imperial".to_string();
+        }
+        let temperature = match self.temperature {
+            Temperature::Celsius => "C",
+            Temperature::Fahrenheit => "F",
+        };
+        let wind = match self.wind {
+            Speed::MetersPerSecond => "m/s",
+            Speed::KilometersPerHour => "km/h",
+            Speed::MilesPerHour => "mph",
+            Speed::Knots => "knots",
+        };
+        let pressure = match self.pressure {
+            Pressure::Millibars => "mb",
+            Pressure::Kilopascals => "kPa",
+            Pressure::InchesOfMercury => "inHg",
+        };
+        let rain = match self.rain {
+            Rain::Millimeters => "mm",
+            Rain::Inches => "in",
+        };
+        let distance = match self.distance {
+            Distance::Kilometers => "km",
+            Distance::Miles => "mi",
+        };
+        format!(
+            "{},{},{},{},{}",
+            temperature, wind, pressure, rain, distance
+        )
+    }
+}
+
+impl Temperature {
+    //
+    // the temperature unit of a stored row, from its `units` symbol; rows
+    // written before the symbol existed are in fahrenheit
+    //
+    pub fn from_label(label: Option<&str>) -> Self {
+        match label {
+            Some("metric") => Temperature::Celsius,
+            Some(label) if label.starts_with("C,") => Temperature::Celsius,
+            _ => Temperature::Fahrenheit,
+        }
+    }
+
+    pub fn to_celsius(&self, value: f64) -> f64 {
+        match self {
+            Temperature::Celsius => value,
+            Temperature::Fahrenheit => (value - 32.0) / 1.8,
+        }
+    }
+}
+
+impl Default for Units {
+    fn default() -> Self {
+        Self {
+            temperature: Temperature::Fahrenheit,
+            ..Self::metric()
+        }
+    }
+}
+
+impl From<&UnitsConfig> for Units {
+    fn from(config: &UnitsConfig) -> Self {
+        let base = match config.system {
+            Some(System::Metric) => Self::metric(),
+            Some(System::Imperial) => Self::imperial(),
+            None => Self::default(),
+        };
+        Self {
+            temperature: config.temperature.unwrap_or(base.temperature),
+            wind: config.wind.unwrap_or(base.wind),
+            pressure: config.pressure.unwrap_or(base.pressure),
+            rain: config.rain.unwrap_or(base.rain),
+            distance: config.distance.unwrap_or(base.distance),
+        }
+    }
+}
@@ -0,0 +1,103 @@
+//!
+//! A sink run on a thread of its own
+//!
+//! Sources hand rows over a bounded queue and never wait on the sink's I/O,
+//! so a database that hangs cannot stall the async runtime or hold the
+//! `SharedSink` lock. The writer thread ticks the sink on its own while the
+//! queue is idle.
+//!
+
+use std::io;
+use std::sync::mpsc::{self, RecvTimeoutError, SyncSender, TrySendError};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use crate::observation::Observation;
+use crate::sink::Sink;
+use crate::Result;
+
+pub const DEFAULT_QUEUE_ROWS: usize = 100_000;
+const IDLE_TICK: Duration = Duration::from_secs(1);
+
+enum Message {
+    Write(Box<Observation>),
+    Tick,
+    Shutdown,
+}
+
+pub struct WriterSink {
+    queue: SyncSender<Message>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl WriterSink {
+    pub fn spawn<S: Sink + Send + 'static>(name: &str, sink: S, queue_rows: usize) -> Self {
+        let (queue, messages) = mpsc::sync_channel(queue_rows);
+        let thread = thread::Builder::new()
+            .name(format!("{}-writer", name))
+            .spawn(move || {
+                let mut sink = sink;
+                loop {
+                    match messages.recv_timeout(IDLE_TICK) {
+                        Ok(Message::Write(observation)) => {
+                            if let Err(e) = sink.write(&observation) {
+                                println!("Error writing {}: {}", observation.table, e);
+                            }
+                        }
+                        Ok(Message::Tick) | Err(RecvTimeoutError::Timeout) => sink.tick(),
+                        Ok(Message::Shutdown) | Err(RecvTimeoutError::Disconnected) => break,
+                    }
+                }
+                sink.shutdown();
+            })
+            .expect("failed to start the writer thread");
+        Self {
+            queue,
+            thread: Some(thread),
+        }
+    }
+}
+
+impl Sink for WriterSink {
+    //
+    // queue the row for the writer thread; a full queue refuses it rather
+    // than blocking the source
+    //
+    fn write(&mut self, observation: &Observation) -> Result<()> {
+        match self
+            .queue
+            .try_send(Message::Write(Box::new(observation.clone())))
+        {
+            Ok(()) => Ok(()),
+            Err(TrySendError::Full(_)) => {
+                Err(io::Error::new(io::ErrorKind::WouldBlock, "writer queue is full").into())
+            }
+            Err(TrySendError::Disconnected(_)) => {
+                Err(io::Error::new(io::ErrorKind::BrokenPipe, "writer has stopped").into())
+            }
+        }
+    }
+
+    fn tick(&mut self) {
+        let _ = self.queue.try_send(Message::Tick);
+    }
+
+    //
+    // wait for the writer to flush what is queued
+    //
+    fn shutdown(&mut self) {
+        let Some(thread) = self.thread.take() else {
+            return;
+        };
+        let _ = self.queue.send(Message::Shutdown);
+        if thread.join().is_err() {
+            println!("Error: the writer thread panicked");
+        }
+    }
+}
+
+impl Drop for WriterSink {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
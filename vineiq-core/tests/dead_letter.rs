@@ -0,0 +1,35 @@
+use std::fs;
+use std::path::PathBuf;
+
+use vineiq_core::dead_letter;
+use vineiq_core::DeadLetter;
+
+fn temp_path(name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("vineiq-{}-{}", std::process::id(), name));
+    let _ = fs::remove_file(&path);
+    path
+}
+
+#[test]
+fn records_round_trip() {
+    let path = temp_path("dead.jsonl");
+    let mut dead_letter = DeadLetter::new("tempest", Some(path.to_str().unwrap()));
+    dead_letter.record(r#"{"type":"obs_st","obs":[[null]]}"#, &"missing timestamp");
+    dead_letter.record("not json\nat all", &"expected value");
+    assert_eq!(dead_letter.count(), 2);
+
+    let records = dead_letter::read(&path).unwrap();
+    assert_eq!(records.len(), 2);
+    assert_eq!(records[0].source, "tempest");
+    assert_eq!(records[0].reason, "missing timestamp");
+    assert_eq!(records[0].payload, r#"{"type":"obs_st","obs":[[null]]}"#);
+    assert_eq!(records[1].payload, "not json\nat all");
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn without_a_path_only_counts() {
+    let mut dead_letter = DeadLetter::new("yolink", None);
+    dead_letter.record("{}", &"missing field `event`");
+    assert_eq!(dead_letter.count(), 1);
+}
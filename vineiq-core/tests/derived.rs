@@ -0,0 +1,82 @@
+use vineiq_core::derived;
+
+fn close(actual: f64, expected: f64, tolerance: f64) {
+    assert!(
+        (actual - expected).abs() <= tolerance,
+        "{} is not within {} of {}",
+        actual,
+        tolerance,
+        expected
+    );
+}
+
+#[test]
+fn vpd() {
+    //
+    // the saturation vapor pressure is 3.17 kPa at 25C and 2.34 kPa at 20C
+    // (FAO-56, table 2.3)
+    //
+    close(derived::vpd(25.0, 50.0), 1.58, 0.01);
+    close(derived::vpd(20.0, 80.0), 0.47, 0.01);
+    close(derived::vpd(30.0, 40.0), 2.55, 0.01);
+    //
+    // saturated air has no deficit
+    //
+    close(derived::vpd(18.0, 100.0), 0.0, 1e-9);
+}
+
+#[test]
+fn dew_point() {
+    close(derived::dew_point(20.0, 50.0).unwrap(), 9.26, 0.05);
+    close(derived::dew_point(30.0, 80.0).unwrap(), 26.17, 0.05);
+    //
+    // saturated air is at its dew point
+    //
+    close(derived::dew_point(12.5, 100.0).unwrap(), 12.5, 1e-9);
+    assert_eq!(derived::dew_point(20.0, 0.0), None);
+}
+
+#[test]
+fn heat_index_matches_the_nws_table() {
+    //
+    // 90F at 70% reads 106F in the NWS heat index chart, 100F at 40% 109F
+    //
+    close(derived::heat_index(32.222, 70.0) * 1.8 + 32.0, 106.0, 1.0);
+    close(derived::heat_index(37.778, 40.0) * 1.8 + 32.0, 109.0, 1.0);
+    //
+    // below 80F the simple formula keeps it close to the air temperature
+    //
+    close(derived::heat_index(20.0, 50.0), 19.4, 0.5);
+}
+
+#[test]
+fn wind_chill() {
+    //
+    // -10C with 30 km/h of wind feels like -20C in the Environment Canada table
+    //
+    close(derived::wind_chill(-10.0, 30.0 / 3.6).unwrap(), -19.5, 0.1);
+    assert_eq!(derived::wind_chill(15.0, 10.0), None);
+    assert_eq!(derived::wind_chill(0.0, 1.0), None);
+}
+
+#[test]
+fn feels_like_picks_the_regime() {
+    assert_eq!(
+        derived::feels_like(-10.0, 60.0, 30.0 / 3.6),
+        derived::wind_chill(-10.0, 30.0 / 3.6).unwrap()
+    );
+    assert_eq!(
+        derived::feels_like(32.0, 70.0, 3.0),
+        derived::heat_index(32.0, 70.0)
+    );
+    assert_eq!(derived::feels_like(18.0, 60.0, 3.0), 18.0);
+}
+
+#[test]
+fn wet_bulb_matches_stull() {
+    //
+    // the worked example in Stull (2011)
+    //
+    close(derived::wet_bulb(20.0, 50.0), 13.7, 0.05);
+    close(derived::wet_bulb(30.0, 90.0), 28.6, 0.3);
+}
@@ -0,0 +1,215 @@
+use std::sync::mpsc;
+use std::time::Duration;
+
+use vineiq_core::config::{questdb_sink, BatchConfig, SpoolConfig};
+use vineiq_core::ilp::{Batch, QuestDbSink};
+use vineiq_core::spool::Spool;
+use vineiq_core::testing::{parse_line, IlpReceiver, Value};
+use vineiq_core::writer::WriterSink;
+use vineiq_core::{Observation, Sink};
+
+#[test]
+fn parses_escapes_and_every_column_type() {
+    let line = parse_line(
+        r#"yolink,sensorName=north\ block\,\ row\=1,deviceId=d88b f=1.5,i=-67i,b=t,s="say \"hi\"",time=1712517507811000t 1712517508000000000"#,
+    )
+    .unwrap();
+    assert_eq!(line.table, "yolink");
+    assert_eq!(line.symbol("sensorName"), Some("north block, row=1"));
+    assert_eq!(line.symbol("deviceId"), Some("d88b"));
+    assert_eq!(line.column("f"), Some(&Value::Float(1.5)));
+    assert_eq!(line.column("i"), Some(&Value::Integer(-67)));
+    assert_eq!(line.column("b"), Some(&Value::Boolean(true)));
+    assert_eq!(
+        line.column("s"),
+        Some(&Value::String(r#"say "hi""#.to_string()))
+    );
+    assert_eq!(
+        line.column("time"),
+        Some(&Value::Timestamp(1712517507811000))
+    );
+    assert_eq!(line.timestamp, Some(1712517508000000000));
+}
+
+#[test]
+fn rejects_a_line_without_columns() {
+    assert!(parse_line("yolink,deviceId=d88b").is_err());
+}
+
+#[test]
+fn receives_what_the_sink_writes() {
+    let receiver = IlpReceiver::start();
+    let mut sink = receiver.sink("core-ilp");
+    let mut observation = Observation::new("vineiq_test", 1_000_000);
+    observation
+        .symbol("block", "B 2")
+        .column_f64("temperature", 68.9)
+        .column_str("note", "first \"pick\"");
+    sink.write(&observation).unwrap();
+
+    let lines = receiver.wait_for(1, Duration::from_secs(5));
+    assert_eq!(lines.len(), 1);
+    assert_eq!(lines[0].symbol("block"), Some("B 2"));
+    assert_eq!(lines[0].column("temperature"), Some(&Value::Float(68.9)));
+    assert_eq!(
+        lines[0].column("note"),
+        Some(&Value::String("first \"pick\"".to_string()))
+    );
+    assert_eq!(lines[0].column("time"), Some(&Value::Timestamp(1_000_000)));
+    assert!(lines[0].timestamp.is_some());
+}
+
+#[test]
+fn replays_the_spool_before_the_batch() {
+    let receiver = IlpReceiver::start();
+    let path = std::env::temp_dir().join(format!("vineiq-{}-replay-sink.ilp", std::process::id()));
+    let path = path.to_str().unwrap().to_string();
+    let _ = std::fs::remove_file(&path);
+    let mut spool = Spool::new(&path, 1024);
+    spool
+        .append(
+            "vineiq_test,n=1 v=1i,time=1000t 1000000\nvineiq_test,n=2 v=2i,time=2000t 2000000\n",
+        )
+        .unwrap();
+
+    let config = SpoolConfig {
+        path: Some(path.clone()),
+        ..SpoolConfig::default()
+    };
+    let batch = BatchConfig {
+        rows: 1,
+        interval_secs: 0,
+    };
+    let mut sink = questdb_sink(&receiver.addr(), "replay", &config, &batch);
+    let mut observation = Observation::new("vineiq_test", 3000);
+    observation.symbol("n", "3").column_i64("v", 3);
+    sink.write(&observation).unwrap();
+
+    let lines = receiver.wait_for(3, Duration::from_secs(5));
+    let order: Vec<Option<&str>> = lines.iter().map(|l| l.symbol("n")).collect();
+    assert_eq!(order, vec![Some("1"), Some("2"), Some("3")]);
+    assert_eq!(lines[0].column("time"), Some(&Value::Timestamp(1000)));
+    assert_eq!(lines[1].timestamp, Some(2000000));
+    assert!(spool.is_empty());
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn spools_while_questdb_is_unreachable() {
+    //
+    // a port nobody listens on
+    //
+    let addr = std::net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .to_string();
+    let path = std::env::temp_dir().join(format!("vineiq-{}-unreachable.ilp", std::process::id()));
+    let path = path.to_str().unwrap().to_string();
+    let _ = std::fs::remove_file(&path);
+    let config = SpoolConfig {
+        path: Some(path.clone()),
+        ..SpoolConfig::default()
+    };
+    let batch = BatchConfig {
+        rows: 1,
+        interval_secs: 0,
+    };
+    let mut sink = questdb_sink(&addr, "unreachable", &config, &batch);
+    let mut observation = Observation::new("vineiq_test", 1000);
+    observation.column_f64("v", 1.5);
+    sink.write(&observation).unwrap();
+
+    let spooled = std::fs::read_to_string(&path).unwrap();
+    assert!(spooled.starts_with("vineiq_test v=1.5,time=1000t "));
+    std::fs::remove_file(&path).unwrap();
+}
+
+fn batched_sink(
+    receiver: &IlpReceiver,
+    name: &str,
+    rows: usize,
+    interval: Duration,
+) -> QuestDbSink {
+    let path = std::env::temp_dir().join(format!("vineiq-{}-{}.ilp", std::process::id(), name));
+    let spool = Spool::new(path.to_str().unwrap(), 1024);
+    QuestDbSink::new(&receiver.addr(), spool, Batch { rows, interval })
+}
+
+fn row(n: i64) -> Observation {
+    let mut observation = Observation::new("vineiq_test", n);
+    observation.column_i64("n", n);
+    observation
+}
+
+#[test]
+fn a_full_batch_flushes_at_the_row_count() {
+    let receiver = IlpReceiver::start();
+    let mut sink = batched_sink(&receiver, "full-batch", 3, Duration::from_secs(3600));
+    for n in 0..3 {
+        sink.write(&row(n)).unwrap();
+    }
+    assert_eq!(receiver.wait_for(3, Duration::from_secs(5)).len(), 3);
+
+    //
+    // the rest wait for the next batch, or for shutdown
+    //
+    sink.write(&row(3)).unwrap();
+    sink.write(&row(4)).unwrap();
+    sink.tick();
+    assert_eq!(receiver.wait_for(4, Duration::from_millis(300)).len(), 3);
+    sink.shutdown();
+    assert_eq!(receiver.wait_for(5, Duration::from_secs(5)).len(), 5);
+}
+
+#[test]
+fn a_partial_batch_flushes_on_tick_after_the_interval() {
+    let receiver = IlpReceiver::start();
+    let mut sink = batched_sink(&receiver, "partial-batch", 100, Duration::from_millis(500));
+    sink.write(&row(0)).unwrap();
+    sink.write(&row(1)).unwrap();
+    sink.tick();
+    assert!(receiver.wait_for(1, Duration::from_millis(200)).is_empty());
+
+    std::thread::sleep(Duration::from_millis(500));
+    sink.tick();
+    let lines = receiver.wait_for(2, Duration::from_secs(5));
+    assert_eq!(lines.len(), 2);
+    assert_eq!(lines[1].column("n"), Some(&Value::Integer(1)));
+}
+
+#[test]
+fn the_writer_thread_flushes_on_shutdown() {
+    let receiver = IlpReceiver::start();
+    let sink = batched_sink(&receiver, "writer", 100, Duration::from_secs(3600));
+    let mut writer = WriterSink::spawn("writer", sink, 10);
+    for n in 0..3 {
+        writer.write(&row(n)).unwrap();
+    }
+    writer.shutdown();
+    assert_eq!(receiver.wait_for(3, Duration::from_secs(5)).len(), 3);
+    assert!(writer.write(&row(3)).is_err());
+}
+
+//
+// a sink stuck on its first write, as it would be on a hung connection
+//
+struct Stalled(mpsc::Receiver<()>);
+
+impl Sink for Stalled {
+    fn write(&mut self, _: &Observation) -> vineiq_core::Result<()> {
+        let _ = self.0.recv();
+        Ok(())
+    }
+}
+
+#[test]
+fn a_stalled_writer_refuses_rows_instead_of_blocking() {
+    let (release, stalled) = mpsc::channel();
+    let mut writer = WriterSink::spawn("stalled", Stalled(stalled), 1);
+    let written: Vec<bool> = (0..3).map(|n| writer.write(&row(n)).is_ok()).collect();
+    assert!(written[0]);
+    assert!(!written[2]);
+    drop(release);
+    writer.shutdown();
+}
@@ -0,0 +1,106 @@
+use std::fs;
+use std::io::ErrorKind;
+use std::path::PathBuf;
+
+use vineiq_core::spool::Spool;
+use vineiq_core::Error;
+
+const ROWS: [&str; 3] = [
+    "yolink,deviceId=d1 temperature=20.5 1000\n",
+    "yolink,deviceId=d2 temperature=21.5 2000\n",
+    "tempest_station,device_id=1110 temperature=19.0 3000\n",
+];
+
+fn temp_path(name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("vineiq-{}-{}", std::process::id(), name));
+    let _ = fs::remove_file(&path);
+    path
+}
+
+fn spool(name: &str, max_bytes: u64) -> (Spool, PathBuf) {
+    let path = temp_path(name);
+    let mut spool = Spool::new(path.to_str().unwrap(), max_bytes);
+    for row in ROWS {
+        spool.append(row).unwrap();
+    }
+    (spool, path)
+}
+
+fn replayed(spool: &Spool, chunk_bytes: usize) -> (u64, Vec<String>) {
+    let mut chunks = Vec::new();
+    let (sent, result) = spool.replay(chunk_bytes, |chunk| {
+        chunks.push(chunk.to_string());
+        Ok(())
+    });
+    result.unwrap();
+    (sent, chunks)
+}
+
+#[test]
+fn replays_oldest_first_in_chunks() {
+    let (mut spool, path) = spool("replay.ilp", 1024);
+    let total: usize = ROWS.iter().map(|r| r.len()).sum();
+
+    //
+    // a chunk holds whole lines, one at a time when they are longer than it
+    //
+    let (sent, chunks) = replayed(&spool, 1);
+    assert_eq!(sent, total as u64);
+    assert_eq!(chunks, ROWS);
+
+    let (_, chunks) = replayed(&spool, 1024);
+    assert_eq!(chunks, vec![ROWS.concat()]);
+
+    //
+    // nothing leaves the spool until it is consumed
+    //
+    assert_eq!(spool.len(), total as u64);
+    spool.consume(sent).unwrap();
+    assert!(spool.is_empty());
+    assert_eq!(replayed(&spool, 1024), (0, Vec::new()));
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn a_failed_replay_reports_what_it_sent() {
+    let (mut spool, path) = spool("failed.ilp", 1024);
+    let mut sent = Vec::new();
+    let (bytes, result) = spool.replay(1, |chunk| {
+        if sent.len() == 1 {
+            return Err(Error::Io(std::io::Error::other("connection reset")));
+        }
+        sent.push(chunk.to_string());
+        Ok(())
+    });
+    assert!(result.is_err());
+    assert_eq!(sent, vec![ROWS[0]]);
+    assert_eq!(bytes, ROWS[0].len() as u64);
+    assert_eq!(replayed(&spool, 1).1, ROWS);
+
+    //
+    // consuming what was sent leaves the rest in order
+    //
+    spool.consume(bytes).unwrap();
+    assert_eq!(replayed(&spool, 1).1, &ROWS[1..]);
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn refuses_rows_past_the_size_cap() {
+    let path = temp_path("full.ilp");
+    let mut spool = Spool::new(path.to_str().unwrap(), 100);
+    spool.append(ROWS[0]).unwrap();
+    spool.append(ROWS[1]).unwrap();
+    let error = spool.append(ROWS[2]).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::StorageFull);
+    assert_eq!(replayed(&spool, 1024).1, vec![ROWS[..2].concat()]);
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn a_missing_spool_is_empty() {
+    let path = temp_path("missing.ilp");
+    let spool = Spool::new(path.to_str().unwrap(), 100);
+    assert!(spool.is_empty());
+    assert_eq!(replayed(&spool, 1024), (0, Vec::new()));
+}
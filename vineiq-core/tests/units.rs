@@ -0,0 +1,51 @@
+use vineiq_core::units::{Distance, Pressure, Speed, Temperature, Units, UnitsConfig};
+
+fn resolve(yaml: &str) -> Units {
+    let config: UnitsConfig = serde_yaml::from_str(yaml).expect("units should parse");
+    Units::from(&config)
+}
+
+#[test]
+fn unset_keeps_fahrenheit_with_metric() {
+    let units = Units::from(&UnitsConfig::default());
+    assert_eq!(units.temperature, Temperature::Fahrenheit);
+    assert_eq!(units.wind, Speed::MetersPerSecond);
+    assert_eq!(units.label(), "F,m/s,mb,mm,km");
+}
+
+#[test]
+fn systems() {
+    assert_eq!(resolve("system: metric"), Units::metric());
+    assert_eq!(resolve("system: imperial"), Units::imperial());
+    assert_eq!(Units::metric().label(), "metric");
+    assert_eq!(Units::imperial().label(), "imperial");
+}
+
+#[test]
+fn overrides_apply_on_top_of_the_system() {
+    let units = resolve("{system: metric, wind: km/h, pressure: kPa}");
+    assert_eq!(units.temperature, Temperature::Celsius);
+    assert_eq!(units.wind, Speed::KilometersPerHour);
+    assert_eq!(units.pressure, Pressure::Kilopascals);
+    assert_eq!(units.distance, Distance::Kilometers);
+    assert_eq!(units.label(), "C,km/h,kPa,mm,km");
+}
+
+#[test]
+fn conversions() {
+    let imperial = Units::imperial();
+    assert_eq!(imperial.temperature(100.0), 212.0);
+    assert!((imperial.wind(10.0) - 22.36936).abs() < 1e-9);
+    assert!((imperial.pressure(1013.25) - 29.921).abs() < 1e-3);
+    assert!((imperial.rain(25.4) - 1.0).abs() < 1e-12);
+    assert!((imperial.distance(10.0) - 6.21371).abs() < 1e-9);
+
+    let metric = Units::metric();
+    assert_eq!(metric.temperature(21.5), 21.5);
+    assert_eq!(metric.wind(3.2), 3.2);
+}
+
+#[test]
+fn rejects_an_unknown_unit() {
+    assert!(serde_yaml::from_str::<UnitsConfig>("wind: furlongs").is_err());
+}
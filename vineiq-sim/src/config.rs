@@ -0,0 +1,136 @@
+//!
+//! Simulator settings; every section has defaults, so no file is needed
+//!
+
+use serde_derive::Deserialize;
+
+#[derive(Deserialize, Clone, Debug, Default)]
+#[serde(default)]
+pub struct Config {
+    pub tempest: TempestConfig,
+    pub yolink: YolinkConfig,
+    pub notify: NotifyConfig,
+}
+
+impl Config {
+    pub fn new(config_file: &str) -> Self {
+        let content = std::fs::read_to_string(config_file).unwrap();
+        serde_yaml::from_str(&content)
+            .unwrap_or_else(|e| panic!("invalid configuration {}: {}", config_file, e))
+    }
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct Range {
+    pub min: f64,
+    pub max: f64,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct TempestConfig {
+    pub listen: String,
+    pub interval_secs: u64,
+    //
+    // chance of a lightning strike event per observation
+    //
+    pub strike_probability: f64,
+    pub temperature: Range,
+    pub humidity: Range,
+}
+
+impl Default for TempestConfig {
+    fn default() -> Self {
+        Self {
+            listen: "127.0.0.1:8765".to_string(),
+            interval_secs: 60,
+            strike_probability: 0.05,
+            temperature: Range {
+                min: 9.0,
+                max: 29.0,
+            },
+            humidity: Range {
+                min: 30.0,
+                max: 85.0,
+            },
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct YolinkConfig {
+    pub http_listen: String,
+    pub mqtt_listen: String,
+    pub home_id: String,
+    pub expires_in: u64,
+    pub interval_secs: u64,
+    pub sensors: Vec<SensorConfig>,
+}
+
+impl Default for YolinkConfig {
+    fn default() -> Self {
+        Self {
+            http_listen: "127.0.0.1:8766".to_string(),
+            mqtt_listen: "127.0.0.1:1883".to_string(),
+            home_id: "sim-home".to_string(),
+            expires_in: 7200,
+            interval_secs: 60,
+            sensors: vec![
+                SensorConfig {
+                    device_id: "d88b4c0100000001".to_string(),
+                    name: "Block A".to_string(),
+                    temperature: Range {
+                        min: 7.0,
+                        max: 27.0,
+                    },
+                    humidity: Range {
+                        min: 35.0,
+                        max: 95.0,
+                    },
+                },
+                SensorConfig {
+                    device_id: "d88b4c0100000002".to_string(),
+                    name: "Block B".to_string(),
+                    temperature: Range {
+                        min: 5.0,
+                        max: 30.0,
+                    },
+                    humidity: Range {
+                        min: 30.0,
+                        max: 90.0,
+                    },
+                },
+            ],
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct SensorConfig {
+    pub device_id: String,
+    pub name: String,
+    pub temperature: Range,
+    pub humidity: Range,
+}
+
+//
+// receivers for the alert notifiers, which print what they are sent
+//
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct NotifyConfig {
+    pub webhook_listen: String,
+    pub mqtt_listen: String,
+    pub smtp_listen: String,
+}
+
+impl Default for NotifyConfig {
+    fn default() -> Self {
+        Self {
+            webhook_listen: "127.0.0.1:8767".to_string(),
+            mqtt_listen: "127.0.0.1:1884".to_string(),
+            smtp_listen: "127.0.0.1:2525".to_string(),
+        }
+    }
+}
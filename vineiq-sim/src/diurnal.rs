@@ -0,0 +1,73 @@
+//!
+//! Synthetic daily curves for the simulated sensors
+//!
+
+use chrono::{Local, Timelike};
+use std::f64::consts::PI;
+
+use crate::config::Range;
+
+//
+// fractional local hour of the day, 0..24
+//
+pub fn hour_now() -> f64 {
+    let now = Local::now();
+    now.hour() as f64 + now.minute() as f64 / 60.0 + now.second() as f64 / 3600.0
+}
+
+//
+// coolest just before sunrise, warmest in mid afternoon
+//
+fn cycle(hour: f64) -> f64 {
+    (2.0 * PI * (hour - 15.0) / 24.0).cos()
+}
+
+pub fn temperature(range: &Range, hour: f64) -> f64 {
+    let mid = (range.max + range.min) / 2.0;
+    let amplitude = (range.max - range.min) / 2.0;
+    mid + amplitude * cycle(hour)
+}
+
+//
+// relative humidity moves against the temperature
+//
+pub fn humidity(range: &Range, hour: f64) -> f64 {
+    let mid = (range.max + range.min) / 2.0;
+    let amplitude = (range.max - range.min) / 2.0;
+    mid - amplitude * cycle(hour)
+}
+
+//
+// clear-sky shortwave radiation in W/m^2, zero between sunset and sunrise
+//
+pub fn solar_radiation(hour: f64) -> f64 {
+    (PI * (hour - 6.0) / 12.0).sin().max(0.0) * 900.0
+}
+
+//
+// afternoon breeze on top of a light background wind, in m/s
+//
+pub fn wind_speed(hour: f64) -> f64 {
+    1.0 + 3.0 * (PI * (hour - 9.0) / 12.0).sin().max(0.0)
+}
+
+//
+// small deterministic jitter so consecutive readings are not identical
+//
+pub struct Noise(u64);
+
+impl Noise {
+    pub fn new(seed: u64) -> Self {
+        Self(seed.max(1))
+    }
+
+    //
+    // uniform in -1..1 (xorshift64)
+    //
+    pub fn next(&mut self) -> f64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        (self.0 >> 11) as f64 / (1u64 << 52) as f64 - 1.0
+    }
+}
@@ -0,0 +1,67 @@
+//!
+//! Local stand-ins for the Tempest and YoLink cloud services
+//!
+//! Serves synthetic diurnal readings so the loggers can be run end to end
+//! without hardware or cloud accounts. Point the existing configuration at
+//! it through the URL settings, e.g. with the defaults:
+//!
+//! ```yaml
+//! tempest:
+//!   access_token: sim
+//!   device_id: "1110"
+//!   websocket_url: "ws://127.0.0.1:8765/swd/data"
+//! yolink:
+//!   yolink:
+//!     token: "http://127.0.0.1:8766/open/yolink/token"
+//!     api: "http://127.0.0.1:8766/open/yolink/v2/api"
+//!   mqtt:
+//!     broker: 127.0.0.1
+//!     port: 1883
+//! alerts:
+//!   notifiers:
+//!     hook: { type: webhook, url: "http://127.0.0.1:8767/hook" }
+//!     broker: { type: mqtt, broker: 127.0.0.1, port: 1884, topic: "vineiq/alerts/{rule}" }
+//!     mail:
+//!       type: smtp
+//!       host: 127.0.0.1
+//!       port: 2525
+//!       tls: none
+//!       from: "vineiq@localhost"
+//!       to: ["crew@localhost"]
+//! ```
+//!
+
+use clap::Parser;
+use vineiq_core::shutdown;
+
+mod config;
+mod diurnal;
+mod mqtt;
+mod notify;
+mod tempest;
+mod yolink_http;
+
+#[derive(Debug, Parser)]
+#[command(version, about, long_about = None)]
+struct Args {
+    /// Simulator settings; the built-in defaults are used if unset
+    #[arg(short, long)]
+    config: Option<String>,
+}
+
+#[tokio::main]
+async fn main() {
+    let args = Args::parse();
+    let config = match &args.config {
+        Some(file) => config::Config::new(file),
+        None => config::Config::default(),
+    };
+
+    tokio::spawn(tempest::serve(config.tempest));
+    tokio::spawn(yolink_http::serve(config.yolink.clone()));
+    tokio::spawn(mqtt::serve(config.yolink));
+    tokio::spawn(notify::serve(config.notify));
+
+    shutdown::shutdown_signal().await;
+    println!("shutting down");
+}
@@ -0,0 +1,176 @@
+//!
+//! Minimal MQTT 3.1.1 broker and the YoLink report publisher
+//!
+//! Enough of a broker for the logger: every client is accepted, QoS 0 only,
+//! no retained messages and no persistent sessions.
+//!
+
+use bytes::BytesMut;
+use rumqttc::mqttbytes::v4::{
+    self, ConnAck, ConnectReturnCode, Packet, PingResp, Publish, SubAck, SubscribeReasonCode,
+};
+use rumqttc::mqttbytes::{self, QoS};
+use serde_json::json;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::broadcast;
+use vineiq_core::jsonl::epoch_millis;
+
+use crate::config::YolinkConfig;
+use crate::diurnal::{self, Noise};
+
+const MAX_PACKET_SIZE: usize = 1024 * 1024;
+
+type Bus = broadcast::Sender<(String, String)>;
+
+pub async fn serve(config: YolinkConfig) {
+    let listener = TcpListener::bind(&config.mqtt_listen)
+        .await
+        .unwrap_or_else(|e| panic!("Error binding {}: {}", config.mqtt_listen, e));
+    println!("yolink mqtt: {}", config.mqtt_listen);
+
+    let (bus, _) = broadcast::channel(64);
+    tokio::spawn(publish_reports(config, bus.clone()));
+
+    loop {
+        let (stream, peer) = match listener.accept().await {
+            Ok(accepted) => accepted,
+            Err(e) => {
+                println!("mqtt: accept failed: {}", e);
+                continue;
+            }
+        };
+        let messages = bus.subscribe();
+        tokio::spawn(async move {
+            println!("mqtt: {} connected", peer);
+            match session(stream, messages).await {
+                Ok(()) => println!("mqtt: {} disconnected", peer),
+                Err(e) => println!("mqtt: {} disconnected: {}", peer, e),
+            }
+        });
+    }
+}
+
+async fn session(
+    mut stream: TcpStream,
+    mut messages: broadcast::Receiver<(String, String)>,
+) -> Result<(), String> {
+    let mut incoming = BytesMut::with_capacity(4096);
+    let mut outgoing = BytesMut::with_capacity(4096);
+    let mut filters: Vec<String> = Vec::new();
+
+    loop {
+        //
+        // answer every complete packet already buffered before reading more
+        //
+        loop {
+            let packet = match v4::read(&mut incoming, MAX_PACKET_SIZE) {
+                Ok(packet) => packet,
+                Err(mqttbytes::Error::InsufficientBytes(_)) => break,
+                Err(e) => return Err(format!("{:?}", e)),
+            };
+            let written = match packet {
+                Packet::Connect(_) => {
+                    ConnAck::new(ConnectReturnCode::Success, false).write(&mut outgoing)
+                }
+                Packet::Subscribe(subscribe) => {
+                    let codes = subscribe
+                        .filters
+                        .iter()
+                        .map(|_| SubscribeReasonCode::Success(QoS::AtMostOnce))
+                        .collect();
+                    filters.extend(subscribe.filters.into_iter().map(|f| f.path));
+                    SubAck::new(subscribe.pkid, codes).write(&mut outgoing)
+                }
+                Packet::PingReq => PingResp.write(&mut outgoing),
+                Packet::Disconnect => return Ok(()),
+                _ => Ok(0),
+            };
+            written.map_err(|e| format!("{:?}", e))?;
+        }
+        if !outgoing.is_empty() {
+            stream
+                .write_all(&outgoing)
+                .await
+                .map_err(|e| e.to_string())?;
+            outgoing.clear();
+        }
+
+        tokio::select! {
+            read = stream.read_buf(&mut incoming) => {
+                if read.map_err(|e| e.to_string())? == 0 {
+                    return Ok(());
+                }
+            }
+            message = messages.recv() => {
+                let (topic, payload) = match message {
+                    Ok(message) => message,
+                    Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                    Err(broadcast::error::RecvError::Closed) => return Ok(()),
+                };
+                if filters.iter().any(|filter| mqttbytes::matches(&topic, filter)) {
+                    Publish::new(topic, QoS::AtMostOnce, payload)
+                        .write(&mut outgoing)
+                        .map_err(|e| format!("{:?}", e))?;
+                }
+            }
+        }
+    }
+}
+
+/*
+  a THSensor.Report on yl-home/{home id}/{device id}/report for every
+  configured sensor, once per interval
+*/
+async fn publish_reports(config: YolinkConfig, bus: Bus) {
+    let mut noise = Noise::new(epoch_millis() as u64);
+    let mut ticker = tokio::time::interval(Duration::from_secs(config.interval_secs.max(1)));
+
+    loop {
+        ticker.tick().await;
+        let hour = diurnal::hour_now();
+        for sensor in &config.sensors {
+            let temperature = diurnal::temperature(&sensor.temperature, hour) + 0.2 * noise.next();
+            let humidity =
+                (diurnal::humidity(&sensor.humidity, hour) + noise.next()).clamp(0.0, 100.0);
+            let time = epoch_millis();
+            let report = json!({
+                "event": "THSensor.Report",
+                "time": time,
+                "msgid": time.to_string(),
+                "deviceId": sensor.device_id,
+                "data": {
+                    "alarm": {
+                        "code": 0,
+                        "highHumidity": false,
+                        "highTemp": false,
+                        "lowBattery": false,
+                        "lowHumidity": false,
+                        "lowTemp": false,
+                        "period": false
+                    },
+                    "battery": 4,
+                    "batteryType": "Li",
+                    "temperature": (temperature * 10.0).round() / 10.0,
+                    "humidity": (humidity * 10.0).round() / 10.0,
+                    "interval": 0,
+                    "mode": "f",
+                    "state": "normal",
+                    "version": "050f",
+                    "loraInfo": {
+                        "gatewayId": "d88b4c1600000000",
+                        "gateways": 1,
+                        "netId": "010201",
+                        "signal": (-70.0 + 10.0 * noise.next()).round() as i64
+                    }
+                }
+            });
+            let topic = format!("yl-home/{}/{}/report", config.home_id, sensor.device_id);
+            //
+            // no receivers just means no logger is connected yet
+            //
+            let _ = bus.send((topic, report.to_string()));
+        }
+    }
+}
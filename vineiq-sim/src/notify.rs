@@ -0,0 +1,39 @@
+//!
+//! Receivers for the alert notifiers
+//!
+//! The webhook, MQTT and SMTP stand-ins from vineiq-alerts on fixed ports,
+//! printing each alert as it arrives.
+//!
+
+use std::time::Duration;
+use vineiq_alerts::testing::{MqttReceiver, SmtpReceiver, WebhookReceiver};
+
+use crate::config::NotifyConfig;
+
+pub async fn serve(config: NotifyConfig) {
+    let webhook = WebhookReceiver::bind(&config.webhook_listen, 0);
+    let mqtt = MqttReceiver::bind(&config.mqtt_listen);
+    let smtp = SmtpReceiver::bind(&config.smtp_listen);
+    println!(
+        "notify: webhook on {}, mqtt on {}, smtp on {}",
+        config.webhook_listen, config.mqtt_listen, config.smtp_listen
+    );
+
+    let mut ticker = tokio::time::interval(Duration::from_secs(1));
+    loop {
+        ticker.tick().await;
+        for request in webhook.take() {
+            println!("notify: webhook {} {}", request.path, request.body);
+        }
+        for publish in mqtt.take() {
+            println!("notify: mqtt {} {}", publish.topic, publish.payload);
+        }
+        for message in smtp.take() {
+            println!(
+                "notify: smtp to {} -- {}",
+                message.to.join(","),
+                message.header("Subject").unwrap_or_default()
+            );
+        }
+    }
+}
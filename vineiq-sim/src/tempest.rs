@@ -0,0 +1,153 @@
+//!
+//! Tempest-compatible websocket server
+//!
+//! Answers `listen_start` with an ack and then sends an `obs_st` every
+//! interval, with the occasional `evt_strike`, for the requested device.
+//!
+
+use futures_util::{SinkExt, StreamExt};
+use serde_json::{json, Value};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::net::{TcpListener, TcpStream};
+use tokio_tungstenite::tungstenite::{self, Message};
+
+use crate::config::TempestConfig;
+use crate::diurnal::{self, Noise};
+
+pub async fn serve(config: TempestConfig) {
+    let listener = TcpListener::bind(&config.listen)
+        .await
+        .unwrap_or_else(|e| panic!("Error binding {}: {}", config.listen, e));
+    println!("tempest websocket: ws://{}", config.listen);
+
+    loop {
+        let (stream, peer) = match listener.accept().await {
+            Ok(accepted) => accepted,
+            Err(e) => {
+                println!("tempest: accept failed: {}", e);
+                continue;
+            }
+        };
+        let config = config.clone();
+        tokio::spawn(async move {
+            println!("tempest: {} connected", peer);
+            match session(stream, &config).await {
+                Ok(()) => println!("tempest: {} disconnected", peer),
+                Err(e) => println!("tempest: {} disconnected: {}", peer, e),
+            }
+        });
+    }
+}
+
+async fn session(stream: TcpStream, config: &TempestConfig) -> Result<(), tungstenite::Error> {
+    let mut socket = tokio_tungstenite::accept_async(stream).await?;
+    socket
+        .send(Message::Text(
+            json!({"type": "connection_opened"}).to_string(),
+        ))
+        .await?;
+
+    let mut device_id: Option<i64> = None;
+    let mut noise = Noise::new(epoch_secs() as u64);
+    let mut ticker = tokio::time::interval(Duration::from_secs(config.interval_secs.max(1)));
+
+    loop {
+        tokio::select! {
+            msg = socket.next() => {
+                let text = match msg {
+                    Some(Ok(Message::Text(text))) => text,
+                    Some(Ok(Message::Close(_))) | None => return Ok(()),
+                    Some(Ok(_)) => continue,
+                    Some(Err(e)) => return Err(e),
+                };
+                let request: Value = serde_json::from_str(&text).unwrap_or(Value::Null);
+                match request["type"].as_str() {
+                    Some("listen_start") => {
+                        device_id = request["device_id"].as_i64();
+                        ticker.reset_immediately();
+                    }
+                    Some("listen_stop") => device_id = None,
+                    _ => {}
+                }
+                let ack = json!({"type": "ack", "id": request["id"]});
+                socket.send(Message::Text(ack.to_string())).await?;
+            }
+            _ = ticker.tick() => {
+                let Some(device_id) = device_id else {
+                    continue;
+                };
+                socket
+                    .send(Message::Text(observation(device_id, config, &mut noise).to_string()))
+                    .await?;
+                if (noise.next() + 1.0) / 2.0 < config.strike_probability {
+                    socket
+                        .send(Message::Text(strike(device_id, &mut noise).to_string()))
+                        .await?;
+                }
+            }
+        }
+    }
+}
+
+fn epoch_secs() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+fn observation(device_id: i64, config: &TempestConfig, noise: &mut Noise) -> Value {
+    let hour = diurnal::hour_now();
+    let temperature = diurnal::temperature(&config.temperature, hour) + 0.2 * noise.next();
+    let humidity = (diurnal::humidity(&config.humidity, hour) + noise.next()).clamp(0.0, 100.0);
+    let radiation = (diurnal::solar_radiation(hour) * (0.95 + 0.05 * noise.next())).max(0.0);
+    let wind_avg = (diurnal::wind_speed(hour) + 0.3 * noise.next()).max(0.0);
+    let wind_direction = (270.0 + 40.0 * noise.next()).rem_euclid(360.0);
+
+    json!({
+        "device_id": device_id,
+        "type": "obs_st",
+        "source": "sim",
+        "obs": [[
+            epoch_secs(),
+            round(wind_avg * 0.6, 2),
+            round(wind_avg, 2),
+            round(wind_avg * 1.6, 2),
+            wind_direction.round(),
+            3,
+            round(1013.0 + 2.0 * noise.next(), 2),
+            round(temperature, 2),
+            round(humidity, 2),
+            (radiation * 120.0).round(),
+            round(radiation / 100.0, 2),
+            radiation.round(),
+            0.0,
+            0,
+            0,
+            0,
+            2.6,
+            config.interval_secs.div_ceil(60),
+            0.0,
+            null,
+            null,
+            0
+        ]]
+    })
+}
+
+fn strike(device_id: i64, noise: &mut Noise) -> Value {
+    json!({
+        "device_id": device_id,
+        "type": "evt_strike",
+        "evt": [
+            epoch_secs(),
+            (20.0 + 15.0 * noise.next()).round(),
+            (4000.0 + 3000.0 * noise.next()).round()
+        ]
+    })
+}
+
+fn round(value: f64, places: i32) -> f64 {
+    let factor = 10f64.powi(places);
+    (value * factor).round() / factor
+}
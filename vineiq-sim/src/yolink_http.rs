@@ -0,0 +1,125 @@
+//!
+//! YoLink token endpoint and API stand-in
+//!
+//! Any path ending in `/token` hands out a token; every other POST is taken
+//! as an API call and answered by its `method`.
+//!
+
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Method, Request, Response, Server, StatusCode};
+use serde_json::{json, Value};
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use vineiq_core::jsonl::epoch_millis;
+
+use crate::config::YolinkConfig;
+
+pub async fn serve(config: YolinkConfig) {
+    let addr: SocketAddr = config
+        .http_listen
+        .parse()
+        .unwrap_or_else(|e| panic!("invalid http_listen {}: {}", config.http_listen, e));
+    let config = Arc::new(config);
+
+    let make_service = make_service_fn(move |_| {
+        let config = config.clone();
+        async move {
+            Ok::<_, Infallible>(service_fn(move |request| {
+                let config = config.clone();
+                async move { Ok::<_, Infallible>(handle(&config, request).await) }
+            }))
+        }
+    });
+
+    println!("yolink http: http://{}", addr);
+    if let Err(e) = Server::bind(&addr).serve(make_service).await {
+        println!("yolink http: {}", e);
+    }
+}
+
+async fn handle(config: &YolinkConfig, request: Request<Body>) -> Response<Body> {
+    if request.method() != Method::POST {
+        return reply(StatusCode::METHOD_NOT_ALLOWED, json!({}));
+    }
+    if request.uri().path().ends_with("/token") {
+        return reply(StatusCode::OK, token(config));
+    }
+
+    let body = match hyper::body::to_bytes(request.into_body()).await {
+        Ok(body) => body,
+        Err(e) => return reply(StatusCode::BAD_REQUEST, json!({"desc": e.to_string()})),
+    };
+    let call: Value = serde_json::from_slice(&body).unwrap_or(Value::Null);
+    let method = call["method"].as_str().unwrap_or_default();
+    println!("yolink http: {}", method);
+
+    let data = match method {
+        "Home.getGeneralInfo" => json!({"id": config.home_id}),
+        "Home.getDeviceList" => device_list(config),
+        _ => {
+            return reply(
+                StatusCode::OK,
+                json!({
+                    "code": "020104",
+                    "time": epoch_millis(),
+                    "method": method,
+                    "desc": "Unsupported method"
+                }),
+            )
+        }
+    };
+    reply(
+        StatusCode::OK,
+        json!({
+            "code": "000000",
+            "time": epoch_millis(),
+            "msgid": epoch_millis(),
+            "method": method,
+            "desc": "Success",
+            "data": data
+        }),
+    )
+}
+
+/*
+  the grant type is not checked: client credentials and refresh both get a
+  fresh token
+*/
+fn token(config: &YolinkConfig) -> Value {
+    let now = epoch_millis();
+    json!({
+        "access_token": format!("sim-access-{}", now),
+        "refresh_token": format!("sim-refresh-{}", now),
+        "token_type": "bearer",
+        "expires_in": config.expires_in,
+        "scope": ["create"]
+    })
+}
+
+fn device_list(config: &YolinkConfig) -> Value {
+    let devices: Vec<Value> = config
+        .sensors
+        .iter()
+        .map(|sensor| {
+            json!({
+                "deviceId": sensor.device_id,
+                "deviceUDID": sensor.device_id,
+                "deviceeui": sensor.device_id,
+                "modelName": "YS8003-UC",
+                "name": sensor.name,
+                "token": format!("sim-device-{}", sensor.device_id),
+                "type": "THSensor"
+            })
+        })
+        .collect();
+    json!({ "devices": devices })
+}
+
+fn reply(status: StatusCode, body: Value) -> Response<Body> {
+    Response::builder()
+        .status(status)
+        .header("Content-Type", "application/json")
+        .body(Body::from(body.to_string()))
+        .expect("valid response")
+}
@@ -0,0 +1,142 @@
+//!
+//! Combined configuration for running every source in one process
+//!
+//!   questdb: "vinedb:9009"
+//!   spool: { ... }          # optional, shared by all sources
+//!   batch: { ... }          # optional, shared by all sources
+//!   units: { ... }          # optional, replaces the units of every source
+//!   tempest: { ... }        # tempest_logger settings, without questdb
+//!   yolink: { ... }         # yolink_logger settings, without yolink.database
+//!   questdb_http: "http://vinedb:9000"  # needed by the analytics below
+//!   gdd: { ... }            # growing degree days, see vineiq_analytics::gdd
+//!   frost: { ... }          # frost forecast, see vineiq_analytics::frost
+//!   mildew: { ... }         # powdery mildew risk, see vineiq_analytics::mildew
+//!   alerts: { ... }         # alert rules, see vineiq_alerts::config
+//!
+//! A source or analytics service is started only when its section is present.
+//!
+
+use serde_derive::Deserialize;
+use serde_json::Value;
+use tempest_logger::tempest;
+use vineiq_alerts::{AlertSink, AlertsConfig};
+use vineiq_analytics::frost::FrostConfig;
+use vineiq_analytics::gdd::GddConfig;
+use vineiq_analytics::mildew::MildewConfig;
+use vineiq_analytics::QueryClient;
+use vineiq_core::config::{questdb_sink, BatchConfig, SpoolConfig};
+use vineiq_core::units::{Units, UnitsConfig};
+use vineiq_core::writer::{WriterSink, DEFAULT_QUEUE_ROWS};
+use vineiq_core::Sink;
+use yolink_logger::yolink;
+
+#[derive(Deserialize, Debug)]
+pub struct Config {
+    pub questdb: String,
+    #[serde(default)]
+    pub spool: SpoolConfig,
+    #[serde(default)]
+    pub batch: BatchConfig,
+    pub units: Option<UnitsConfig>,
+    pub tempest: Option<Value>,
+    pub yolink: Option<yolink::Config>,
+    pub questdb_http: Option<String>,
+    pub gdd: Option<GddConfig>,
+    pub frost: Option<FrostConfig>,
+    pub mildew: Option<MildewConfig>,
+    pub alerts: Option<AlertsConfig>,
+}
+
+impl Config {
+    pub fn new(config_file: &str) -> Self {
+        let content = std::fs::read_to_string(config_file).unwrap();
+        let mut config: Config = serde_yaml::from_str(&content)
+            .unwrap_or_else(|e| panic!("invalid configuration {}: {}", config_file, e));
+        config.share_units();
+        config
+    }
+
+    //
+    // the analytics and alerts sections, checked before any source is started
+    //
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(gdd) = &self.gdd {
+            gdd.validate()?;
+        }
+        if let Some(frost) = &self.frost {
+            frost.validate()?;
+        }
+        if let Some(mildew) = &self.mildew {
+            mildew.validate()?;
+        }
+        if let Some(alerts) = &self.alerts {
+            alerts.validate()?;
+        }
+        let analytics = self.gdd.is_some() || self.frost.is_some() || self.mildew.is_some();
+        if analytics && self.questdb_http.is_none() {
+            return Err("questdb_http is required by the analytics".to_string());
+        }
+        Ok(())
+    }
+
+    //
+    // the database sink, on its writer thread and behind the alert rules
+    // when there are any
+    //
+    pub fn open_sink(&self, name: &str) -> Box<dyn Sink + Send> {
+        let sink = Box::new(self.open_writer(name));
+        match &self.alerts {
+            Some(alerts) => Box::new(
+                AlertSink::from_config(sink, alerts)
+                    .expect("the alert rules are checked by Config::validate"),
+            ),
+            None => sink,
+        }
+    }
+
+    pub fn open_writer(&self, name: &str) -> WriterSink {
+        let sink = questdb_sink(&self.questdb, name, &self.spool, &self.batch);
+        WriterSink::spawn(name, sink, DEFAULT_QUEUE_ROWS)
+    }
+
+    pub fn get_units(&self) -> Units {
+        self.units.as_ref().map(Units::from).unwrap_or_default()
+    }
+
+    pub fn get_query_client(&self) -> QueryClient {
+        let url = self
+            .questdb_http
+            .as_deref()
+            .expect("questdb_http is required by the analytics");
+        QueryClient::new(url)
+    }
+
+    //
+    // every table in one database should be in the same units, so a top
+    // level setting is handed down to each source section
+    //
+    fn share_units(&mut self) {
+        let Some(units) = &self.units else {
+            return;
+        };
+        if let Some(section) = self.tempest.take() {
+            let mut conf = tempest::Conf::from_value(section);
+            conf.set_units(units);
+            self.tempest = Some(conf.into_value());
+        }
+        if let Some(yolink) = self.yolink.as_mut() {
+            yolink.set_units(units);
+        }
+    }
+}
+
+//
+// whether a configuration file, of any of the formats, has a top level
+// `section`
+//
+pub fn has_section(path: &str, section: &str) -> bool {
+    std::fs::read_to_string(path)
+        .ok()
+        .and_then(|content| serde_yaml::from_str::<serde_yaml::Value>(&content).ok())
+        .is_some_and(|value| value.get(section).is_some())
+}
@@ -0,0 +1,130 @@
+//!
+//! Stored payloads fed back through the loggers' message handling, for the
+//! reprocess and replay commands
+//!
+
+use serde_json::Value;
+use tempest_logger::database::Appender as TempestAppender;
+use tempest_logger::tempest;
+use vineiq_core::units::Units;
+use vineiq_core::{DeadLetter, SharedSink, Sink};
+use yolink_logger::database::Appender as YolinkAppender;
+use yolink_logger::yolink;
+
+use crate::config::Config;
+
+const SPOOL_NAME: &str = "vineiq-replay";
+
+pub struct Feed {
+    config: Config,
+    sink: SharedSink,
+    dead_letter: Option<String>,
+    tempest: Option<TempestAppender>,
+    yolink: Option<YolinkAppender>,
+}
+
+impl Feed {
+    //
+    // rejected messages go to the given dead-letter file, or to the one
+    // configured for their source. The rows are written straight to the
+    // database unless `alerts` is set: the alerts they raised went out the
+    // first time round. The feed spools apart from the live service, which
+    // may be running beside it.
+    //
+    pub fn new(config: Config, dead_letter: Option<&str>, alerts: bool) -> Self {
+        let sink = if alerts {
+            config.open_sink(SPOOL_NAME)
+        } else {
+            Box::new(config.open_writer(SPOOL_NAME))
+        };
+        let sink = SharedSink::new(sink);
+        Self {
+            config,
+            sink,
+            dead_letter: dead_letter.map(str::to_string),
+            tempest: None,
+            yolink: None,
+        }
+    }
+
+    //
+    // false when no logger handles messages from this source
+    //
+    pub async fn process(&mut self, source: &str, payload: &str) -> bool {
+        match source {
+            "tempest" => {
+                if self.tempest.is_none() {
+                    self.tempest = Some(self.tempest_appender());
+                }
+                self.tempest.as_mut().unwrap().log_record(payload);
+                true
+            }
+            "yolink" if self.config.yolink.is_some() => {
+                if self.yolink.is_none() {
+                    self.yolink = Some(self.yolink_appender().await);
+                }
+                self.yolink.as_mut().unwrap().log_event(payload);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    pub fn rejected(&self) -> u64 {
+        self.tempest.as_ref().map_or(0, |a| a.rejected())
+            + self.yolink.as_ref().map_or(0, |a| a.rejected())
+    }
+
+    pub fn tick(&mut self) {
+        self.sink.tick();
+    }
+
+    pub fn shutdown(&mut self) {
+        self.sink.shutdown();
+    }
+
+    fn dead_letter(&self, source: &str, configured: impl FnOnce() -> DeadLetter) -> DeadLetter {
+        match &self.dead_letter {
+            Some(path) => DeadLetter::new(source, Some(path)),
+            None => configured(),
+        }
+    }
+
+    fn tempest_appender(&self) -> TempestAppender {
+        let section = self.config.tempest.clone().unwrap_or(Value::Null);
+        let mut conf = tempest::Conf::from_value(section);
+        let dead_letter = self.dead_letter("tempest", || conf.get_dead_letter().open("tempest"));
+        let mut appender = TempestAppender::new(Box::new(self.sink.clone()), dead_letter);
+        appender.set_units(Units::from(&conf.get_units()));
+        appender
+    }
+
+    //
+    // the device names come from the API; without it only the sensors listed
+    // in the configuration can be matched
+    //
+    async fn yolink_appender(&self) -> YolinkAppender {
+        let mut conf = self.config.yolink.clone().unwrap();
+        let dead_letter = self.dead_letter("yolink", || conf.get_dead_letter().open("yolink"));
+        let mut appender = YolinkAppender::new(
+            Box::new(self.sink.clone()),
+            dead_letter,
+            &conf.get_sensors(),
+            conf.get_strict(),
+        );
+        appender.set_units(Units::from(&conf.get_units()));
+        match yolink::Access::new(&conf.get_token_url(), &conf.get_ua_id(), &conf.get_sec_id())
+            .await
+        {
+            Ok(access) => {
+                let mut api = yolink::Api::new(&conf.get_api_url(), &access.token());
+                match api.get_all_devices().await {
+                    Ok(devices) => appender.register_devices(devices),
+                    Err(e) => println!("Error acquiring the device list: {}", e),
+                }
+            }
+            Err(e) => println!("Error acquiring an access token: {}", e),
+        }
+        appender
+    }
+}
@@ -0,0 +1,395 @@
+use clap::{Parser, Subcommand};
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tempest_logger::tempest;
+use tokio::task::JoinHandle;
+use vineiq_analytics::frost::{self, FrostConfig};
+use vineiq_analytics::gdd::{self, GddConfig};
+use vineiq_analytics::mildew::{self, MildewConfig};
+use vineiq_analytics::QueryClient;
+use vineiq_core::config::questdb_sink;
+use vineiq_core::units::Units;
+use vineiq_core::writer::{WriterSink, DEFAULT_QUEUE_ROWS};
+use vineiq_core::{shutdown, Recorder, SharedSink, Sink};
+use yolink_logger::yolink;
+
+mod config;
+mod feed;
+mod replay;
+mod reprocess;
+
+const MIN_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(300);
+const STABLE_SESSION: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Parser)]
+#[command(version, about, long_about = None)]
+struct Args {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Run every source in the combined configuration
+    Run {
+        #[arg(short, long)]
+        config: String,
+        /// Append every raw payload to this JSONL file
+        #[arg(long)]
+        record: Option<String>,
+    },
+    /// Run only the Tempest logger
+    Tempest {
+        #[arg(short, long)]
+        config: String,
+        /// Append every raw payload to this JSONL file
+        #[arg(long)]
+        record: Option<String>,
+    },
+    /// Run only the YoLink logger
+    Yolink {
+        #[arg(short, long)]
+        config: String,
+        /// Append every raw payload to this JSONL file
+        #[arg(long)]
+        record: Option<String>,
+    },
+    /// Feed a recorded JSONL file through the loggers into the database
+    Replay {
+        #[arg(short, long)]
+        config: String,
+        /// Playback speed relative to real time; as fast as possible if unset
+        #[arg(long)]
+        speed: Option<f64>,
+        /// Evaluate the alert rules, and notify, as the rows are written
+        #[arg(long)]
+        alerts: bool,
+        file: String,
+    },
+    /// Process the messages in a dead-letter file again
+    Reprocess {
+        #[arg(short, long)]
+        config: String,
+        /// Evaluate the alert rules, and notify, as the rows are written
+        #[arg(long)]
+        alerts: bool,
+        file: String,
+    },
+    /// Bring the growing degree day table up to date once
+    Gdd {
+        #[arg(short, long)]
+        config: String,
+    },
+}
+
+#[tokio::main]
+async fn main() {
+    let args = Args::parse();
+    let shutdown = shutdown::flag();
+
+    let (mut sink, sources) = match args.command {
+        Command::Run { config, record } => {
+            let recorder = open_recorder(record);
+            let config = load_config(&config);
+            let sink = SharedSink::new(config.open_sink("vineiq"));
+            let mut sources = Vec::new();
+            if let Some(gdd) = &config.gdd {
+                let client = config.get_query_client();
+                let units = config.get_units();
+                sources.push(gdd_source(gdd, client, units, &sink, &shutdown));
+            }
+            if let Some(frost) = &config.frost {
+                let client = config.get_query_client();
+                let units = config.get_units();
+                sources.push(frost_source(frost, client, units, &sink, &shutdown));
+            }
+            if let Some(mildew) = &config.mildew {
+                let client = config.get_query_client();
+                sources.push(mildew_source(mildew, client, &sink, &shutdown));
+            }
+            if let Some(tempest) = config.tempest {
+                let conf = tempest::Conf::from_value(tempest);
+                sources.push(tempest_source(conf, &sink, &recorder, &shutdown));
+            }
+            if let Some(yolink) = config.yolink {
+                sources.push(yolink_source(yolink, &sink, &recorder, &shutdown));
+            }
+            if sources.is_empty() {
+                panic!("no sources configured");
+            }
+            (sink, sources)
+        }
+        Command::Tempest { config, record } => {
+            reject_alerts(&config);
+            let recorder = open_recorder(record);
+            let mut conf = tempest::Conf::new(&config);
+            let sink = questdb_sink(
+                &conf.get_questdb_url(),
+                "tempest",
+                &conf.get_spool(),
+                &conf.get_batch(),
+            );
+            let sink = SharedSink::new(Box::new(WriterSink::spawn(
+                "tempest",
+                sink,
+                DEFAULT_QUEUE_ROWS,
+            )));
+            let sources = vec![tempest_source(conf, &sink, &recorder, &shutdown)];
+            (sink, sources)
+        }
+        Command::Yolink { config, record } => {
+            reject_alerts(&config);
+            let recorder = open_recorder(record);
+            let mut conf = yolink::Config::new(&config);
+            let sink = questdb_sink(
+                &conf.get_database_url(),
+                "yolink",
+                &conf.get_spool(),
+                &conf.get_batch(),
+            );
+            let sink = SharedSink::new(Box::new(WriterSink::spawn(
+                "yolink",
+                sink,
+                DEFAULT_QUEUE_ROWS,
+            )));
+            let sources = vec![yolink_source(conf, &sink, &recorder, &shutdown)];
+            (sink, sources)
+        }
+        Command::Replay {
+            config,
+            speed,
+            alerts,
+            file,
+        } => {
+            replay::replay(load_config(&config), &file, speed, alerts, &shutdown).await;
+            return;
+        }
+        Command::Reprocess {
+            config,
+            alerts,
+            file,
+        } => {
+            reprocess::reprocess(load_config(&config), &file, alerts).await;
+            return;
+        }
+        Command::Gdd { config: path } => {
+            let config = load_config(&path);
+            //
+            // the update runs without a gdd section, on the defaults, which
+            // `validate` does not hold to the analytics' requirements
+            //
+            if config.questdb_http.is_none() {
+                println!("Error: {} has no questdb_http to read from", path);
+                std::process::exit(1);
+            }
+            let gdd = config.gdd.clone().unwrap_or_default();
+            let mut sink = config.open_writer("gdd");
+            match gdd::update(
+                &gdd,
+                &config.get_query_client(),
+                &mut sink,
+                &config.get_units(),
+            )
+            .await
+            {
+                Ok(count) => println!("gdd: wrote {} rows", count),
+                Err(e) => println!("gdd: update failed: {}", e),
+            }
+            sink.shutdown();
+            return;
+        }
+    };
+
+    for source in sources {
+        let _ = source.await;
+    }
+    sink.shutdown();
+}
+
+//
+// a configuration that cannot run is reported once, before anything starts
+//
+fn load_config(path: &str) -> config::Config {
+    let config = config::Config::new(path);
+    if let Err(e) = config.validate() {
+        println!("Error: invalid configuration {}: {}", path, e);
+        std::process::exit(1);
+    }
+    config
+}
+
+//
+// only `run` evaluates alert rules, so a single logger given a configuration
+// with them refuses to start rather than run without alerting
+//
+fn reject_alerts(path: &str) {
+    if config::has_section(path, "alerts") {
+        println!(
+            "Error: {} has alert rules, which only `vineiq run` evaluates",
+            path
+        );
+        std::process::exit(1);
+    }
+}
+
+fn open_recorder(path: Option<String>) -> Option<Recorder> {
+    path.map(|path| {
+        Recorder::open(&path).unwrap_or_else(|e| panic!("Error opening {}: {}", path, e))
+    })
+}
+
+fn tempest_source(
+    conf: tempest::Conf,
+    sink: &SharedSink,
+    recorder: &Option<Recorder>,
+    shutdown: &Arc<AtomicBool>,
+) -> JoinHandle<()> {
+    let sink = sink.clone();
+    let recorder = recorder.clone();
+    let flag = shutdown.clone();
+    let start = move || {
+        let mut conf = conf.clone();
+        let sink = sink.clone();
+        let recorder = recorder.clone();
+        let flag = flag.clone();
+        tokio::spawn(async move {
+            tempest_logger::run(&mut conf, Box::new(sink), recorder, &flag).await;
+            Ok(())
+        })
+    };
+    tokio::spawn(supervise("tempest", shutdown.clone(), start))
+}
+
+fn yolink_source(
+    conf: yolink::Config,
+    sink: &SharedSink,
+    recorder: &Option<Recorder>,
+    shutdown: &Arc<AtomicBool>,
+) -> JoinHandle<()> {
+    let sink = sink.clone();
+    let recorder = recorder.clone();
+    let flag = shutdown.clone();
+    let start = move || {
+        let mut conf = conf.clone();
+        let sink = sink.clone();
+        let recorder = recorder.clone();
+        let flag = flag.clone();
+        tokio::spawn(async move {
+            yolink_logger::run(&mut conf, Box::new(sink), recorder, &flag)
+                .await
+                .map_err(|e| e.to_string())
+        })
+    };
+    tokio::spawn(supervise("yolink", shutdown.clone(), start))
+}
+
+fn gdd_source(
+    config: &GddConfig,
+    client: QueryClient,
+    units: Units,
+    sink: &SharedSink,
+    shutdown: &Arc<AtomicBool>,
+) -> JoinHandle<()> {
+    let config = config.clone();
+    let sink = sink.clone();
+    let flag = shutdown.clone();
+    let start = move || {
+        let config = config.clone();
+        let client = client.clone();
+        let mut sink = sink.clone();
+        let flag = flag.clone();
+        tokio::spawn(async move {
+            gdd::run(&config, &client, &mut sink, &units, &flag).await;
+            Ok(())
+        })
+    };
+    tokio::spawn(supervise("gdd", shutdown.clone(), start))
+}
+
+fn frost_source(
+    config: &FrostConfig,
+    client: QueryClient,
+    units: Units,
+    sink: &SharedSink,
+    shutdown: &Arc<AtomicBool>,
+) -> JoinHandle<()> {
+    let config = config.clone();
+    let sink = sink.clone();
+    let flag = shutdown.clone();
+    let start = move || {
+        let config = config.clone();
+        let client = client.clone();
+        let mut sink = sink.clone();
+        let flag = flag.clone();
+        tokio::spawn(async move {
+            frost::run(&config, &client, &mut sink, &units, &flag).await;
+            Ok(())
+        })
+    };
+    tokio::spawn(supervise("frost", shutdown.clone(), start))
+}
+
+fn mildew_source(
+    config: &MildewConfig,
+    client: QueryClient,
+    sink: &SharedSink,
+    shutdown: &Arc<AtomicBool>,
+) -> JoinHandle<()> {
+    let config = config.clone();
+    let sink = sink.clone();
+    let flag = shutdown.clone();
+    let start = move || {
+        let config = config.clone();
+        let client = client.clone();
+        let mut sink = sink.clone();
+        let flag = flag.clone();
+        tokio::spawn(async move {
+            mildew::run(&config, &client, &mut sink, &flag).await;
+            Ok(())
+        })
+    };
+    tokio::spawn(supervise("mildew", shutdown.clone(), start))
+}
+
+//
+// restart a source that fails or panics, with exponential backoff, so one
+// misbehaving source leaves the others running
+//
+async fn supervise<F, T>(name: &str, shutdown: Arc<AtomicBool>, mut start: F)
+where
+    F: FnMut() -> T,
+    T: Future<Output = Result<Result<(), String>, tokio::task::JoinError>>,
+{
+    let mut backoff = MIN_BACKOFF;
+    loop {
+        let started = Instant::now();
+        let reason = match start().await {
+            Ok(Ok(())) => "stopped".to_string(),
+            Ok(Err(e)) => e,
+            Err(e) => e.to_string(),
+        };
+        if shutdown.load(Ordering::Relaxed) {
+            return;
+        }
+        if started.elapsed() > STABLE_SESSION {
+            backoff = MIN_BACKOFF;
+        }
+        println!(
+            "{} source failed: {} -- restarting in {}s",
+            name,
+            reason,
+            backoff.as_secs()
+        );
+        let resume_at = Instant::now() + backoff;
+        while Instant::now() < resume_at {
+            if shutdown.load(Ordering::Relaxed) {
+                return;
+            }
+            tokio::time::sleep(MIN_BACKOFF).await;
+        }
+        backoff = (backoff * 2).min(MAX_BACKOFF);
+    }
+}
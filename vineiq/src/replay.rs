@@ -0,0 +1,56 @@
+//!
+//! Feed a file of recorded payloads through the loggers into the sink
+//!
+
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use vineiq_core::{recorder, shutdown};
+
+use crate::config::Config;
+use crate::feed::Feed;
+
+//
+// with a speed the gaps between messages are kept, divided by the speed
+// (1 is real time); without one the file is replayed as fast as possible
+//
+pub async fn replay(
+    config: Config,
+    file: &str,
+    speed: Option<f64>,
+    alerts: bool,
+    shutdown: &AtomicBool,
+) {
+    let captures =
+        recorder::read(Path::new(file)).unwrap_or_else(|e| panic!("Error reading {}: {}", file, e));
+
+    let mut feed = Feed::new(config, None, alerts);
+    let mut replayed = 0;
+    let mut previous: Option<i64> = None;
+    for capture in &captures {
+        if let (Some(speed), Some(previous)) = (speed, previous) {
+            let gap = (capture.time - previous).max(0) as f64 / 1000.0 / speed;
+            if !shutdown::pause(Duration::from_secs_f64(gap), shutdown, || feed.tick()).await {
+                break;
+            }
+        }
+        if shutdown.load(Ordering::Relaxed) {
+            break;
+        }
+        previous = Some(capture.time);
+        if feed.process(&capture.source, &capture.payload).await {
+            replayed += 1;
+        } else {
+            println!("no logger for {} messages -- skipping", capture.source);
+        }
+    }
+    feed.shutdown();
+
+    println!(
+        "replayed {} of {} messages from {}, {} rejected",
+        replayed,
+        captures.len(),
+        file,
+        feed.rejected()
+    );
+}
@@ -0,0 +1,53 @@
+//!
+//! Feed a dead-letter file back through the parsers
+//!
+//! The file is moved aside first, so a logger that is still running starts a
+//! new one; whatever is rejected again is appended to that new file.
+//!
+
+use std::fs;
+use std::path::PathBuf;
+use vineiq_core::dead_letter;
+use vineiq_core::DeadLetter;
+
+use crate::config::Config;
+use crate::feed::Feed;
+
+pub async fn reprocess(config: Config, file: &str, alerts: bool) {
+    let path = PathBuf::from(file);
+    let mut working = path.clone().into_os_string();
+    working.push(".reprocess");
+    let working = PathBuf::from(working);
+    if working.exists() {
+        panic!(
+            "{} already exists -- an earlier reprocess did not finish",
+            working.display()
+        );
+    }
+    fs::rename(&path, &working)
+        .unwrap_or_else(|e| panic!("Error moving {} aside: {}", path.display(), e));
+    let records = dead_letter::read(&working)
+        .unwrap_or_else(|e| panic!("Error reading {}: {}", working.display(), e));
+
+    let mut feed = Feed::new(config, Some(file), alerts);
+    let mut unhandled = 0;
+    for record in &records {
+        if !feed.process(&record.source, &record.payload).await {
+            //
+            // nothing here can parse it, so it goes back as it was
+            //
+            DeadLetter::new(&record.source, Some(file)).record(&record.payload, &record.reason);
+            unhandled += 1;
+        }
+    }
+    feed.shutdown();
+
+    fs::remove_file(&working)
+        .unwrap_or_else(|e| panic!("Error removing {}: {}", working.display(), e));
+    println!(
+        "reprocessed {} messages from {}, {} rejected again",
+        records.len(),
+        file,
+        feed.rejected() + unhandled
+    );
+}
@@ -2,18 +2,54 @@ extern crate serde_derive;
 
 use std::collections::HashMap;
 
-use chrono::NaiveDateTime;
-use questdb::{
-    ingress::{Buffer, Sender, TimestampMicros, TimestampNanos},
-    Result,
-};
-use serde_json::Value;
+use vineiq_core::intake::Intake;
+use vineiq_core::units::Units;
+use vineiq_core::{derived, DeadLetter, Observation, Recorder, Result, Sink};
 
-use crate::yolink::Sensor;
+use crate::model::{Envelope, Event, ThSensorData};
+use crate::yolink::{Device, Sensor};
 
 pub struct Appender {
-    db_appender: Sender,
+    intake: Intake,
     sensors: HashMap<String, Sensor>,
+    devices: HashMap<String, Device>,
+    strict: bool,
+}
+
+//
+// what gets written alongside a reading: the API supplies the device name,
+// model and type, the configuration adds the location and vineyard block
+//
+struct Registration {
+    name: String,
+    model: Option<String>,
+    dtype: Option<String>,
+    lat: Option<f64>,
+    long: Option<f64>,
+    block: Option<String>,
+}
+
+impl Registration {
+    fn append_symbols(&self, observation: &mut Observation) {
+        if let Some(model) = &self.model {
+            observation.symbol("model", model);
+        }
+        if let Some(dtype) = &self.dtype {
+            observation.symbol("deviceType", dtype);
+        }
+        if let Some(block) = &self.block {
+            observation.symbol("block", block);
+        }
+    }
+
+    fn append_location(&self, observation: &mut Observation) {
+        if let Some(lat) = self.lat {
+            observation.column_f64("lat", lat);
+        }
+        if let Some(long) = self.long {
+            observation.column_f64("long", long);
+        }
+    }
 }
 
 impl Appender {
@@ -60,9 +96,12 @@ impl Appender {
       "time": 1712517507811
     }
     */
-    pub fn new(db_url: &String, sensors: &Vec<Sensor>) -> Appender {
-        let db_appender = Sender::from_conf(format!("tcp::addr={db_url};"));
-
+    pub fn new(
+        sink: Box<dyn Sink + Send>,
+        dead_letter: DeadLetter,
+        sensors: &Vec<Sensor>,
+        strict: bool,
+    ) -> Appender {
         let mut sensor_map: HashMap<String, Sensor> = HashMap::new();
 
         for sensor in sensors {
@@ -70,76 +109,213 @@ impl Appender {
         }
 
         Appender {
-            db_appender: db_appender.expect("Error: failed to connecto to questdb"),
+            intake: Intake::new("yolink", sink, dead_letter),
             sensors: sensor_map,
+            devices: HashMap::new(),
+            strict,
+        }
+    }
+
+    pub fn tick(&mut self) {
+        self.intake.tick();
+    }
+
+    pub fn shutdown(&mut self) {
+        self.intake.shutdown();
+    }
+
+    pub fn set_recorder(&mut self, recorder: Recorder) {
+        self.intake.set_recorder(recorder);
+    }
+
+    pub fn set_units(&mut self, units: Units) {
+        self.intake.set_units(units);
+    }
+
+    pub fn rejected(&self) -> u64 {
+        self.intake.rejected()
+    }
+
+    //
+    // an event that cannot be parsed or written goes to the dead-letter file
+    // instead of stopping the logger
+    //
+    pub fn log_event(&mut self, message: &str) {
+        self.intake.record(message);
+        if let Err(e) = self.process_event(message) {
+            self.intake.reject(message, &e);
+        }
+    }
+
+    fn process_event(&mut self, message: &str) -> Result<()> {
+        match serde_json::from_str::<Event>(message)? {
+            Event::Report(report) => self.process_report(&report),
+            Event::Alert(alert) => self.process_alert(&alert),
+            Event::Other => {
+                println!("Unknown event\n{}", message);
+                Ok(())
+            }
+        }
+    }
+
+    pub fn register_devices(&mut self, devices: HashMap<String, Device>) {
+        for (device_id, device) in devices {
+            if !self.devices.contains_key(&device_id) {
+                println!(
+                    "Registered device {} ({} {}) id {}",
+                    device.name, device.dtype, device.model, device_id
+                );
+            }
+            self.devices.insert(device_id, device);
         }
     }
 
-    pub fn to_fahrenheit(&mut self, celcius: f64) -> Option<f64> {
-        Some((celcius * 1.8) + 32.0)
+    pub fn is_registered(&self, device_id: &str) -> bool {
+        self.sensors.contains_key(device_id) || self.devices.contains_key(device_id)
     }
 
-    pub fn get_vpd(&mut self, fahrenheit: f64, humidity: f64) -> Option<f64> {
-        Some(0.0)
+    pub fn is_strict(&self) -> bool {
+        self.strict
     }
 
-    pub fn process_alert(&mut self, data: &Value) -> Result<()> {
-        println!(
-            "process_alert: \n{}",
-            serde_json::to_string_pretty(&data).unwrap()
-        );
-        Ok(())
+    fn registration(&self, device_id: &str) -> Option<Registration> {
+        let sensor = self.sensors.get(device_id);
+        let device = self.devices.get(device_id);
+        if sensor.is_none() && (self.strict || device.is_none()) {
+            return None;
+        }
+
+        let name = sensor
+            .and_then(|s| s.name.clone())
+            .or_else(|| device.map(|d| d.name.clone()))
+            .unwrap_or_else(|| device_id.to_string());
+        Some(Registration {
+            name,
+            model: device.map(|d| d.model.clone()),
+            dtype: device.map(|d| d.dtype.clone()),
+            lat: sensor.and_then(|s| s.lat),
+            long: sensor.and_then(|s| s.long),
+            block: sensor.and_then(|s| s.block.clone()),
+        })
     }
-    pub fn process_report(&mut self, json_object: &Value) -> Result<()> {
+
+    /*
+      {
+        "data": {
+          "alarm": {
+            "code": 1,
+            "highHumidity": false,
+            "highTemp": false,
+            "lowBattery": false,
+            "lowHumidity": false,
+            "lowTemp": true,
+            "period": false
+          },
+          "battery": 4,
+          "humidity": 71.5,
+          "mode": "f",
+          "state": "alert",
+          "temperature": 0.8,
+          ...
+        },
+        "deviceId": "d88b4c010008b987",
+        "event": "THSensor.Alert",
+        "msgid": "1712517507810",
+        "time": 1712517507811
+      }
+    */
+    pub fn process_alert(&mut self, alert: &Envelope<ThSensorData>) -> Result<()> {
+        println!("process_alert:");
+
+        println!("{:?}", alert);
+
+        let time_us = alert.time * 1000;
+        let units = self.intake.units();
+        let device_id = alert.device_id.as_str();
+        let data = &alert.data;
+        let alarm = &data.alarm;
+        let registration = match self.registration(device_id) {
+            Some(r) => r,
+            None => {
+                print!("Unknown sensor id {} -- ignoring alert", device_id);
+                return Ok(());
+            }
+        };
+
+        let mut observation = Observation::new("yolink_alert", time_us);
+        observation
+            .symbol("sensorName", &registration.name)
+            .symbol("deviceId", device_id);
+        registration.append_symbols(&mut observation);
+        observation
+            .symbol("state", data.state.as_deref().unwrap_or("alert"))
+            .symbol("units", &units.label());
+        registration.append_location(&mut observation);
+        observation
+            .column_i64("code", alarm.code)
+            .column_bool("highTemp", alarm.high_temp)
+            .column_bool("lowTemp", alarm.low_temp)
+            .column_bool("highHumidity", alarm.high_humidity)
+            .column_bool("lowHumidity", alarm.low_humidity)
+            .column_bool("lowBattery", alarm.low_battery)
+            .column_bool("period", alarm.period)
+            //
+            // the reading that tripped the alarm, when the device includes it
+            //
+            .column_opt_f64(
+                "temperature",
+                data.temperature.map(|c| units.temperature(c)),
+            )
+            .column_opt_f64("humidity", data.humidity)
+            .column_opt_i64("battery", data.battery);
+
+        self.intake.write(&observation)
+    }
+
+    pub fn process_report(&mut self, report: &Envelope<ThSensorData>) -> Result<()> {
         println!("process_report:");
 
-        println!("{}", json_object);
-
-        let time_ms = json_object["time"].as_i64().unwrap() * 1000; // microseconds
-        let device_id = json_object["deviceId"].as_str().expect("Missing deviceId");
-        let data = &json_object["data"];
-        let fahrenheit = self
-            .to_fahrenheit(data["temperature"].as_f64().expect("missing temperature"))
-            .unwrap();
-        let humidity = data["humidity"].as_f64().expect("missing humidity");
-        let vpd = self.get_vpd(fahrenheit, humidity).unwrap();
-        let sensor: Sensor = match self.sensors.get(device_id) {
-            Some(s) => {
+        println!("{:?}", report);
+
+        let time_us = report.time * 1000;
+        let units = self.intake.units();
+        let device_id = report.device_id.as_str();
+        let data = &report.data;
+        let lora_info = data.lora_info.clone().unwrap_or_default();
+        let registration = match self.registration(device_id) {
+            Some(r) => {
                 print!("Received data for sensor id {}", device_id);
-                s.clone()
-            },
+                r
+            }
             None => {
                 print!("Unknown sensor id {} -- ignoring data", device_id);
                 return Ok(());
             }
         };
 
-        let mut buffer = Buffer::new();
-        let _ = buffer
-            .table("yolink")?
-            .symbol("sensorName", sensor.name)?
-            .symbol("deviceId", device_id)?
-            .symbol("gatewayId", data["loraInfo"]["gatewayId"].as_str().unwrap())?
-            .symbol("netId", data["loraInfo"]["netId"].as_str().unwrap())?
-            .symbol("mode", data["mode"].as_str().unwrap())?
-            .symbol("state", data["state"].as_str().unwrap())?
-            .column_f64("lat", sensor.lat)?
-            .column_f64("long", sensor.long)?
-            .column_f64(
-                "temperature",
-                self.to_fahrenheit(data["temperature"].as_f64().unwrap())
-                    .unwrap(),
-            )?
-            .column_f64("humidity", data["temperature"].as_f64().unwrap())?
-            .column_f64("vpd", vpd)?
-            .column_i64("battery", data["battery"].as_i64().unwrap())?
-            .column_bool("lowBattery", data["alarm"]["lowBattery"].as_bool().unwrap())?
-            .column_i64("signal", data["loraInfo"]["signal"].as_i64().unwrap())?
-            .column_ts("time", TimestampMicros::new(time_ms))?
-            .at(TimestampNanos::now())
-            .unwrap();
-        let _ = self.db_appender.flush(&mut buffer).unwrap();
-
-        Ok(())
+        let mut observation = Observation::new("yolink", time_us);
+        observation
+            .symbol("sensorName", &registration.name)
+            .symbol("deviceId", device_id);
+        registration.append_symbols(&mut observation);
+        for (name, value) in [
+            ("gatewayId", &lora_info.gateway_id),
+            ("netId", &lora_info.net_id),
+            ("mode", &data.mode),
+            ("state", &data.state),
+        ] {
+            if let Some(value) = value {
+                observation.symbol(name, value);
+            }
+        }
+        observation.symbol("units", &units.label());
+        registration.append_location(&mut observation);
+        derived::append_climate(&mut observation, &units, data.temperature, data.humidity);
+        observation
+            .column_opt_i64("battery", data.battery)
+            .column_bool("lowBattery", data.alarm.low_battery)
+            .column_opt_i64("signal", lora_info.signal);
+
+        self.intake.write(&observation)
     }
 }
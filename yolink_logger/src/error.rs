@@ -0,0 +1,32 @@
+//!
+//! Errors surfaced while talking to the YoLink cloud
+//!
+
+use std::fmt;
+
+#[derive(Debug)]
+pub enum Error {
+    Http(reqwest::Error),
+    //
+    // the API answered, but not with what was asked for, e.g. an HTML error
+    // page or an error code in place of a token
+    //
+    Api(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Http(e) => write!(f, "http error: {}", e),
+            Error::Api(e) => write!(f, "api error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<reqwest::Error> for Error {
+    fn from(e: reqwest::Error) -> Self {
+        Error::Http(e)
+    }
+}
@@ -0,0 +1,58 @@
+//!
+//! YoLink temperature and humidity logger
+//!
+
+pub mod database;
+pub mod error;
+pub mod model;
+pub mod yolink;
+
+use error::Error;
+use std::sync::atomic::AtomicBool;
+use vineiq_core::units::Units;
+use vineiq_core::{Recorder, Sink};
+
+//
+// log the YoLink home until shutdown is set; an error means the API could
+// not be reached to get started
+//
+pub async fn run(
+    config: &mut yolink::Config,
+    sink: Box<dyn Sink + Send>,
+    recorder: Option<Recorder>,
+    shutdown: &AtomicBool,
+) -> Result<(), Error> {
+    let mut access = yolink::Access::new(
+        &config.get_token_url(),
+        &config.get_ua_id(),
+        &config.get_sec_id(),
+    )
+    .await?;
+
+    let mut yolink_api = yolink::Api::new(&config.get_api_url(), &access.token());
+    let device_list = yolink_api.get_all_devices().await?;
+
+    let home_id = yolink_api.get_home_id().await?;
+    let service_name = config.get_service_name();
+    let sensors = config.get_sensors();
+
+    let dead_letter = config.get_dead_letter().open("yolink");
+    let mut db_appender = database::Appender::new(sink, dead_letter, &sensors, config.get_strict());
+    db_appender.set_units(Units::from(&config.get_units()));
+    if let Some(recorder) = recorder {
+        db_appender.set_recorder(recorder);
+    }
+    db_appender.register_devices(device_list);
+    let mut database_logger = yolink::MqttDatabaseLogger::new(
+        &config.get_mqtt_broker(),
+        config.get_mqtt_port(),
+        &home_id,
+        &access.token(),
+        &service_name,
+    );
+    database_logger
+        .connect_to_broker(&mut db_appender, &mut access, &mut yolink_api, shutdown)
+        .await;
+
+    Ok(())
+}
@@ -0,0 +1,81 @@
+//!
+//! Typed YoLink MQTT events
+//!
+
+use serde_derive::Deserialize;
+
+#[derive(Deserialize, Debug, PartialEq)]
+#[serde(tag = "event")]
+pub enum Event {
+    #[serde(rename = "THSensor.Report")]
+    Report(Envelope<ThSensorData>),
+    #[serde(rename = "THSensor.Alert")]
+    Alert(Envelope<ThSensorData>),
+    #[serde(other)]
+    Other,
+}
+
+/*
+  {
+    "data": { ... },
+    "deviceId": "d88b4c010008b987",
+    "event": "THSensor.Report",
+    "msgid": "1712517507810",
+    "time": 1712517507811
+  }
+*/
+#[derive(Deserialize, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Envelope<T> {
+    pub device_id: String,
+    pub msgid: Option<String>,
+    //
+    // milliseconds since the epoch
+    //
+    pub time: i64,
+    pub data: T,
+}
+
+//
+// a report carries every field; an alert may leave out the reading and the
+// radio information, so everything past the alarm flags is optional
+//
+#[derive(Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ThSensorData {
+    #[serde(default)]
+    pub alarm: Alarm,
+    pub battery: Option<i64>,
+    pub battery_type: Option<String>,
+    //
+    // celsius, whatever the mode shown in the app
+    //
+    pub temperature: Option<f64>,
+    pub humidity: Option<f64>,
+    pub mode: Option<String>,
+    pub state: Option<String>,
+    pub interval: Option<i64>,
+    pub version: Option<String>,
+    pub lora_info: Option<LoraInfo>,
+}
+
+#[derive(Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(default, rename_all = "camelCase")]
+pub struct Alarm {
+    pub code: i64,
+    pub high_humidity: bool,
+    pub high_temp: bool,
+    pub low_battery: bool,
+    pub low_humidity: bool,
+    pub low_temp: bool,
+    pub period: bool,
+}
+
+#[derive(Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct LoraInfo {
+    pub gateway_id: Option<String>,
+    pub gateways: Option<i64>,
+    pub net_id: Option<String>,
+    pub signal: Option<i64>,
+}
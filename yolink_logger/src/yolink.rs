@@ -1,57 +1,93 @@
 //!
-//!
-//!
+//! YoLink API client and MQTT event logger
 //!
 
 use crate::database::Appender;
-use reqwest::Error;
-use rumqttc::{AsyncClient, Event, MqttOptions, Packet, QoS};
+use crate::error::Error;
+use rumqttc::{AsyncClient, Event, EventLoop, MqttOptions, Outgoing, Packet, QoS};
 use serde_derive::{Deserialize, Serialize};
 use serde_json::json;
 use serde_json::Value;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::time::Duration;
 use std::time::UNIX_EPOCH;
+use tokio::time::Instant;
+use vineiq_core::config::{BatchConfig, DeadLetterConfig, SpoolConfig};
+use vineiq_core::shutdown;
+use vineiq_core::units::UnitsConfig;
+
+const MIN_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(300);
+const REFRESH_MARGIN: Duration = Duration::from_secs(600);
+const REFRESH_RETRY: Duration = Duration::from_secs(60);
+const DISCOVERY_INTERVAL: Duration = Duration::from_secs(300);
+const TICK_INTERVAL: Duration = Duration::from_secs(1);
+const CLOSE_TIMEOUT: Duration = Duration::from_secs(2);
+const SWITCH_TIMEOUT: Duration = Duration::from_secs(30);
 
-#[derive(Serialize, Deserialize, PartialEq, Debug)]
+#[derive(Serialize, Deserialize, PartialEq, Clone, Debug)]
 struct Service {
     name: String,
 }
 
-#[derive(Serialize, Deserialize, PartialEq, Debug)]
+#[derive(Serialize, Deserialize, PartialEq, Clone, Debug)]
 pub struct Yolink {
     token: String,
     api: String,
-    database: String,
+    //
+    // only needed when the logger runs on its own; the combined service
+    // writes through the top level questdb setting
+    //
+    database: Option<String>,
 }
 
-#[derive(Serialize, Deserialize, PartialEq, Debug)]
+#[derive(Serialize, Deserialize, PartialEq, Clone, Debug)]
 pub struct Mqtt {
     broker: String,
     port: u16,
 }
 
-#[derive(Serialize, Deserialize, PartialEq, Debug)]
+#[derive(Serialize, Deserialize, PartialEq, Clone, Debug)]
 pub struct Security {
     ua_id: String,
     sec_id: String,
 }
 
+//
+// devices are discovered through the API; a sensor entry only adds the
+// location and block metadata (and optionally overrides the device name)
+//
 #[derive(Serialize, Deserialize, PartialEq, Clone, Debug)]
 pub struct Sensor {
-    pub name: String,
     pub eui: String,
-    pub lat: f64,
-    pub long: f64,
+    pub name: Option<String>,
+    pub lat: Option<f64>,
+    pub long: Option<f64>,
+    pub block: Option<String>,
 }
 
-#[derive(Serialize, Deserialize, PartialEq, Debug)]
+#[derive(Serialize, Deserialize, PartialEq, Clone, Debug)]
 pub struct Config {
     service: Service,
     yolink: Yolink,
     mqtt: Mqtt,
     security: Security,
+    #[serde(default)]
+    spool: SpoolConfig,
+    #[serde(default)]
+    batch: BatchConfig,
+    #[serde(default)]
+    dead_letter: DeadLetterConfig,
+    #[serde(default)]
+    units: UnitsConfig,
+    #[serde(default)]
     sensors: Vec<Sensor>,
+    //
+    // only log the devices listed under sensors
+    //
+    #[serde(default)]
+    strict: bool,
 }
 
 impl Config {
@@ -73,7 +109,10 @@ impl Config {
         self.yolink.api.clone()
     }
     pub fn get_database_url(&mut self) -> String {
-        self.yolink.database.clone()
+        self.yolink
+            .database
+            .clone()
+            .expect("missing yolink database url")
     }
     pub fn get_mqtt_broker(&mut self) -> String {
         self.mqtt.broker.clone()
@@ -90,52 +129,140 @@ impl Config {
     pub fn get_sensors(&mut self) -> Vec<Sensor> {
         self.sensors.clone()
     }
+    pub fn get_spool(&mut self) -> SpoolConfig {
+        self.spool.clone()
+    }
+    pub fn get_batch(&mut self) -> BatchConfig {
+        self.batch.clone()
+    }
+    pub fn get_dead_letter(&mut self) -> DeadLetterConfig {
+        self.dead_letter.clone()
+    }
+    pub fn get_strict(&mut self) -> bool {
+        self.strict
+    }
+    pub fn get_units(&mut self) -> UnitsConfig {
+        self.units.clone()
+    }
+    pub fn set_units(&mut self, units: &UnitsConfig) {
+        self.units = units.clone();
+    }
 }
 
-pub struct Access {}
+pub struct Access {
+    url: String,
+    ua_id: String,
+    sec_id: String,
+    access_token: String,
+    refresh_token: Option<String>,
+    refresh_at: Instant,
+}
 
 impl Access {
-    pub async fn token(url: &String, ua_id: &String, sec_id: &String) -> Result<String, Error> {
+    pub async fn new(url: &str, ua_id: &str, sec_id: &str) -> Result<Self, Error> {
+        let mut access = Self {
+            url: url.to_string(),
+            ua_id: ua_id.to_string(),
+            sec_id: sec_id.to_string(),
+            access_token: String::new(),
+            refresh_token: None,
+            refresh_at: Instant::now(),
+        };
+        access.client_credentials().await?;
+        Ok(access)
+    }
+
+    pub fn token(&self) -> String {
+        self.access_token.clone()
+    }
+
+    pub fn refresh_at(&self) -> Instant {
+        self.refresh_at
+    }
+
+    //
+    // exchange the refresh token for a new access token, falling back to the
+    // client credentials when the refresh token has been rejected
+    //
+    pub async fn refresh(&mut self) -> Result<(), Error> {
+        if let Some(refresh_token) = self.refresh_token.clone() {
+            let request_body = format!(
+                "grant_type=refresh_token&client_id={}&refresh_token={}",
+                self.ua_id, refresh_token
+            );
+            let client = reqwest::Client::new();
+            let request = client
+                .post(&self.url)
+                .header("Content-Type", "application/x-www-form-urlencoded")
+                .body(request_body);
+            match self.request_token(request).await {
+                Ok(()) => return Ok(()),
+                Err(e) => println!("Error refreshing access token: {}", e),
+            }
+        }
+        self.client_credentials().await
+    }
+
+    async fn client_credentials(&mut self) -> Result<(), Error> {
         //print!("\nconnecting to https://api.yosmart.com/open/yolink/token");
         let client = reqwest::Client::new();
 
-        let request_body = format!("grant_type=client_credentials");
+        let request_body = "grant_type=client_credentials";
         let request = client
-            .post(url)
+            .post(&self.url)
             .header("Content-Type", "application/x-www-form-urlencoded")
-            .basic_auth(ua_id, Some(sec_id))
-            .body(request_body.to_string());
-
-        let response = request.send().await?;
-        let response_code = response.status().as_u16();
-        match response_code {
-            200 => 200,
-            _ => {
-                panic!("Error: HTTPs response code: {}", response_code);
-            }
-        };
+            .basic_auth(&self.ua_id, Some(&self.sec_id))
+            .body(request_body);
+
+        self.request_token(request).await
+    }
+
+    /*
+      {
+        "access_token": "...",
+        "token_type": "bearer",
+        "expires_in": 7200,
+        "refresh_token": "...",
+        "scope": ["create"]
+      }
+    */
+    async fn request_token(&mut self, request: reqwest::RequestBuilder) -> Result<(), Error> {
+        let response = request.send().await?.error_for_status()?;
         let json_response: String = response.text().await?;
-        let value = serde_json::from_str::<Value>(&json_response).unwrap();
-        //print!("\njson: {:?}", value);
+        let value = serde_json::from_str::<Value>(&json_response)
+            .map_err(|e| Error::Api(format!("unreadable token response: {}", e)))?;
 
-        let access_token = value["access_token"]
+        self.access_token = value["access_token"]
             .as_str()
-            .expect("missing access_token")
+            .ok_or_else(|| Error::Api(format!("no access_token in {}", value)))?
             .to_string();
-
-        print!("\naccess_token: {:?}", access_token);
-        Ok(access_token)
+        self.refresh_token = value["refresh_token"].as_str().map(|s| s.to_string());
+
+        //
+        // refresh well ahead of the expiry so the MQTT session never runs on
+        // a stale credential
+        //
+        let expires_in = Duration::from_secs(value["expires_in"].as_u64().unwrap_or(7200));
+        self.refresh_at = Instant::now() + expires_in - REFRESH_MARGIN.min(expires_in / 2);
+
+        println!(
+            "access_token: {:?} (expires in {}s)",
+            self.access_token,
+            expires_in.as_secs()
+        );
+        Ok(())
     }
 }
 
+#[allow(dead_code)]
 #[derive(Clone, Debug)]
 pub struct Device {
-    id: String,
-    eui: String,
-    model: String,
-    name: String,
-    token: String,
-    dtype: String,
+    pub id: String,
+    pub eui: String,
+    pub model: String,
+    pub name: String,
+    pub token: String,
+    pub dtype: String,
 }
 
 pub struct Api {
@@ -144,12 +271,16 @@ pub struct Api {
 }
 
 impl Api {
-    pub fn new(api_url: &String, access_token: &String) -> Self {
+    pub fn new(api_url: &str, access_token: &str) -> Self {
         Self {
-            url: api_url.clone(),
-            access_token: access_token.clone(),
+            url: api_url.to_string(),
+            access_token: access_token.to_string(),
         }
     }
+    pub fn set_access_token(&mut self, access_token: &str) {
+        self.access_token = access_token.to_string();
+    }
+
     pub async fn get_all_devices(&mut self) -> Result<HashMap<String, Device>, Error> {
         let epoch_ms = std::time::SystemTime::now()
             .duration_since(UNIX_EPOCH)
@@ -168,27 +299,19 @@ impl Api {
             .header("Authorization", "Bearer ".to_owned() + &self.access_token)
             .body(serde_json::to_string(&request_body).unwrap());
 
-        let response = request.send().await?;
-        let response_code = response.status().as_u16();
-        match response_code {
-            200 => 200,
-            _ => {
-                panic!("Error: failed to get the device list: {}", response_code);
-            }
-        };
-        let json_response: String = response.text().await?;
-        let json_object = serde_json::from_str::<Value>(&json_response).unwrap();
+        let json_object = api_response(request, "device list").await?;
 
         let mut device_list = HashMap::new();
         if let serde_json::Value::Array(devices) = &json_object["data"]["devices"] {
             for d in devices {
+                let field = |name: &str| d[name].as_str().unwrap_or_default().to_string();
                 let device = Device {
-                    id: d["deviceId"].to_string(),
-                    eui: d["deviceeui"].to_string(),
-                    model: d["modelName"].to_string(),
-                    name: d["name"].to_string(),
-                    token: d["token"].to_string(),
-                    dtype: d["type"].to_string(),
+                    id: field("deviceId"),
+                    eui: field("deviceeui"),
+                    model: field("modelName"),
+                    name: field("name"),
+                    token: field("token"),
+                    dtype: field("type"),
                 };
                 device_list.insert(device.id.clone(), device);
             }
@@ -215,108 +338,324 @@ impl Api {
             .header("Authorization", "Bearer ".to_owned() + &self.access_token)
             .body(serde_json::to_string(&request_body).unwrap());
 
-        let response = request.send().await?;
-        let response_code = response.status().as_u16();
-        match response_code {
-            200 => 200,
-            _ => {
-                panic!("Error: failed to get the home id: {}", response_code);
-            }
-        };
-        let json_response: String = response.text().await?;
-        let json_object = serde_json::from_str::<Value>(&json_response).unwrap();
-
-        if json_object["code"].as_str().expect("Error getting home id") != "000000" {
-            panic!("Error: return code for home id: {}", json_object["code"]);
+        let json_object = api_response(request, "home id").await?;
+        if json_object["code"].as_str() != Some("000000") {
+            return Err(Error::Api(format!(
+                "return code for home id: {}",
+                json_object["code"]
+            )));
         }
 
-        Ok(json_object["data"]["id"]
+        json_object["data"]["id"]
             .as_str()
-            .expect("response missing home id")
-            .to_string())
+            .map(str::to_string)
+            .ok_or_else(|| Error::Api("response missing home id".to_string()))
     }
 }
 
+//
+// the body of a successful API call; anything else is an error for the
+// caller to log and retry, e.g. a 401 with a token that has just expired
+//
+async fn api_response(request: reqwest::RequestBuilder, what: &str) -> Result<Value, Error> {
+    let response = request.send().await?;
+    let status = response.status();
+    if !status.is_success() {
+        return Err(Error::Api(format!(
+            "failed to get the {}: {}",
+            what,
+            status.as_u16()
+        )));
+    }
+    let json_response: String = response.text().await?;
+    serde_json::from_str::<Value>(&json_response)
+        .map_err(|e| Error::Api(format!("unreadable {} response: {}", what, e)))
+}
+
 pub struct MqttDatabaseLogger {
     broker: String,
     port: u16,
     topic: String,
     username: String,
-    servicename: String,    
+    servicename: String,
+    sessions: u32,
+    discovered_at: Option<Instant>,
 }
 
 impl MqttDatabaseLogger {
     pub fn new(
-        mqtt_broker: &String,
+        mqtt_broker: &str,
         mqtt_port: u16,
-        home_id: &String,
-        access_token: &String,
-        service_name: &String,
+        home_id: &str,
+        access_token: &str,
+        service_name: &str,
     ) -> Self {
-   
         Self {
-            broker: mqtt_broker.clone(),
+            broker: mqtt_broker.to_string(),
             port: mqtt_port,
             topic: format!("yl-home/{}/+/report", home_id),
-            username: access_token.clone(),
-            servicename: service_name.clone(),
+            username: access_token.to_string(),
+            servicename: service_name.to_string(),
+            sessions: 0,
+            discovered_at: None,
         }
     }
 
-    fn log_event(&mut self, db_appender: &mut Appender, message: &String) -> Result<(), Error> {
-        let json_object = serde_json::from_str::<Value>(&message).unwrap();
-        match json_object["event"].as_str() {
-            Some("THSensor.Report") => db_appender
-                .process_report(&json_object)
-                .expect("report error"),
-            Some("THSensor.Alert") => db_appender
-                .process_alert(&json_object)
-                .expect("alert error"),
-            _ => println!(
-                "Unknown event\n{}",
-                serde_json::to_string_pretty(&json_object).unwrap()
-            ),
+    async fn log_event(&mut self, db_appender: &mut Appender, api: &mut Api, message: &str) {
+        if let Ok(json_object) = serde_json::from_str::<Value>(message) {
+            if let Some(device_id) = json_object["deviceId"].as_str() {
+                self.discover_device(db_appender, api, device_id).await;
+            }
         }
-        Ok(())
+        db_appender.log_event(message);
     }
 
-    pub async fn connect_to_broker(&mut self, db_appender: &mut Appender) {
-        println!("\nconnecting to broker: {}:{}", self.broker, self.port);
+    //
+    // a device added in the YoLink app shows up on the broker before we know
+    // its name, so fetch the device list again (at most every few minutes)
+    //
+    async fn discover_device(
+        &mut self,
+        db_appender: &mut Appender,
+        api: &mut Api,
+        device_id: &str,
+    ) {
+        if db_appender.is_registered(device_id) || db_appender.is_strict() {
+            return;
+        }
+        if let Some(discovered_at) = self.discovered_at {
+            if discovered_at.elapsed() < DISCOVERY_INTERVAL {
+                return;
+            }
+        }
+        self.discovered_at = Some(Instant::now());
 
-        let mut mqttoptions = MqttOptions::new(
-            self.servicename.clone(),
-            self.broker.clone(),
-            self.port.try_into().unwrap(),
+        println!(
+            "Unknown device id {} -- refreshing the device list",
+            device_id
         );
+        match api.get_all_devices().await {
+            Ok(devices) => db_appender.register_devices(devices),
+            Err(e) => println!("Error acquiring the device list: {}", e),
+        }
+    }
+
+    //
+    // every session after the first gets a client id of its own, so that the
+    // broker keeps the old session open while the next one connects
+    //
+    fn new_session(&mut self) -> (AsyncClient, EventLoop) {
+        let client_id = match self.sessions {
+            0 => self.servicename.clone(),
+            n => format!("{}-{}", self.servicename, n),
+        };
+        self.sessions += 1;
+        let mut mqttoptions = MqttOptions::new(client_id, self.broker.clone(), self.port);
         mqttoptions.set_keep_alive(Duration::from_secs(20));
         mqttoptions.set_credentials(self.username.clone(), "".to_string());
 
-        let (client, mut eventloop) = AsyncClient::new(mqttoptions, 10);
-        client
-            .subscribe(self.topic.clone(), QoS::AtMostOnce)
-            .await
-            .unwrap();
-        println!("done");
+        AsyncClient::new(mqttoptions, 10)
+    }
+
+    //
+    // while two sessions are open the same report can arrive on both; it is
+    // logged once, by its msgid
+    //
+    async fn log_once(
+        &mut self,
+        seen: &mut HashSet<String>,
+        db_appender: &mut Appender,
+        api: &mut Api,
+        payload: &[u8],
+    ) {
+        let message = String::from_utf8_lossy(payload).to_string();
+        let msgid = serde_json::from_str::<Value>(&message)
+            .ok()
+            .and_then(|event| event["msgid"].as_str().map(str::to_string));
+        if let Some(msgid) = msgid {
+            if !seen.insert(msgid) {
+                return;
+            }
+        }
+        self.log_event(db_appender, api, &message).await;
+    }
+
+    //
+    // move onto a session authenticated with the current token without a
+    // gap: the new session connects and subscribes beside the old one, which
+    // is only disconnected, and drained, once the broker has acknowledged the
+    // subscription. False, with the old session kept, when the new one does
+    // not come up in time.
+    //
+    async fn switch_session(
+        &mut self,
+        client: &mut AsyncClient,
+        eventloop: &mut EventLoop,
+        db_appender: &mut Appender,
+        api: &mut Api,
+    ) -> bool {
+        let (new_client, mut new_eventloop) = self.new_session();
+        let mut seen = HashSet::new();
+        let mut old_up = true;
+        let deadline = Instant::now() + SWITCH_TIMEOUT;
+        loop {
+            tokio::select! {
+                notification = new_eventloop.poll() => match notification {
+                    Ok(Event::Incoming(Packet::ConnAck(_))) => {
+                        if let Err(e) = new_client.try_subscribe(self.topic.clone(), QoS::AtMostOnce) {
+                            println!("Error subscribing to {}: {}", self.topic, e);
+                            return false;
+                        }
+                    }
+                    Ok(Event::Incoming(Packet::SubAck(_))) => break,
+                    Ok(Event::Incoming(Packet::Publish(packet))) => {
+                        self.log_once(&mut seen, db_appender, api, &packet.payload).await;
+                    }
+                    Ok(_) => {}
+                    Err(e) => {
+                        println!("Error opening a session with the new token: {}", e);
+                        return false;
+                    }
+                },
+                notification = eventloop.poll(), if old_up => match notification {
+                    Ok(Event::Incoming(Packet::Publish(packet))) => {
+                        self.log_once(&mut seen, db_appender, api, &packet.payload).await;
+                    }
+                    Ok(_) => {}
+                    Err(e) => {
+                        println!("broker connection error on the old session: {}", e);
+                        old_up = false;
+                    }
+                },
+                _ = tokio::time::sleep_until(deadline) => {
+                    println!("Error opening a session with the new token: timed out");
+                    return false;
+                }
+            }
+        }
+        println!("subscribed to {} with the new token", self.topic);
+
+        let old_client = std::mem::replace(client, new_client);
+        let mut old_eventloop = std::mem::replace(eventloop, new_eventloop);
+        if !old_up || old_client.try_disconnect().is_err() {
+            return true;
+        }
+        let deadline = Instant::now() + CLOSE_TIMEOUT;
+        loop {
+            tokio::select! {
+                notification = eventloop.poll() => {
+                    if let Ok(Event::Incoming(Packet::Publish(packet))) = notification {
+                        self.log_once(&mut seen, db_appender, api, &packet.payload).await;
+                    }
+                }
+                notification = old_eventloop.poll() => match notification {
+                    Ok(Event::Incoming(Packet::Publish(packet))) => {
+                        self.log_once(&mut seen, db_appender, api, &packet.payload).await;
+                    }
+                    Ok(Event::Outgoing(Outgoing::Disconnect)) | Err(_) => return true,
+                    Ok(_) => {}
+                },
+                _ = tokio::time::sleep_until(deadline) => return true,
+            }
+        }
+    }
+
+    pub async fn connect_to_broker(
+        &mut self,
+        db_appender: &mut Appender,
+        access: &mut Access,
+        api: &mut Api,
+        shutdown: &AtomicBool,
+    ) {
+        println!("\nconnecting to broker: {}:{}", self.broker, self.port);
+
+        let (mut client, mut eventloop) = self.new_session();
+        let mut refresh_at = access.refresh_at();
+        let mut ticker = tokio::time::interval(TICK_INTERVAL);
 
+        //
+        // the event loop reconnects on the next poll after an error, so the
+        // loop only has to pace the attempts and renew the subscription
+        //
+        let mut backoff = MIN_BACKOFF;
         loop {
-            let notification = eventloop.poll().await;
-            match notification.unwrap() {
-                Event::Incoming(Packet::Publish(packet)) => {
+            let notification = tokio::select! {
+                notification = eventloop.poll() => notification,
+                _ = ticker.tick() => {
+                    if shutdown.load(Ordering::Relaxed) {
+                        let _ = client.try_disconnect();
+                        return;
+                    }
+                    db_appender.tick();
+                    continue;
+                }
+                _ = tokio::time::sleep_until(refresh_at) => {
+                    match access.refresh().await {
+                        Ok(()) => {
+                            //
+                            // the broker authenticates with the token as username,
+                            // so a new credential needs a new session
+                            //
+                            api.set_access_token(&access.token());
+                            self.username = access.token();
+                            println!("access token refreshed -- moving to a new session");
+                            if self.switch_session(&mut client, &mut eventloop, db_appender, api).await {
+                                refresh_at = access.refresh_at();
+                            } else {
+                                //
+                                // the old session stays up on the old credentials
+                                // until the next attempt
+                                //
+                                refresh_at = Instant::now() + REFRESH_RETRY;
+                            }
+                        }
+                        Err(e) => {
+                            println!(
+                                "Error refreshing access token: {} -- retrying in {}s",
+                                e,
+                                REFRESH_RETRY.as_secs()
+                            );
+                            refresh_at = Instant::now() + REFRESH_RETRY;
+                        }
+                    }
+                    continue;
+                }
+            };
+            match notification {
+                Ok(Event::Incoming(Packet::ConnAck(connack))) => {
+                    println!("connected to broker: {:?}", connack.code);
+                    backoff = MIN_BACKOFF;
+                    //
+                    // a clean session forgets the subscription on every reconnect
+                    //
+                    if let Err(e) = client.try_subscribe(self.topic.clone(), QoS::AtMostOnce) {
+                        println!("Error subscribing to {}: {}", self.topic, e);
+                    }
+                }
+                Ok(Event::Incoming(Packet::SubAck(_))) => {
+                    println!("subscribed to {}", self.topic);
+                }
+                Ok(Event::Incoming(Packet::Publish(packet))) => {
                     let message = String::from_utf8_lossy(&packet.payload).to_string();
-                    let _ = self
-                        .log_event(db_appender, &message)
-                        .expect("Error processing log event");
+                    self.log_event(db_appender, api, &message).await;
                 }
-                Event::Outgoing(_) => {
-                    //println!(".");
+                Ok(Event::Incoming(Packet::Disconnect)) => {
+                    println!("broker closed the connection");
                 }
-                _ => {
-                    //println!(".");
+                Ok(_) => {}
+                Err(e) => {
+                    println!(
+                        "broker connection error: {} -- reconnecting in {}s",
+                        e,
+                        backoff.as_secs()
+                    );
+                    if !shutdown::pause(backoff, shutdown, || db_appender.tick()).await {
+                        let _ = client.try_disconnect();
+                        return;
+                    }
+                    backoff = (backoff * 2).min(MAX_BACKOFF);
                 }
             }
         }
     }
-    pub fn status() {
-        unimplemented!();
-    }
 }
@@ -0,0 +1,80 @@
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpListener;
+use std::thread;
+use yolink_logger::error::Error;
+use yolink_logger::yolink::{Access, Api};
+
+//
+// answers every request with the same status and body
+//
+fn serve(status: &'static str, body: &'static str) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}/open/yolink", listener.local_addr().unwrap());
+    thread::spawn(move || {
+        for mut stream in listener.incoming().flatten() {
+            let mut reader = BufReader::new(&stream);
+            let mut length = 0;
+            loop {
+                let mut line = String::new();
+                if reader.read_line(&mut line).unwrap_or(0) == 0 || line == "\r\n" {
+                    break;
+                }
+                if let Some(value) = line.to_ascii_lowercase().strip_prefix("content-length:") {
+                    length = value.trim().parse().unwrap_or(0);
+                }
+            }
+            let mut request = vec![0; length];
+            let _ = reader.read_exact(&mut request);
+            let _ = write!(
+                stream,
+                "HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                status,
+                body.len(),
+                body
+            );
+        }
+    });
+    url
+}
+
+#[tokio::test]
+async fn an_error_page_for_a_token_is_an_error() {
+    let url = serve("200 OK", "<html>Service Unavailable</html>");
+    assert!(matches!(
+        Access::new(&url, "ua", "sec").await,
+        Err(Error::Api(_))
+    ));
+
+    let url = serve("200 OK", r#"{"code": "010104", "msg": "invalid client"}"#);
+    assert!(matches!(
+        Access::new(&url, "ua", "sec").await,
+        Err(Error::Api(_))
+    ));
+
+    let url = serve(
+        "200 OK",
+        r#"{"access_token": "t1", "expires_in": 7200, "refresh_token": "r1"}"#,
+    );
+    let access = Access::new(&url, "ua", "sec").await.unwrap();
+    assert_eq!(access.token(), "t1");
+}
+
+#[tokio::test]
+async fn a_rejected_device_list_is_an_error() {
+    let url = serve("401 Unauthorized", "");
+    let mut api = Api::new(&url, "expired");
+    assert!(matches!(api.get_all_devices().await, Err(Error::Api(_))));
+    assert!(matches!(api.get_home_id().await, Err(Error::Api(_))));
+
+    let url = serve("200 OK", "not json");
+    let mut api = Api::new(&url, "token");
+    assert!(matches!(api.get_all_devices().await, Err(Error::Api(_))));
+
+    let url = serve(
+        "200 OK",
+        r#"{"code": "000000", "data": {"devices": [{"deviceId": "d1", "name": "Row 4"}]}}"#,
+    );
+    let mut api = Api::new(&url, "token");
+    let devices = api.get_all_devices().await.unwrap();
+    assert_eq!(devices["d1"].name, "Row 4");
+}
@@ -0,0 +1,137 @@
+use std::time::Duration;
+
+use vineiq_core::testing::{IlpReceiver, Line, Value};
+use vineiq_core::{derived, units, DeadLetter};
+use yolink_logger::database::Appender;
+use yolink_logger::model::Event;
+use yolink_logger::yolink::Sensor;
+
+const DEVICE_ID: &str = "d88b4c010008b987";
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+fn parse(payload: &str) -> Event {
+    serde_json::from_str(payload).expect("payload should parse")
+}
+
+fn appender(receiver: &IlpReceiver, name: &str) -> Appender {
+    let sensors = vec![Sensor {
+        eui: DEVICE_ID.to_string(),
+        name: Some("north block, row 1".to_string()),
+        lat: Some(38.5),
+        long: Some(-122.8),
+        block: Some("A".to_string()),
+    }];
+    Appender::new(
+        Box::new(receiver.sink(name)),
+        DeadLetter::new(name, None),
+        &sensors,
+        false,
+    )
+}
+
+fn only_line(receiver: &IlpReceiver) -> Line {
+    let lines = receiver.wait_for(1, TIMEOUT);
+    assert_eq!(lines.len(), 1, "expected one row, got {:?}", lines);
+    lines.into_iter().next().unwrap()
+}
+
+fn pairs<T>(items: Vec<(&str, T)>) -> Vec<(String, T)> {
+    items
+        .into_iter()
+        .map(|(name, value)| (name.to_string(), value))
+        .collect()
+}
+
+#[test]
+fn report_over_ilp() {
+    let receiver = IlpReceiver::start();
+    let mut appender = appender(&receiver, "yolink-report");
+    let Event::Report(report) = parse(include_str!("payloads/th_sensor_report.json")) else {
+        panic!("expected THSensor.Report");
+    };
+    appender.process_report(&report).unwrap();
+
+    let line = only_line(&receiver);
+    assert_eq!(line.table, "yolink");
+    assert_eq!(
+        line.symbols,
+        pairs(vec![
+            ("sensorName", "north block, row 1".to_string()),
+            ("deviceId", DEVICE_ID.to_string()),
+            ("block", "A".to_string()),
+            ("gatewayId", "d88b4c1603046d08".to_string()),
+            ("netId", "010201".to_string()),
+            ("mode", "f".to_string()),
+            ("state", "normal".to_string()),
+            ("units", "F,m/s,mb,mm,km".to_string()),
+        ])
+    );
+    assert_eq!(
+        line.columns,
+        pairs(vec![
+            ("lat", Value::Float(38.5)),
+            ("long", Value::Float(-122.8)),
+            ("temperature", Value::Float(units::to_fahrenheit(18.4))),
+            ("humidity", Value::Float(32.5)),
+            ("vpd", Value::Float(derived::vpd(18.4, 32.5))),
+            (
+                "dew_point",
+                Value::Float(units::to_fahrenheit(
+                    derived::dew_point(18.4, 32.5).unwrap(),
+                )),
+            ),
+            (
+                "heat_index",
+                Value::Float(units::to_fahrenheit(derived::heat_index(18.4, 32.5))),
+            ),
+            (
+                "wet_bulb",
+                Value::Float(units::to_fahrenheit(derived::wet_bulb(18.4, 32.5))),
+            ),
+            ("battery", Value::Integer(4)),
+            ("lowBattery", Value::Boolean(false)),
+            ("signal", Value::Integer(-67)),
+            ("time", Value::Timestamp(1712517507811000)),
+        ])
+    );
+}
+
+#[test]
+fn alert_over_ilp() {
+    let receiver = IlpReceiver::start();
+    let mut appender = appender(&receiver, "yolink-alert");
+    let Event::Alert(alert) = parse(include_str!("payloads/th_sensor_alert.json")) else {
+        panic!("expected THSensor.Alert");
+    };
+    appender.process_alert(&alert).unwrap();
+
+    let line = only_line(&receiver);
+    assert_eq!(line.table, "yolink_alert");
+    assert_eq!(line.symbol("sensorName"), Some("north block, row 1"));
+    assert_eq!(line.symbol("state"), Some("alert"));
+    assert_eq!(line.column("code"), Some(&Value::Integer(1)));
+    assert_eq!(line.column("lowTemp"), Some(&Value::Boolean(true)));
+    assert_eq!(line.column("highTemp"), Some(&Value::Boolean(false)));
+    assert_eq!(
+        line.column("temperature"),
+        Some(&Value::Float(units::to_fahrenheit(0.8)))
+    );
+    assert_eq!(line.column("humidity"), Some(&Value::Float(71.5)));
+    assert_eq!(
+        line.column("time"),
+        Some(&Value::Timestamp(1712517507811000))
+    );
+}
+
+#[test]
+fn unknown_sensor_writes_nothing() {
+    let receiver = IlpReceiver::start();
+    let mut appender = appender(&receiver, "yolink-unknown");
+    let payload = include_str!("payloads/th_sensor_report.json").replace(DEVICE_ID, "ffff");
+    let Event::Report(report) = parse(&payload) else {
+        panic!("expected THSensor.Report");
+    };
+    appender.process_report(&report).unwrap();
+
+    assert!(receiver.wait_for(1, Duration::from_millis(200)).is_empty());
+}
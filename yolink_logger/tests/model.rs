@@ -0,0 +1,116 @@
+use vineiq_core::testing::CaptureSink;
+use vineiq_core::{DeadLetter, Field, Observation};
+use yolink_logger::database::Appender;
+use yolink_logger::model::Event;
+use yolink_logger::yolink::Sensor;
+
+const DEVICE_ID: &str = "d88b4c010008b987";
+
+fn parse(payload: &str) -> Event {
+    serde_json::from_str(payload).expect("payload should parse")
+}
+
+fn appender(capture: &CaptureSink) -> Appender {
+    let sensors = vec![Sensor {
+        eui: DEVICE_ID.to_string(),
+        name: Some("north-1".to_string()),
+        lat: Some(38.5),
+        long: Some(-122.8),
+        block: Some("A".to_string()),
+    }];
+    Appender::new(
+        Box::new(capture.clone()),
+        DeadLetter::new("yolink", None),
+        &sensors,
+        false,
+    )
+}
+
+fn column<'a>(row: &'a Observation, name: &str) -> Option<&'a Field> {
+    row.columns.iter().find(|(n, _)| n == name).map(|(_, v)| v)
+}
+
+#[test]
+fn report() {
+    let Event::Report(report) = parse(include_str!("payloads/th_sensor_report.json")) else {
+        panic!("expected THSensor.Report");
+    };
+    assert_eq!(report.device_id, DEVICE_ID);
+    assert_eq!(report.time, 1712517507811);
+    assert_eq!(report.data.temperature, Some(18.4));
+    assert_eq!(report.data.humidity, Some(32.5));
+    assert_eq!(report.data.battery, Some(4));
+    let lora_info = report.data.lora_info.unwrap();
+    assert_eq!(lora_info.signal, Some(-67));
+    assert_eq!(lora_info.gateway_id.as_deref(), Some("d88b4c1603046d08"));
+}
+
+#[test]
+fn alert() {
+    let Event::Alert(alert) = parse(include_str!("payloads/th_sensor_alert.json")) else {
+        panic!("expected THSensor.Alert");
+    };
+    assert!(alert.data.alarm.low_temp);
+    assert!(!alert.data.alarm.high_temp);
+    assert_eq!(alert.data.alarm.code, 1);
+    assert_eq!(alert.data.temperature, Some(0.8));
+}
+
+#[test]
+fn alert_without_reading() {
+    let Event::Alert(alert) = parse(include_str!("payloads/th_sensor_alert_minimal.json")) else {
+        panic!("expected THSensor.Alert");
+    };
+    assert!(alert.data.alarm.low_battery);
+    assert!(!alert.data.alarm.period);
+    assert_eq!(alert.data.temperature, None);
+    assert_eq!(alert.data.lora_info, None);
+}
+
+#[test]
+fn other_events() {
+    assert_eq!(
+        parse(include_str!("payloads/th_sensor_set_alarm.json")),
+        Event::Other
+    );
+}
+
+#[test]
+fn report_row_writes_humidity() {
+    let capture = CaptureSink::default();
+    let mut appender = appender(&capture);
+    let Event::Report(report) = parse(include_str!("payloads/th_sensor_report.json")) else {
+        panic!("expected THSensor.Report");
+    };
+    appender.process_report(&report).unwrap();
+
+    let rows = capture.rows();
+    let row = &rows[0];
+    assert_eq!(row.table, "yolink");
+    assert_eq!(row.time, 1712517507811000);
+    assert_eq!(row.get_symbol("sensorName"), Some("north-1"));
+    assert_eq!(row.get_symbol("block"), Some("A"));
+    assert_eq!(row.get_symbol("netId"), Some("010201"));
+    assert_eq!(row.get_f64("humidity"), Some(32.5));
+    assert!((row.get_f64("temperature").unwrap() - 65.12).abs() < 1e-9);
+    assert_eq!(column(row, "signal"), Some(&Field::I64(-67)));
+    assert_eq!(column(row, "lowBattery"), Some(&Field::Bool(false)));
+}
+
+#[test]
+fn alert_row_leaves_out_missing_reading() {
+    let capture = CaptureSink::default();
+    let mut appender = appender(&capture);
+    let Event::Alert(alert) = parse(include_str!("payloads/th_sensor_alert_minimal.json")) else {
+        panic!("expected THSensor.Alert");
+    };
+    appender.process_alert(&alert).unwrap();
+
+    let rows = capture.rows();
+    let row = &rows[0];
+    assert_eq!(row.table, "yolink_alert");
+    assert_eq!(column(row, "lowBattery"), Some(&Field::Bool(true)));
+    assert_eq!(column(row, "code"), Some(&Field::I64(4)));
+    assert_eq!(column(row, "temperature"), None);
+    assert_eq!(column(row, "battery"), None);
+}